@@ -1,3 +1,7 @@
 pub mod expr;
 pub mod stmt;
-pub mod ast_pretty_print;
\ No newline at end of file
+pub mod ast_pretty_print;
+pub mod ast_dot;
+pub mod walk;
+pub mod const_fold;
+pub mod ast_diff;
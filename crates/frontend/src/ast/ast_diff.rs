@@ -0,0 +1,146 @@
+use super::expr::{escape_str, format_real, Expr, IfExprBranch};
+
+// First structural difference between two expressions, locations aside.
+// Describes where it is, from the root, and both nodes found there. For
+// tests of the AST transformations
+pub fn ast_diff(before: &Expr, after: &Expr) -> Option<String> {
+    diff_at("expr".into(), before, after)
+}
+
+fn diff_at(path: String, before: &Expr, after: &Expr) -> Option<String> {
+    let (desc_before, desc_after) = (describe(before), describe(after));
+
+    if desc_before != desc_after {
+        return Some(format!(
+            "{}: `{}` ({}) became `{}` ({})",
+            path, before, desc_before, after, desc_after
+        ));
+    }
+
+    // Same kind of node with the same attributes, so the same children
+    children(before)
+        .into_iter()
+        .zip(children(after))
+        .find_map(|((name, b), (_, a))| diff_at(format!("{}.{}", path, name), b, a))
+}
+
+// Kind of the node and what it holds, besides its children
+fn describe(expr: &Expr) -> String {
+    let attr = match expr {
+        Expr::Binary(e) => format!("'{}'", e.operator),
+        Expr::Logical(e) => format!("'{}'", e.operator),
+        Expr::Unary(e) => format!("'{}'", e.operator),
+        Expr::IntLiteral(e) => e.value.to_string(),
+        Expr::RealLiteral(e) => format_real(e.value),
+        Expr::StrLiteral(e) => format!("\"{}\"", escape_str(&e.value)),
+        Expr::BoolLiteral(e) => e.value.to_string(),
+        Expr::Identifier(e) => e.name.to_string(),
+        Expr::Assign(e) => e.name.to_string(),
+        Expr::Call(e) => format!("{} args", e.args.len()),
+        Expr::TypeCheck(e) => e.type_name.to_string(),
+        Expr::Get(e) => format!("{}{}", if e.optional { "?." } else { "." }, e.name),
+        Expr::Slice(e) => e.range_operator().to_string(),
+        // Statements before the values aren't expressions, they are
+        // compared as a whole
+        Expr::If(e) if e.then_branch.stmts.is_empty() && e.else_branch.stmts.is_empty() => String::new(),
+        Expr::If(e) => {
+            let stmts = |b: &IfExprBranch| b.stmts.iter().map(|s| format!("{}; ", s)).collect::<String>();
+            format!("{{ {}}} else {{ {}}}", stmts(&e.then_branch), stmts(&e.else_branch))
+        }
+        Expr::Grouping(_) | Expr::NullLiteral(_) | Expr::Index(_) => String::new(),
+    };
+
+    match attr.is_empty() {
+        true => expr.kind_name().to_string(),
+        false => format!("{} {}", expr.kind_name(), attr),
+    }
+}
+
+fn children(expr: &Expr) -> Vec<(String, &Expr)> {
+    fn named<'a>(name: &str, e: &'a Expr) -> (String, &'a Expr) {
+        (name.to_string(), e)
+    }
+
+    match expr {
+        Expr::Binary(e) => vec![named("left", &e.left), named("right", &e.right)],
+        Expr::Logical(e) => vec![named("left", &e.left), named("right", &e.right)],
+        Expr::Grouping(e) => vec![named("expr", &e.expr)],
+        Expr::Unary(e) => vec![named("right", &e.right)],
+        Expr::Assign(e) => vec![named("value", &e.value)],
+        Expr::Call(e) => std::iter::once(named("callee", &e.callee))
+            .chain(e.args.iter().enumerate().map(|(i, a)| (format!("args[{}]", i), a)))
+            .collect(),
+        Expr::TypeCheck(e) => vec![named("value", &e.value)],
+        Expr::Get(e) => vec![named("object", &e.object)],
+        Expr::Index(e) => vec![named("object", &e.object), named("index", &e.index)],
+        Expr::Slice(e) => vec![
+            named("object", &e.object),
+            named("start", &e.start),
+            named("end", &e.end),
+        ],
+        Expr::If(e) => vec![
+            named("condition", &e.condition),
+            named("then", &e.then_branch.value),
+            named("else", &e.else_branch.value),
+        ],
+        Expr::IntLiteral(_)
+        | Expr::RealLiteral(_)
+        | Expr::StrLiteral(_)
+        | Expr::BoolLiteral(_)
+        | Expr::NullLiteral(_)
+        | Expr::Identifier(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ast_diff;
+    use crate::ast::const_fold::fold_expr;
+    use crate::parser::parse_expression;
+
+    #[test]
+    fn diff_folding() {
+        let before = parse_expression("1 + 2").unwrap();
+        let after = fold_expr(before.clone());
+
+        assert_eq!(
+            ast_diff(&before, &after).unwrap(),
+            "expr: `1 + 2` (binary '+') became `3` (int 3)"
+        );
+
+        // Located from the root, only the first difference
+        let before = parse_expression("f(a, -(2 * 3), b.c + 1)").unwrap();
+        let after = fold_expr(before.clone());
+        assert_eq!(
+            ast_diff(&before, &after).unwrap(),
+            "expr.args[1]: `-(2 * 3)` (unary '-') became `-6` (int -6)"
+        );
+
+        let before = parse_expression("x[i..n]").unwrap();
+        let after = parse_expression("x[i..=n]").unwrap();
+        assert_eq!(
+            ast_diff(&before, &after).unwrap(),
+            "expr: `x[i..n]` (slice ..) became `x[i..=n]` (slice ..=)"
+        );
+    }
+
+    #[test]
+    fn no_diff() {
+        // Locations are ignored
+        let before = parse_expression("a   +  f(1)").unwrap();
+        let after = parse_expression("a + f(1)").unwrap();
+        assert_eq!(ast_diff(&before, &after), None);
+
+        // Not folded, so unchanged
+        let before = parse_expression("a + 1 + 2").unwrap();
+        assert_eq!(ast_diff(&before, &fold_expr(before.clone())), None);
+
+        // Groupings are part of the structure
+        let before = parse_expression("(a) + b").unwrap();
+        let after = parse_expression("a + b").unwrap();
+        assert_eq!(
+            ast_diff(&before, &after).unwrap(),
+            "expr.left: `a` (grouping) became `a` (identifier a)"
+        );
+    }
+}
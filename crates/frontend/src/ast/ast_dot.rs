@@ -0,0 +1,295 @@
+use tools::results::{PhyReport, PhyResult};
+
+use super::expr::{
+    format_real, AssignExpr, BinaryExpr, BoolLiteralExpr, NullLiteralExpr, CallExpr, Expr, GetExpr, GroupingExpr, IdentifierExpr, IfExpr, IfExprBranch, IndexExpr,
+    IntLiteralExpr, LogicalExpr, RealLiteralExpr, SliceExpr, StrLiteralExpr, TypeCheckExpr, UnaryExpr,
+    VisitExpr,
+};
+use super::stmt::{
+    BlockStmt, EnumDeclStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ImportStmt, PrintStmt,
+    ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileLetStmt, WhileStmt,
+};
+
+#[derive(Debug)]
+pub enum AstDotErr {}
+
+type PhyResAstDot = PhyResult<AstDotErr>;
+
+impl PhyReport for AstDotErr {
+    fn get_err_msg(&self) -> String {
+        String::from("")
+    }
+}
+
+// Renders the AST as a Graphviz graph, to be used with 'dot -Tpng'
+pub fn ast_to_dot(stmts: &[Stmt]) -> String {
+    let mut printer = AstDot::default();
+    let root = printer.node("Program");
+
+    for stmt in stmts {
+        // The visitor can't fail, its error type has no variant
+        let Ok(id) = stmt.accept(&mut printer);
+        printer.edge(root, id);
+    }
+
+    let mut dot = String::from("digraph ast {\n");
+    for line in &printer.lines {
+        dot.push_str("    ");
+        dot.push_str(line);
+        dot.push('\n');
+    }
+    dot.push('}');
+
+    dot
+}
+
+// Each visit returns the id of the node it created
+#[derive(Default)]
+struct AstDot {
+    lines: Vec<String>,
+    next_id: usize,
+}
+
+impl AstDot {
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.lines.push(format!("n{} [label=\"{}\"];", id, label));
+
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.lines.push(format!("n{} -> n{};", from, to));
+    }
+
+    fn node_with_exprs(&mut self, label: &str, exprs: &[&Expr]) -> Result<usize, PhyResAstDot> {
+        let id = self.node(label);
+
+        for expr in exprs {
+            let child = expr.accept(self)?;
+            self.edge(id, child);
+        }
+
+        Ok(id)
+    }
+
+    fn node_with_stmts(&mut self, label: &str, stmts: &[Stmt]) -> Result<usize, PhyResAstDot> {
+        let id = self.node(label);
+
+        for stmt in stmts {
+            let child = stmt.accept(self)?;
+            self.edge(id, child);
+        }
+
+        Ok(id)
+    }
+
+    // Only the value if there is no statement before it
+    fn branch(&mut self, branch: &IfExprBranch) -> Result<usize, PhyResAstDot> {
+        if branch.stmts.is_empty() {
+            return branch.value.accept(self);
+        }
+
+        let id = self.node_with_stmts("Branch", &branch.stmts)?;
+        let value = branch.value.accept(self)?;
+        self.edge(id, value);
+
+        Ok(id)
+    }
+}
+
+impl VisitStmt<usize, AstDotErr> for AstDot {
+    fn visit_expr_stmt(&mut self, stmt: &ExprStmt) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs("ExprStmt", &[&stmt.expr])
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs("Print", &[&stmt.expr])
+    }
+
+    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> Result<usize, PhyResAstDot> {
+        let label = format!("VarDecl {}", stmt.name);
+
+        match &stmt.value {
+            Some(v) => self.node_with_exprs(&label, &[v]),
+            None => Ok(self.node(&label)),
+        }
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Result<usize, PhyResAstDot> {
+        self.node_with_stmts("Block", &stmt.stmts)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<usize, PhyResAstDot> {
+        let id = self.node_with_exprs("If", &[&stmt.condition])?;
+
+        for branch in [&stmt.then_branch, &stmt.else_branch].into_iter().flatten() {
+            let child = branch.accept(self)?;
+            self.edge(id, child);
+        }
+
+        Ok(id)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Result<usize, PhyResAstDot> {
+        let id = self.node_with_exprs("While", &[&stmt.condition])?;
+        let body = stmt.body.accept(self)?;
+        self.edge(id, body);
+
+        Ok(id)
+    }
+
+    fn visit_while_let_stmt(&mut self, stmt: &WhileLetStmt) -> Result<usize, PhyResAstDot> {
+        let id = self.node_with_exprs(&format!("WhileLet {}", stmt.name), &[&stmt.value])?;
+        let body = stmt.body.accept(self)?;
+        self.edge(id, body);
+
+        Ok(id)
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Result<usize, PhyResAstDot> {
+        let range = match stmt.range.end {
+            Some(end) => format!("{}..{}", stmt.range.start, end),
+            None => format!("{}", stmt.range.start),
+        };
+        let id = self.node(&format!("For {} in {}", stmt.placeholder.name, range));
+        let body = stmt.body.accept(self)?;
+        self.edge(id, body);
+
+        Ok(id)
+    }
+
+    fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> Result<usize, PhyResAstDot> {
+        let label = format!("FnDecl {}({})", stmt.name, stmt.params.join(", "));
+        self.node_with_stmts(&label, &stmt.body)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<usize, PhyResAstDot> {
+        match &stmt.value {
+            Some(v) => self.node_with_exprs("Return", &[v]),
+            None => Ok(self.node("Return")),
+        }
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<usize, PhyResAstDot> {
+        Ok(self.node(&format!("Import \"{}\"", stmt.path)))
+    }
+
+    fn visit_enum_decl_stmt(&mut self, stmt: &EnumDeclStmt) -> Result<usize, PhyResAstDot> {
+        Ok(self.node(&format!("Enum {} {{ {} }}", stmt.name, stmt.variants.join(", "))))
+    }
+}
+
+impl VisitExpr<usize, AstDotErr> for AstDot {
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs(&format!("Binary {}", expr.operator), &[&expr.left, &expr.right])
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs("Grouping", &[&expr.expr])
+    }
+
+    fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> Result<usize, PhyResAstDot> {
+        Ok(self.node(&format!("Int {}", expr.value)))
+    }
+
+    fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> Result<usize, PhyResAstDot> {
+        Ok(self.node(&format!("Real {}", format_real(expr.value))))
+    }
+
+    fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> Result<usize, PhyResAstDot> {
+        Ok(self.node(&format!("Str \"{}\"", expr.value)))
+    }
+
+    fn visit_bool_literal_expr(&mut self, expr: &BoolLiteralExpr) -> Result<usize, PhyResAstDot> {
+        Ok(self.node(&format!("Bool {}", expr.value)))
+    }
+
+    fn visit_null_literal_expr(&mut self, _: &NullLiteralExpr) -> Result<usize, PhyResAstDot> {
+        Ok(self.node("Null"))
+    }
+
+    fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> Result<usize, PhyResAstDot> {
+        Ok(self.node(&format!("Identifier {}", expr.name)))
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs(&format!("Unary {}", expr.operator), &[&expr.right])
+    }
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs(&format!("Assign {}", expr.name), &[&expr.value])
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs(&format!("Logical {}", expr.operator), &[&expr.left, &expr.right])
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> Result<usize, PhyResAstDot> {
+        let id = self.node_with_exprs("Call", &[&expr.callee])?;
+
+        for arg in &expr.args {
+            let child = arg.accept(self)?;
+            self.edge(id, child);
+        }
+
+        Ok(id)
+    }
+
+    fn visit_type_check_expr(&mut self, expr: &TypeCheckExpr) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs(&format!("Is {}", expr.type_name), &[&expr.value])
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<usize, PhyResAstDot> {
+        let op = if expr.optional { "?." } else { "." };
+        self.node_with_exprs(&format!("Get {}{}", op, expr.name), &[&expr.object])
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<usize, PhyResAstDot> {
+        self.node_with_exprs("Index", &[&expr.object, &expr.index])
+    }
+
+    fn visit_slice_expr(&mut self, expr: &SliceExpr) -> Result<usize, PhyResAstDot> {
+        let label = format!("Slice {}", expr.range_operator());
+        self.node_with_exprs(&label, &[&expr.object, &expr.start, &expr.end])
+    }
+
+    fn visit_if_expr(&mut self, expr: &IfExpr) -> Result<usize, PhyResAstDot> {
+        let id = self.node_with_exprs("IfExpr", &[&expr.condition])?;
+
+        for branch in [&expr.then_branch, &expr.else_branch] {
+            let child = self.branch(branch)?;
+            self.edge(id, child);
+        }
+
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ast_to_dot;
+    use crate::parser::utils::lex_and_parse;
+
+    #[test]
+    fn binop_to_dot() {
+        let nodes = lex_and_parse("1 + 2").unwrap();
+        let dot = ast_to_dot(&nodes);
+
+        assert!(dot.starts_with("digraph ast {\n"));
+        assert!(dot.ends_with('}'));
+        assert!(dot.contains("n0 [label=\"Program\"];"));
+        assert!(dot.contains("n1 [label=\"ExprStmt\"];"));
+        assert!(dot.contains("n2 [label=\"Binary +\"];"));
+        assert!(dot.contains("n3 [label=\"Int 1\"];"));
+        assert!(dot.contains("n4 [label=\"Int 2\"];"));
+        assert!(dot.contains("n2 -> n3;"));
+        assert!(dot.contains("n2 -> n4;"));
+        assert!(dot.contains("n1 -> n2;"));
+        assert!(dot.contains("n0 -> n1;"));
+    }
+
+}
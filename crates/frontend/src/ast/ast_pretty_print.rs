@@ -1,10 +1,10 @@
 use tools::results::{PhyReport, PhyResult};
 
 use super::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr, LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr
+    AssignExpr, BinaryExpr, CallExpr, Expr, GetExpr, GroupingExpr, IfExpr, IfExprBranch, IdentifierExpr, IndexExpr, format_real, IntLiteralExpr, LogicalExpr, BoolLiteralExpr, NullLiteralExpr, RealLiteralExpr, SliceExpr, StrLiteralExpr, TypeCheckExpr, UnaryExpr, VisitExpr
 };
 
-use super::stmt::{BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileStmt};
+use super::stmt::{BlockStmt, EnumDeclStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ImportStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileLetStmt, WhileStmt};
 
 #[derive(Debug)]
 pub enum AstPrinterErr {}
@@ -36,6 +36,21 @@ impl AstPrinter {
 
         Ok(final_str)
     }
+
+    // Only the value if there is no statement before it
+    fn branch(&mut self, branch: &IfExprBranch) -> Result<String, PhyResAstPrint> {
+        let mut final_str = String::from("(block");
+
+        for stmt in branch.stmts.iter() {
+            final_str.push(' ');
+            final_str.push_str(stmt.accept(self)?.as_str());
+        }
+
+        match branch.stmts.is_empty() {
+            true => branch.value.accept(self),
+            false => Ok(format!("{} {})", final_str, branch.value.accept(self)?)),
+        }
+    }
 }
 
 impl VisitStmt<String, AstPrinterErr> for AstPrinter {
@@ -65,6 +80,12 @@ impl VisitStmt<String, AstPrinterErr> for AstPrinter {
         todo!()
     }
 
+    fn visit_while_let_stmt(&mut self, stmt: &WhileLetStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        let value = stmt.value.accept(self)?;
+        let while_str = format!("while var {} = {} {}", stmt.name, value, stmt.body.accept(self)?);
+        self.parenthesize(&while_str, &[])
+    }
+
     fn visit_for_stmt(&mut self, _stmt: &ForStmt) -> Result<String, PhyResult<AstPrinterErr>> {
         todo!()
     }
@@ -76,6 +97,14 @@ impl VisitStmt<String, AstPrinterErr> for AstPrinter {
     fn visit_return_stmt(&mut self, _stmt: &ReturnStmt) -> Result<String, PhyResult<AstPrinterErr>> {
         todo!()
     }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize(&format!("import \"{}\"", stmt.path), &[])
+    }
+
+    fn visit_enum_decl_stmt(&mut self, stmt: &EnumDeclStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize(&format!("enum {} {}", stmt.name, stmt.variants.join(" ")), &[])
+    }
 }
 
 impl VisitExpr<String, AstPrinterErr> for AstPrinter {
@@ -92,13 +121,21 @@ impl VisitExpr<String, AstPrinterErr> for AstPrinter {
     }
 
     fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> Result<String, PhyResAstPrint> {
-        Ok(format!("{}", expr.value))
+        Ok(format_real(expr.value))
     }
 
     fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> Result<String, PhyResAstPrint> {
         Ok(format!("\"{}\"", expr.value))
     }
 
+    fn visit_bool_literal_expr(&mut self, expr: &BoolLiteralExpr) -> Result<String, PhyResAstPrint> {
+        Ok(expr.value.to_string())
+    }
+
+    fn visit_null_literal_expr(&mut self, _: &NullLiteralExpr) -> Result<String, PhyResAstPrint> {
+        Ok("null".into())
+    }
+
     fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> Result<String, PhyResAstPrint> {
         Ok(expr.name.to_string())
     }
@@ -119,4 +156,30 @@ impl VisitExpr<String, AstPrinterErr> for AstPrinter {
     fn visit_call_expr(&mut self, _expr: &CallExpr) -> Result<String, PhyResult<AstPrinterErr>> {
         todo!()
     }
+
+    fn visit_type_check_expr(&mut self, expr: &TypeCheckExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize(&format!("is {}", expr.type_name), &[&expr.value])
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        let op = if expr.optional { "?." } else { "." };
+        self.parenthesize(&format!("{}{}", op, expr.name), &[&expr.object])
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize("[]", &[&expr.object, &expr.index])
+    }
+
+    fn visit_slice_expr(&mut self, expr: &SliceExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        let name = format!("[{}]", expr.range_operator());
+        self.parenthesize(&name, &[&expr.object, &expr.start, &expr.end])
+    }
+
+    fn visit_if_expr(&mut self, expr: &IfExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        let condition = expr.condition.accept(self)?;
+        let then_branch = self.branch(&expr.then_branch)?;
+        let else_branch = self.branch(&expr.else_branch)?;
+
+        Ok(format!("(if {} {} {})", condition, then_branch, else_branch))
+    }
 }
@@ -0,0 +1,519 @@
+use std::rc::Rc;
+
+use tools::results::Loc;
+
+use super::expr::{
+    AssignExpr, BinaryExpr, BoolLiteralExpr, CallExpr, Expr, GetExpr, GroupingExpr, IfExpr, IfExprBranch, IndexExpr,
+    IntLiteralExpr, LogicalExpr, RealLiteralExpr, SliceExpr, TypeCheckExpr, UnaryExpr,
+};
+use super::stmt::{
+    BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt,
+    WhileLetStmt, WhileStmt,
+};
+use super::walk::{walk_exprs, walk_stmts};
+
+// Evaluates the expressions made of literals only, before running the
+// code. An operation that would fail at runtime (overflow, division by
+// zero) is kept as is, so that its error is still reported when it runs
+pub fn fold_constants(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    ConstFolder::default().fold(stmts)
+}
+
+pub fn fold_expr(expr: Expr) -> Expr {
+    ConstFolder::default().fold_expr(expr)
+}
+
+#[derive(Debug, Default)]
+pub struct ConstFolder {
+    // Calls to pure natives with literal arguments, like len("abc"), are
+    // folded too. Only the code is checked for a replacement of the
+    // native: one made by the host, with 'register_native' or through
+    // the environment of a session, isn't seen
+    pub fold_natives: bool,
+}
+
+impl ConstFolder {
+    pub fn fold(&self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+        // An imported file could replace any native
+        if !self.fold_natives || walk_stmts(&stmts).any(|s| matches!(s, Stmt::Import(_))) {
+            return fold_stmts(stmts, &[]);
+        }
+
+        // A call to a native can only be folded if the program never reuses
+        // its name: locals can be declared with it and globals reassigned
+        let natives: Vec<&str> = PURE_NATIVES
+            .iter()
+            .copied()
+            .filter(|n| !walk_stmts(&stmts).any(|s| declares(s, n)))
+            .filter(|n| !walk_exprs(&stmts).any(|e| assigns(e, n)))
+            .collect();
+
+        fold_stmts(stmts, &natives)
+    }
+
+    // A lone expression doesn't tell whether the rest of the program
+    // replaces a native, they are assumed not to be
+    pub fn fold_expr(&self, expr: Expr) -> Expr {
+        match self.fold_natives {
+            true => fold_in(expr, PURE_NATIVES),
+            false => fold_in(expr, &[]),
+        }
+    }
+}
+
+// Natives without side effect, whose result only depends on the arguments
+const PURE_NATIVES: &[&str] = &["len"];
+
+fn fold_stmts(stmts: Vec<Stmt>, natives: &[&str]) -> Vec<Stmt> {
+    stmts.into_iter().map(|s| fold_stmt(s, natives)).collect()
+}
+
+fn fold_in(expr: Expr, natives: &[&str]) -> Expr {
+    match expr {
+        Expr::Binary(e) => {
+            let left = fold_in(*e.left, natives);
+            let right = fold_in(*e.right, natives);
+
+            if let (Some(l), Some(r)) = (Const::from_expr(&left), Const::from_expr(&right)) {
+                if let Some(res) = fold_binary(&e.operator, l, r) {
+                    return res.into_expr(e.loc);
+                }
+            }
+
+            Expr::Binary(BinaryExpr {
+                left: Box::new(left),
+                right: Box::new(right),
+                ..e
+            })
+        }
+        Expr::Unary(e) => {
+            let right = fold_in(*e.right, natives);
+
+            if let Some(res) = Const::from_expr(&right).and_then(|c| fold_unary(&e.operator, c)) {
+                return res.into_expr(e.loc);
+            }
+
+            Expr::Unary(UnaryExpr { right: Box::new(right), ..e })
+        }
+        // A grouped constant is the constant, located on the parentheses
+        Expr::Grouping(e) => {
+            let inner = fold_in(*e.expr, natives);
+
+            match Const::from_expr(&inner) {
+                Some(c) => c.into_expr(e.loc),
+                None => Expr::Grouping(GroupingExpr { expr: Box::new(inner), ..e }),
+            }
+        }
+        Expr::Assign(e) => Expr::Assign(AssignExpr {
+            value: Box::new(fold_in(*e.value, natives)),
+            ..e
+        }),
+        // Like at runtime, a constant left operand decides whether the
+        // right one is the result, whatever it is
+        Expr::Logical(e) => {
+            let left = fold_in(*e.left, natives);
+            let right = fold_in(*e.right, natives);
+
+            match (e.operator.as_str(), Const::from_expr(&left)) {
+                ("and", Some(c @ Const::Bool(false))) | ("or", Some(c @ Const::Bool(true))) => {
+                    c.into_expr(e.loc)
+                }
+                ("and", Some(Const::Bool(true))) | ("or", Some(Const::Bool(false))) => right,
+                _ => Expr::Logical(LogicalExpr {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    ..e
+                }),
+            }
+        }
+        Expr::Call(e) => {
+            let callee = fold_in(*e.callee, natives);
+            let args: Vec<Expr> = e.args.into_iter().map(|e| fold_in(e, natives)).collect();
+
+            if let Some(res) = fold_native_call(&callee, &args, natives) {
+                return res.into_expr(e.loc);
+            }
+
+            Expr::Call(CallExpr { callee: Box::new(callee), args, ..e })
+        }
+        Expr::TypeCheck(e) => Expr::TypeCheck(TypeCheckExpr {
+            value: Box::new(fold_in(*e.value, natives)),
+            ..e
+        }),
+        Expr::Get(e) => Expr::Get(GetExpr {
+            object: Box::new(fold_in(*e.object, natives)),
+            ..e
+        }),
+        Expr::Index(e) => Expr::Index(IndexExpr {
+            object: Box::new(fold_in(*e.object, natives)),
+            index: Box::new(fold_in(*e.index, natives)),
+            ..e
+        }),
+        Expr::Slice(e) => Expr::Slice(SliceExpr {
+            object: Box::new(fold_in(*e.object, natives)),
+            start: Box::new(fold_in(*e.start, natives)),
+            end: Box::new(fold_in(*e.end, natives)),
+            ..e
+        }),
+        Expr::If(e) => Expr::If(IfExpr {
+            condition: Box::new(fold_in(*e.condition, natives)),
+            then_branch: fold_branch(e.then_branch, natives),
+            else_branch: fold_branch(e.else_branch, natives),
+            ..e
+        }),
+        Expr::IntLiteral(_)
+        | Expr::RealLiteral(_)
+        | Expr::StrLiteral(_)
+        | Expr::BoolLiteral(_)
+        | Expr::NullLiteral(_)
+        | Expr::Identifier(_) => expr,
+    }
+}
+
+fn fold_branch(branch: IfExprBranch, natives: &[&str]) -> IfExprBranch {
+    // Shared once the expression has been cloned
+    let stmts = match Rc::try_unwrap(branch.stmts) {
+        Ok(stmts) => Rc::new(fold_stmts(stmts, natives)),
+        Err(stmts) => stmts,
+    };
+
+    IfExprBranch { stmts, value: Box::new(fold_in(*branch.value, natives)) }
+}
+
+fn fold_stmt(stmt: Stmt, natives: &[&str]) -> Stmt {
+    let fold_boxed = |s: Box<Stmt>| Box::new(fold_stmt(*s, natives));
+
+    match stmt {
+        Stmt::Expr(s) => Stmt::Expr(ExprStmt { expr: fold_in(s.expr, natives), ..s }),
+        Stmt::Print(s) => Stmt::Print(PrintStmt { expr: fold_in(s.expr, natives), ..s }),
+        Stmt::VarDecl(s) => Stmt::VarDecl(VarDeclStmt { value: s.value.map(|e| fold_in(e, natives)), ..s }),
+        Stmt::Block(s) => Stmt::Block(BlockStmt { stmts: fold_stmts(s.stmts, natives), ..s }),
+        Stmt::If(s) => Stmt::If(IfStmt {
+            condition: fold_in(s.condition, natives),
+            then_branch: s.then_branch.map(fold_boxed),
+            else_branch: s.else_branch.map(fold_boxed),
+            ..s
+        }),
+        Stmt::While(s) => Stmt::While(WhileStmt {
+            condition: fold_in(s.condition, natives),
+            body: fold_boxed(s.body),
+            ..s
+        }),
+        Stmt::WhileLet(s) => Stmt::WhileLet(WhileLetStmt {
+            value: fold_in(s.value, natives),
+            body: fold_boxed(s.body),
+            ..s
+        }),
+        Stmt::For(s) => Stmt::For(ForStmt { body: fold_boxed(s.body), ..s }),
+        // The body is only shared once functions are declared at runtime
+        Stmt::FnDecl(s) => {
+            let body = match Rc::try_unwrap(s.body) {
+                Ok(body) => Rc::new(fold_stmts(body, natives)),
+                Err(body) => body,
+            };
+
+            Stmt::FnDecl(FnDeclStmt { body, ..s })
+        }
+        Stmt::Return(s) => Stmt::Return(ReturnStmt { value: s.value.map(|e| fold_in(e, natives)), ..s }),
+        Stmt::Import(_) | Stmt::EnumDecl(_) => stmt,
+    }
+}
+
+// Value of a literal expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Const {
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+}
+
+impl Const {
+    fn from_expr(expr: &Expr) -> Option<Const> {
+        match expr {
+            Expr::IntLiteral(e) => Some(Const::Int(e.value)),
+            Expr::RealLiteral(e) => Some(Const::Real(e.value)),
+            Expr::BoolLiteral(e) => Some(Const::Bool(e.value)),
+            _ => None,
+        }
+    }
+
+    fn into_expr(self, loc: Loc) -> Expr {
+        match self {
+            Const::Int(value) => Expr::IntLiteral(IntLiteralExpr { value, loc }),
+            Const::Real(value) => Expr::RealLiteral(RealLiteralExpr { value, loc }),
+            Const::Bool(value) => Expr::BoolLiteral(BoolLiteralExpr { value, loc }),
+        }
+    }
+}
+
+fn fold_native_call(callee: &Expr, args: &[Expr], natives: &[&str]) -> Option<Const> {
+    let Expr::Identifier(ident) = callee else { return None };
+    if !natives.contains(&ident.name.as_str()) {
+        return None;
+    }
+
+    match (ident.name.as_str(), args) {
+        // Length in chars, like at runtime
+        ("len", [Expr::StrLiteral(s)]) => i64::try_from(s.value.chars().count()).ok().map(Const::Int),
+        _ => None,
+    }
+}
+
+fn declares(stmt: &Stmt, name: &str) -> bool {
+    match stmt {
+        Stmt::VarDecl(s) => s.name == name,
+        Stmt::WhileLet(s) => s.name == name,
+        Stmt::For(s) => s.placeholder.name == name,
+        Stmt::FnDecl(s) => s.name == name || s.params.iter().any(|p| p == name),
+        Stmt::EnumDecl(s) => s.name == name,
+        _ => false,
+    }
+}
+
+fn assigns(expr: &Expr, name: &str) -> bool {
+    matches!(expr, Expr::Assign(e) if e.name == name)
+}
+
+fn fold_unary(operator: &str, value: Const) -> Option<Const> {
+    match (operator, value) {
+        ("-", Const::Int(i)) => i.checked_neg().map(Const::Int),
+        ("-", Const::Real(r)) => Some(Const::Real(-r)),
+        ("!", Const::Bool(b)) => Some(Const::Bool(!b)),
+        _ => None,
+    }
+}
+
+// Same rules as the runtime: int arithmetic is checked and as soon as
+// one operand is a real, the operation is a real one
+fn fold_binary(operator: &str, lhs: Const, rhs: Const) -> Option<Const> {
+    match (lhs, rhs) {
+        (Const::Int(a), Const::Int(b)) => fold_int(operator, a, b),
+        // Mixed equality is exact, the int isn't rounded to a real
+        (Const::Int(i), Const::Real(r)) | (Const::Real(r), Const::Int(i))
+            if matches!(operator, "==" | "!=") =>
+        {
+            Some(Const::Bool(int_eq_real(i, r) == (operator == "==")))
+        }
+        (Const::Int(a), Const::Real(b)) => fold_real(operator, a as f64, b),
+        (Const::Real(a), Const::Int(b)) => fold_real(operator, a, b as f64),
+        (Const::Real(a), Const::Real(b)) => fold_real(operator, a, b),
+        (Const::Bool(a), Const::Bool(b)) if matches!(operator, "==" | "!=") => {
+            compare(operator, a, b)
+        }
+        _ => None,
+    }
+}
+
+fn fold_int(operator: &str, a: i64, b: i64) -> Option<Const> {
+    let res = match operator {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "/" => a.checked_div(b),
+        "%" => a.checked_rem(b),
+        _ => return compare(operator, a, b),
+    };
+
+    res.map(Const::Int)
+}
+
+fn fold_real(operator: &str, a: f64, b: f64) -> Option<Const> {
+    let res = match operator {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" => a / b,
+        "%" => a % b,
+        _ => return compare(operator, a, b),
+    };
+
+    Some(Const::Real(res))
+}
+
+fn compare<T: PartialOrd>(operator: &str, a: T, b: T) -> Option<Const> {
+    let res = match operator {
+        "<" => a < b,
+        "<=" => a <= b,
+        ">" => a > b,
+        ">=" => a >= b,
+        "==" => a == b,
+        "!=" => a != b,
+        _ => return None,
+    };
+
+    Some(Const::Bool(res))
+}
+
+// Same as the runtime comparison
+fn int_eq_real(int: i64, real: f64) -> bool {
+    real.fract() == 0.0
+        && real >= i64::MIN as f64
+        && real < i64::MAX as f64
+        && real as i64 == int
+}
+
+#[cfg(test)]
+mod tests {
+    use tools::results::Loc;
+
+    use super::{fold_constants, fold_expr, ConstFolder};
+    use crate::ast::expr::{BoolLiteralExpr, Expr, IntLiteralExpr};
+    use crate::ast::stmt::Stmt;
+    use crate::parser::{parse, parse_expression};
+
+    fn fold(code: &str) -> Expr {
+        fold_expr(parse_expression(code).unwrap())
+    }
+
+    #[test]
+    fn fold_arithmetic() {
+        let cases = [
+            ("1 + 2 * 3", "7"),
+            ("7 / 2 - 7 % 2", "2"),
+            ("1 + 2.5", "3.5"),
+            ("5. / 2", "2.5"),
+            ("a + 2 * 3", "a + 6"),
+            ("a + 1 + 2", "a + 1 + 2"),
+            ("f(1 + 1, (2))", "f(2, 2)"),
+        ];
+
+        for (code, expected) in cases {
+            assert_eq!(fold(code).to_string(), expected, "{}", code);
+        }
+
+        // Runtime errors aren't folded
+        assert_eq!(fold("1 / 0").to_string(), "1 / 0");
+        assert_eq!(fold("9223372036854775807 + 1").to_string(), "9223372036854775807 + 1");
+        assert!(matches!(fold("1.5 / 0"), Expr::RealLiteral(r) if r.value.is_infinite()));
+    }
+
+    #[test]
+    fn fold_negation() {
+        assert_eq!(
+            fold("-(3 + 2)"),
+            Expr::IntLiteral(IntLiteralExpr { value: -5, loc: Loc::new(0, 8) })
+        );
+        assert_eq!(fold("- -5").to_string(), "5");
+        assert_eq!(fold("2 * -3 + 1").to_string(), "-5");
+        assert_eq!(fold("-2.5 - 1").to_string(), "-3.5");
+
+        let Expr::Unary(u) = fold("-x") else { panic!() };
+        assert!(matches!(u.right.as_ref(), Expr::Identifier(i) if i.name == "x"));
+
+        assert_eq!(fold("-(x + 1)").to_string(), "-(x + 1)");
+    }
+
+    #[test]
+    fn fold_stmts() {
+        let code = "
+var a = 60 * 60
+fn f(x) {
+    if x > 2 - 1 { return -(x * (1 + 1)) }
+}
+while a < 10 * 10 { a = a + 1 - 1 }
+";
+        let nodes = fold_constants(parse(code).unwrap());
+
+        let Stmt::VarDecl(v) = &nodes[0] else { panic!() };
+        assert_eq!(v.value.as_ref().unwrap().to_string(), "3600");
+
+        let Stmt::FnDecl(f) = &nodes[1] else { panic!() };
+        let Stmt::If(i) = &f.body[0] else { panic!() };
+        assert_eq!(i.condition.to_string(), "x > 1");
+        let Some(Stmt::Return(r)) = i.then_branch.as_deref() else { panic!() };
+        assert_eq!(r.value.as_ref().unwrap().to_string(), "-(x * 2)");
+
+        let Stmt::While(w) = &nodes[2] else { panic!() };
+        assert_eq!(w.condition.to_string(), "a < 100");
+    }
+
+    #[test]
+    fn fold_bool() {
+        let cases = [
+            ("true and false", "false"),
+            ("false or true", "true"),
+            ("!true", "false"),
+            ("!!false", "false"),
+            ("1 < 2", "true"),
+            ("2.5 >= 3", "false"),
+            ("1 + 1 == 2.0", "true"),
+            ("9007199254740993 == 9007199254740992.0", "false"),
+            ("true != false", "true"),
+            ("!(1 > 2) and 3 <= 3", "true"),
+        ];
+
+        for (code, expected) in cases {
+            assert_eq!(fold(code).to_string(), expected, "{}", code);
+        }
+
+        // Operations that fail at runtime are kept
+        assert_eq!(fold("!1").to_string(), "!1");
+        assert_eq!(fold("true < false").to_string(), "true < false");
+        assert_eq!(fold("1 and x").to_string(), "1 and x");
+    }
+
+    #[test]
+    fn fold_short_circuit() {
+        assert_eq!(fold("false and x").to_string(), "false");
+        assert_eq!(fold("true or f()").to_string(), "true");
+        assert_eq!(fold("1 > 2 and x").to_string(), "false");
+        assert_eq!(fold("true and x").to_string(), "x");
+        assert_eq!(fold("false or x == 1 + 1").to_string(), "x == 2");
+
+        // The right operand alone doesn't decide
+        assert_eq!(fold("x and false").to_string(), "x and false");
+        assert_eq!(fold("x or 1 < 2").to_string(), "x or true");
+
+        assert_eq!(
+            fold("false and x"),
+            Expr::BoolLiteral(BoolLiteralExpr { value: false, loc: Loc::new(0, 11) })
+        );
+    }
+
+    #[test]
+    fn fold_len() {
+        let folder = ConstFolder { fold_natives: true };
+        let fold = |code: &str| folder.fold_expr(parse_expression(code).unwrap());
+
+        assert_eq!(
+            fold("len(\"abc\")"),
+            Expr::IntLiteral(IntLiteralExpr { value: 3, loc: Loc::new(0, 10) })
+        );
+        assert_eq!(fold("len(\"héllo\") * 2 + len(\"\")").to_string(), "10");
+
+        // Only literal arguments of known natives
+        assert_eq!(fold("len(x)").to_string(), "len(x)");
+        assert_eq!(fold("len(\"a\" + x)").to_string(), "len(\"a\" + x)");
+        assert_eq!(fold("len(1)").to_string(), "len(1)");
+        assert_eq!(fold("len(\"a\", \"b\")").to_string(), "len(\"a\", \"b\")");
+        assert_eq!(fold("size(\"abc\")").to_string(), "size(\"abc\")");
+
+        // A local declaration can replace the native
+        let code = "print len(\"abc\")\nfn f(len) { return len(\"abc\") }";
+        let nodes = folder.fold(parse(code).unwrap());
+        let Stmt::Print(p) = &nodes[0] else { panic!() };
+        assert_eq!(p.expr.to_string(), "len(\"abc\")");
+
+        // So can an assignment, anywhere in the program
+        let code = "fn g(s) { return 1 }\nfn h() { len = g }\nprint len(\"abc\")";
+        let nodes = folder.fold(parse(code).unwrap());
+        let Stmt::Print(p) = &nodes[2] else { panic!() };
+        assert_eq!(p.expr.to_string(), "len(\"abc\")");
+
+        // Or an imported file
+        let code = "import \"lib.rz\"\nprint len(\"abc\")";
+        let nodes = folder.fold(parse(code).unwrap());
+        let Stmt::Print(p) = &nodes[1] else { panic!() };
+        assert_eq!(p.expr.to_string(), "len(\"abc\")");
+
+        let nodes = folder.fold(parse("print len(\"abc\")").unwrap());
+        let Stmt::Print(p) = &nodes[0] else { panic!() };
+        assert_eq!(p.expr.to_string(), "3");
+
+        // Opt-in
+        assert_eq!(fold_expr(parse_expression("len(\"abc\")").unwrap()).to_string(), "len(\"abc\")");
+        let nodes = fold_constants(parse("print len(\"abc\")").unwrap());
+        let Stmt::Print(p) = &nodes[0] else { panic!() };
+        assert_eq!(p.expr.to_string(), "len(\"abc\")");
+    }
+}
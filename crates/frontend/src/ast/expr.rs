@@ -1,8 +1,11 @@
 use std::fmt::Display;
+use std::rc::Rc;
 
 use ecow::EcoString;
 use tools::results::{Loc, PhyReport, PhyResult};
 
+use super::stmt::Stmt;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
     Binary(BinaryExpr),
@@ -10,26 +13,221 @@ pub enum Expr {
     IntLiteral(IntLiteralExpr),
     RealLiteral(RealLiteralExpr),
     StrLiteral(StrLiteralExpr),
+    BoolLiteral(BoolLiteralExpr),
+    NullLiteral(NullLiteralExpr),
     Identifier(IdentifierExpr),
     Unary(UnaryExpr),
     Assign(AssignExpr),
     Logical(LogicalExpr),
     Call(CallExpr),
+    TypeCheck(TypeCheckExpr),
+    Get(GetExpr),
+    Index(IndexExpr),
+    Slice(SliceExpr),
+    If(IfExpr),
 }
 
+// Binding power of each expression kind, in the same order as the
+// parser's descent. Higher binds tighter
+const PREC_ASSIGN: u8 = 1;
+const PREC_OR: u8 = 2;
+const PREC_COALESCE: u8 = 3;
+const PREC_AND: u8 = 4;
+const PREC_EQUALITY: u8 = 5;
+const PREC_IS: u8 = 6;
+const PREC_COMPARISON: u8 = 7;
+const PREC_TERM: u8 = 8;
+const PREC_FACTOR: u8 = 9;
+const PREC_UNARY: u8 = 10;
+const PREC_CALL: u8 = 11;
+const PREC_PRIMARY: u8 = 12;
+
+// Groupings aren't printed as is, parentheses are only added where the
+// precedence requires them so that the output parses back to the same tree
 impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Left operands printed without parentheses are followed in a loop,
+        // so that long chains like 'a + b + c ...' or 'f()()...' don't recurse
+        let mut chain = vec![self];
+        while let Some((left, min_prec)) = chain[chain.len() - 1].left_operand() {
+            if left.precedence() < min_prec || left.left_operand().is_none() {
+                break;
+            }
+            chain.push(left);
+        }
+
+        match chain[chain.len() - 1].left_operand() {
+            Some((left, min_prec)) => {
+                left.fmt_prec(f, min_prec)?;
+                chain.iter().rev().try_for_each(|e| e.fmt_after_left(f))
+            }
+            None => self.fmt_node(f),
+        }
+    }
+}
+
+impl Expr {
+    // Operand printed first, with the precedence it needs to go without parentheses
+    fn left_operand(&self) -> Option<(&Expr, u8)> {
+        match self {
+            Expr::Binary(e) => Some((&e.left, self.precedence())),
+            // 'and' and 'or' can't be chained, '??' is left associative
+            Expr::Logical(e) => match e.operator == "??" {
+                true => Some((&e.left, self.precedence())),
+                false => Some((&e.left, self.precedence() + 1)),
+            },
+            Expr::Call(e) => Some((&e.callee, PREC_CALL)),
+            Expr::TypeCheck(e) => Some((&e.value, PREC_COMPARISON)),
+            Expr::Get(e) => Some((&e.object, PREC_CALL)),
+            Expr::Index(e) => Some((&e.object, PREC_CALL)),
+            Expr::Slice(e) => Some((&e.object, PREC_CALL)),
+            _ => None,
+        }
+    }
+
+    // What follows the left operand
+    fn fmt_after_left(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Binary(e) => {
+                write!(f, " {} ", e.operator)?;
+                e.right.fmt_prec(f, self.precedence() + 1)
+            }
+            Expr::Logical(e) => {
+                write!(f, " {} ", e.operator)?;
+                e.right.fmt_prec(f, self.precedence() + 1)
+            }
+            Expr::Call(e) => {
+                write!(f, "(")?;
+                for (i, arg) in e.args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expr::TypeCheck(e) => write!(f, " is {}", e.type_name),
+            Expr::Get(e) => match e.optional {
+                true => write!(f, "?.{}", e.name),
+                false => write!(f, ".{}", e.name),
+            },
+            Expr::Index(e) => write!(f, "[{}]", e.index),
+            Expr::Slice(e) => write!(f, "[{}{}{}]", e.start, e.range_operator(), e.end),
+            _ => unreachable!("no left operand"),
+        }
+    }
+
+    // Expressions without left operand
+    fn fmt_node(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expr::Binary(e) => write!(f, "{} {} {}", e.left, e.operator, e.right),
             Expr::Grouping(e) => write!(f, "{}", e.expr),
             Expr::IntLiteral(e) => write!(f, "{}", e.value),
-            Expr::RealLiteral(e) => write!(f, "{}", e.value),
-            Expr::StrLiteral(e) => write!(f, "{}", e.value),
+            Expr::RealLiteral(e) => write!(f, "{}", format_real(e.value)),
+            // Escaped back, to be lexed to the same value
+            Expr::StrLiteral(e) => write!(f, "\"{}\"", escape_str(&e.value)),
+            Expr::BoolLiteral(e) => write!(f, "{}", e.value),
+            Expr::NullLiteral(_) => write!(f, "null"),
             Expr::Identifier(e) => write!(f, "{}", e.name),
-            Expr::Unary(e) => write!(f, "{} {}", e.operator, e.right),
-            Expr::Assign(e) => write!(f, "{} {}", e.name, e.value),
-            Expr::Logical(e) => write!(f, "{} {} {}", e.left, e.operator, e.right),
-            Expr::Call(e) => write!(f, "{}: {:?}", e.callee, e.args),
+            // Operand of a unary is parsed as a primary
+            Expr::Unary(e) => {
+                write!(f, "{}", e.operator)?;
+                e.right.fmt_prec(f, PREC_PRIMARY)
+            }
+            Expr::Assign(e) => {
+                write!(f, "{} = ", e.name)?;
+                e.value.fmt_prec(f, PREC_ASSIGN)
+            }
+            Expr::If(e) => write!(
+                f,
+                "if {} {{ {} }} else {{ {} }}",
+                e.condition, e.then_branch, e.else_branch
+            ),
+            _ => unreachable!("printed as a chain"),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Assign(_) => PREC_ASSIGN,
+            Expr::Logical(e) => match e.operator.as_str() {
+                "or" => PREC_OR,
+                "??" => PREC_COALESCE,
+                _ => PREC_AND,
+            },
+            Expr::Binary(e) => match e.operator.as_str() {
+                "==" | "!=" => PREC_EQUALITY,
+                "<" | "<=" | ">" | ">=" => PREC_COMPARISON,
+                "+" | "-" => PREC_TERM,
+                _ => PREC_FACTOR,
+            },
+            Expr::TypeCheck(_) => PREC_IS,
+            Expr::Unary(_) => PREC_UNARY,
+            Expr::Call(_) | Expr::Get(_) | Expr::Index(_) | Expr::Slice(_) => PREC_CALL,
+            Expr::Grouping(e) => e.expr.precedence(),
+            Expr::IntLiteral(_)
+            | Expr::RealLiteral(_)
+            | Expr::StrLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::NullLiteral(_)
+            | Expr::Identifier(_)
+            | Expr::If(_) => PREC_PRIMARY,
+        }
+    }
+
+    fn fmt_prec(&self, f: &mut std::fmt::Formatter<'_>, min_prec: u8) -> std::fmt::Result {
+        match self.precedence() < min_prec {
+            true => write!(f, "({})", self),
+            false => write!(f, "{}", self),
+        }
+    }
+
+    // Structural equality that ignores locations and groupings
+    pub fn semantic_eq(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (Expr::Grouping(g), _) => g.expr.semantic_eq(other),
+            (_, Expr::Grouping(g)) => self.semantic_eq(&g.expr),
+            (Expr::Binary(a), Expr::Binary(b)) => {
+                a.operator == b.operator && a.left.semantic_eq(&b.left) && a.right.semantic_eq(&b.right)
+            }
+            (Expr::IntLiteral(a), Expr::IntLiteral(b)) => a.value == b.value,
+            (Expr::RealLiteral(a), Expr::RealLiteral(b)) => a.value == b.value,
+            (Expr::StrLiteral(a), Expr::StrLiteral(b)) => a.value == b.value,
+            (Expr::BoolLiteral(a), Expr::BoolLiteral(b)) => a.value == b.value,
+            (Expr::NullLiteral(_), Expr::NullLiteral(_)) => true,
+            (Expr::Identifier(a), Expr::Identifier(b)) => a.name == b.name,
+            (Expr::Unary(a), Expr::Unary(b)) => {
+                a.operator == b.operator && a.right.semantic_eq(&b.right)
+            }
+            (Expr::Assign(a), Expr::Assign(b)) => a.name == b.name && a.value.semantic_eq(&b.value),
+            (Expr::Logical(a), Expr::Logical(b)) => {
+                a.operator == b.operator && a.left.semantic_eq(&b.left) && a.right.semantic_eq(&b.right)
+            }
+            (Expr::Call(a), Expr::Call(b)) => {
+                a.callee.semantic_eq(&b.callee)
+                    && a.args.len() == b.args.len()
+                    && a.args.iter().zip(&b.args).all(|(x, y)| x.semantic_eq(y))
+            }
+            (Expr::TypeCheck(a), Expr::TypeCheck(b)) => {
+                a.type_name == b.type_name && a.value.semantic_eq(&b.value)
+            }
+            (Expr::Get(a), Expr::Get(b)) => {
+                a.name == b.name && a.optional == b.optional && a.object.semantic_eq(&b.object)
+            }
+            (Expr::Index(a), Expr::Index(b)) => {
+                a.object.semantic_eq(&b.object) && a.index.semantic_eq(&b.index)
+            }
+            (Expr::Slice(a), Expr::Slice(b)) => {
+                a.inclusive == b.inclusive
+                    && a.object.semantic_eq(&b.object)
+                    && a.start.semantic_eq(&b.start)
+                    && a.end.semantic_eq(&b.end)
+            }
+            (Expr::If(a), Expr::If(b)) => {
+                a.condition.semantic_eq(&b.condition)
+                    && a.then_branch.semantic_eq(&b.then_branch)
+                    && a.else_branch.semantic_eq(&b.else_branch)
+            }
+            _ => false,
         }
     }
 }
@@ -42,11 +240,41 @@ impl Expr {
             Self::IntLiteral(i) => i.loc.clone(),
             Self::RealLiteral(r) => r.loc.clone(),
             Self::StrLiteral(s) => s.loc.clone(),
+            Self::BoolLiteral(b) => b.loc.clone(),
+            Self::NullLiteral(n) => n.loc.clone(),
             Self::Identifier(i) => i.loc.clone(),
             Self::Unary(u) => u.loc.clone(),
             Self::Assign(a) => a.loc.clone(),
             Self::Logical(l) => l.loc.clone(),
             Self::Call(c) => c.loc.clone(),
+            Self::TypeCheck(t) => t.loc.clone(),
+            Self::Get(g) => g.loc.clone(),
+            Self::Index(i) => i.loc.clone(),
+            Self::Slice(s) => s.loc.clone(),
+            Self::If(i) => i.loc.clone(),
+        }
+    }
+
+    // Short name of the variant, for diagnostics and tooling
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Self::Binary(_) => "binary",
+            Self::Grouping(_) => "grouping",
+            Self::IntLiteral(_) => "int",
+            Self::RealLiteral(_) => "real",
+            Self::StrLiteral(_) => "str",
+            Self::BoolLiteral(_) => "bool",
+            Self::NullLiteral(_) => "null",
+            Self::Identifier(_) => "identifier",
+            Self::Unary(_) => "unary",
+            Self::Assign(_) => "assign",
+            Self::Logical(_) => "logical",
+            Self::Call(_) => "call",
+            Self::TypeCheck(_) => "type_check",
+            Self::Get(_) => "get",
+            Self::Index(_) => "index",
+            Self::Slice(_) => "slice",
+            Self::If(_) => "if",
         }
     }
 }
@@ -77,12 +305,51 @@ pub struct RealLiteralExpr {
     pub loc: Loc,
 }
 
+// Shortest text parsed back to the same real. Whole reals keep their
+// '.0', otherwise they would be read as ints, and very large or small
+// ones use an exponent: 1e300, 1e-7
+pub fn format_real(value: f64) -> String {
+    format!("{:?}", value)
+}
+
+// Text of a string literal lexed back to the same string: only the
+// escapes the lexer knows, other control chars as \u{..}
+pub fn escape_str(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct StrLiteralExpr {
     pub value: EcoString,
     pub loc: Loc,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct BoolLiteralExpr {
+    pub value: bool,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NullLiteralExpr {
+    pub loc: Loc,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct IdentifierExpr {
     pub name: EcoString,
@@ -118,6 +385,85 @@ pub struct CallExpr {
     pub loc: Loc,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeCheckExpr {
+    pub value: Box<Expr>,
+    pub type_name: EcoString,
+    pub loc: Loc,
+}
+
+// With 'optional', the access short-circuits to null when the object is
+// null instead of being an error
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: EcoString,
+    pub optional: bool,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexExpr {
+    pub object: Box<Expr>,
+    pub index: Box<Expr>,
+    pub loc: Loc,
+}
+
+// 'object[start..end]', or 'object[start..=end]' to include the end
+#[derive(Debug, PartialEq, Clone)]
+pub struct SliceExpr {
+    pub object: Box<Expr>,
+    pub start: Box<Expr>,
+    pub end: Box<Expr>,
+    pub inclusive: bool,
+    pub loc: Loc,
+}
+
+impl SliceExpr {
+    pub fn range_operator(&self) -> &'static str {
+        match self.inclusive {
+            true => "..=",
+            false => "..",
+        }
+    }
+}
+
+// Expression form of 'if', each branch is a block holding the expression
+// it evaluates to. The 'else' branch is mandatory
+#[derive(Debug, PartialEq, Clone)]
+pub struct IfExpr {
+    pub condition: Box<Expr>,
+    pub then_branch: IfExprBranch,
+    pub else_branch: IfExprBranch,
+    pub loc: Loc,
+}
+
+// Statements run in the scope of the branch, then its last expression
+// giving the value
+#[derive(Debug, PartialEq, Clone)]
+pub struct IfExprBranch {
+    pub stmts: Rc<Vec<Stmt>>,
+    pub value: Box<Expr>,
+}
+
+impl Display for IfExprBranch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for stmt in self.stmts.iter() {
+            write!(f, "{}; ", stmt)?;
+        }
+
+        write!(f, "{}", self.value)
+    }
+}
+
+impl IfExprBranch {
+    pub fn semantic_eq(&self, other: &IfExprBranch) -> bool {
+        self.stmts.len() == other.stmts.len()
+            && self.stmts.iter().zip(other.stmts.iter()).all(|(a, b)| a.semantic_eq(b))
+            && self.value.semantic_eq(&other.value)
+    }
+}
+
 impl Expr {
     pub fn accept<T, U: PhyReport>(
         &self,
@@ -129,11 +475,18 @@ impl Expr {
             Expr::IntLiteral(e) => visitor.visit_int_literal_expr(e),
             Expr::RealLiteral(e) => visitor.visit_real_literal_expr(e),
             Expr::StrLiteral(e) => visitor.visit_str_literal_expr(e),
+            Expr::BoolLiteral(e) => visitor.visit_bool_literal_expr(e),
+            Expr::NullLiteral(e) => visitor.visit_null_literal_expr(e),
             Expr::Identifier(e) => visitor.visit_identifier_expr(e),
             Expr::Unary(e) => visitor.visit_unary_expr(e),
             Expr::Assign(e) => visitor.visit_assign_expr(e),
             Expr::Logical(l) => visitor.visit_logical_expr(l),
             Expr::Call(c) => visitor.visit_call_expr(c),
+            Expr::TypeCheck(t) => visitor.visit_type_check_expr(t),
+            Expr::Get(g) => visitor.visit_get_expr(g),
+            Expr::Index(i) => visitor.visit_index_expr(i),
+            Expr::Slice(s) => visitor.visit_slice_expr(s),
+            Expr::If(i) => visitor.visit_if_expr(i),
         }
     }
 }
@@ -144,11 +497,18 @@ pub trait VisitExpr<T, U: PhyReport> {
     fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> Result<T, PhyResult<U>>;
     fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> Result<T, PhyResult<U>>;
     fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> Result<T, PhyResult<U>>;
+    fn visit_bool_literal_expr(&mut self, expr: &BoolLiteralExpr) -> Result<T, PhyResult<U>>;
+    fn visit_null_literal_expr(&mut self, expr: &NullLiteralExpr) -> Result<T, PhyResult<U>>;
     fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> Result<T, PhyResult<U>>;
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Result<T, PhyResult<U>>;
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Result<T, PhyResult<U>>;
     fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Result<T, PhyResult<U>>;
     fn visit_call_expr(&mut self, expr: &CallExpr) -> Result<T, PhyResult<U>>;
+    fn visit_type_check_expr(&mut self, expr: &TypeCheckExpr) -> Result<T, PhyResult<U>>;
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<T, PhyResult<U>>;
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<T, PhyResult<U>>;
+    fn visit_slice_expr(&mut self, expr: &SliceExpr) -> Result<T, PhyResult<U>>;
+    fn visit_if_expr(&mut self, expr: &IfExpr) -> Result<T, PhyResult<U>>;
 }
 
 // Into
@@ -170,3 +530,110 @@ impl From<&AssignExpr> for Expr {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Expr;
+    use crate::{ast::stmt::Stmt, parser::utils::lex_and_parse};
+
+    fn parse_expr(code: &str) -> Expr {
+        let mut nodes = lex_and_parse(code).unwrap();
+        match nodes.remove(0) {
+            Stmt::Expr(s) => s.expr,
+            _ => panic!("expected an expression statement"),
+        }
+    }
+
+    #[test]
+    fn display_min_parens() {
+        let cases = [
+            ("(1 + 2) * 3", "(1 + 2) * 3"),
+            ("1 + (2 * 3)", "1 + 2 * 3"),
+            ("(a - b) - c", "a - b - c"),
+            ("a - (b - c)", "a - (b - c)"),
+            ("-(a + 1)", "-(a + 1)"),
+            ("(a or b) and c", "(a or b) and c"),
+            ("((a))", "a"),
+            ("(a < b) is bool", "a < b is bool"),
+            ("(a.b)(2.0 + 1, 2)", "a.b(2.0 + 1, 2)"),
+            ("x = (y = 2)", "x = y = 2"),
+        ];
+
+        for (code, expected) in cases {
+            assert_eq!(parse_expr(code).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let codes = [
+            "(1 + 2) * 3",
+            "1 + 2 * 3",
+            "a / (b / c) % 4",
+            "-(-a)",
+            "!(a == b) != (2.5 <= c)",
+            "(a ?? b) ?? c",
+            "a ?? (b ?? c)",
+            "(a and b) or (c and d)",
+            "(a is int) is bool",
+            "(a + 1).b?.c",
+            "(a ?? b)[i - 1].c[0]",
+            "s[1..n - 1][0..=2]",
+            "f(1 + 2, \"s\")(g)",
+            "f(\"a\\\"b\\\\\\n\")",
+            "\"it's \\t\\r\\0 \\x01 \\u{7f} é\"",
+            "!true or a ?? null",
+            "x = if a > 1 { a * (2 - b) } else { 0 }",
+            "x = if a { var b = 2; print b; b } else { fn f(c) { return c }; f(1) }",
+            "a.b(1)[2] + c * d - -e ?? f is int",
+            "(a + b).c + (d or e) - f(g)(h)",
+        ];
+
+        for code in codes {
+            let expr = parse_expr(code);
+            let reparsed = parse_expr(&expr.to_string());
+
+            assert!(expr.semantic_eq(&reparsed), "{} -> {}", code, expr);
+        }
+
+        // Only the escapes the lexer accepts
+        assert_eq!(parse_expr(r#""it's\x01""#).to_string(), r#""it's\u{1}""#);
+    }
+
+    #[test]
+    fn display_long_chain() {
+        use super::{BinaryExpr, CallExpr, IntLiteralExpr};
+        use tools::results::Loc;
+
+        // Built by hand, deeper than the parser allows nesting
+        let one = || Expr::IntLiteral(IntLiteralExpr { value: 1, loc: Loc::new(0, 1) });
+        let mut sum = one();
+        let mut calls = one();
+
+        for i in 0..10_000 {
+            let operator = if i % 2 == 0 { "+" } else { "-" };
+            sum = Expr::Binary(BinaryExpr {
+                left: Box::new(sum),
+                operator: operator.into(),
+                right: Box::new(one()),
+                loc: Loc::new(0, 1),
+            });
+            calls = Expr::Call(CallExpr { callee: Box::new(calls), args: vec![one()], loc: Loc::new(0, 1) });
+        }
+
+        assert_eq!(sum.to_string(), format!("1{}", " + 1 - 1".repeat(5_000)));
+        assert_eq!(calls.to_string(), format!("1{}", "(1)".repeat(10_000)));
+    }
+
+    #[test]
+    fn kind_names() {
+        assert_eq!(parse_expr("1 + 2").kind_name(), "binary");
+        assert_eq!(parse_expr("(a)").kind_name(), "grouping");
+        assert_eq!(parse_expr("12").kind_name(), "int");
+        assert_eq!(parse_expr("a is int").kind_name(), "type_check");
+
+        let nodes = lex_and_parse("var a = 1\nfn f() {}\nwhile var b = a {}").unwrap();
+        let names = nodes.iter().map(|n| n.kind_name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["var_decl", "fn_decl", "while_let"]);
+    }
+}
@@ -1,11 +1,12 @@
+use std::fmt::Display;
 use std::rc::Rc;
 
 use ecow::EcoString;
 
-use super::expr::Expr;
+use super::expr::{escape_str, Expr};
 use tools::results::{Loc, PhyReport, PhyResult};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Stmt {
     Expr(ExprStmt),
     Print(PrintStmt),
@@ -13,37 +14,44 @@ pub enum Stmt {
     Block(BlockStmt),
     If(IfStmt),
     While(WhileStmt),
+    WhileLet(WhileLetStmt),
     For(ForStmt),
     FnDecl(FnDeclStmt),
     Return(ReturnStmt),
+    Import(ImportStmt),
+    EnumDecl(EnumDeclStmt),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ExprStmt {
     pub expr: Expr,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct PrintStmt {
     pub expr: Expr,
+    // The 'print' keyword alone, 'loc' covers the whole statement
+    pub keyword_loc: Loc,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct BlockStmt {
     pub stmts: Vec<Stmt>,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct VarDeclStmt {
     pub name: EcoString,
     pub value: Option<Expr>,
+    // From the '///' comments right above the declaration
+    pub doc: Option<EcoString>,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct IfStmt {
     pub condition: Expr,
     pub then_branch: Option<Box<Stmt>>,
@@ -51,14 +59,23 @@ pub struct IfStmt {
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct WhileStmt {
     pub condition: Expr,
     pub body: Box<Stmt>,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+// 'while var name = value', looping while the value isn't null
+#[derive(Debug, PartialEq)]
+pub struct WhileLetStmt {
+    pub name: EcoString,
+    pub value: Expr,
+    pub body: Box<Stmt>,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq)]
 pub struct ForStmt {
     pub placeholder: VarDeclStmt,
     pub range: ForRange,
@@ -66,26 +83,40 @@ pub struct ForStmt {
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ForRange {
     pub start: i64,
     pub end: Option<i64>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct FnDeclStmt {
     pub name: EcoString,
     pub params: Rc<Vec<EcoString>>,
     pub body: Rc<Vec<Stmt>>,
+    pub doc: Option<EcoString>,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ReturnStmt {
     pub value: Option<Expr>,
     pub loc: Loc,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct ImportStmt {
+    pub path: EcoString,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EnumDeclStmt {
+    pub name: EcoString,
+    pub variants: Vec<EcoString>,
+    pub loc: Loc,
+}
+
 impl Stmt {
     pub fn accept<T, U: PhyReport>(
         &self,
@@ -98,9 +129,158 @@ impl Stmt {
             Stmt::Block(stmt) => visitor.visit_block_stmt(stmt),
             Stmt::If(stmt) => visitor.visit_if_stmt(stmt),
             Stmt::While(stmt) => visitor.visit_while_stmt(stmt),
+            Stmt::WhileLet(stmt) => visitor.visit_while_let_stmt(stmt),
             Stmt::For(stmt) => visitor.visit_for_stmt(stmt),
             Stmt::FnDecl(stmt) => visitor.visit_fn_decl_stmt(stmt),
             Stmt::Return(stmt) => visitor.visit_return_stmt(stmt),
+            Stmt::Import(stmt) => visitor.visit_import_stmt(stmt),
+            Stmt::EnumDecl(stmt) => visitor.visit_enum_decl_stmt(stmt),
+        }
+    }
+
+    pub fn get_loc(&self) -> Loc {
+        match self {
+            Stmt::Expr(s) => s.loc.clone(),
+            Stmt::Print(s) => s.loc.clone(),
+            Stmt::VarDecl(s) => s.loc.clone(),
+            Stmt::Block(s) => s.loc.clone(),
+            Stmt::If(s) => s.loc.clone(),
+            Stmt::While(s) => s.loc.clone(),
+            Stmt::WhileLet(s) => s.loc.clone(),
+            Stmt::For(s) => s.loc.clone(),
+            Stmt::FnDecl(s) => s.loc.clone(),
+            Stmt::Return(s) => s.loc.clone(),
+            Stmt::Import(s) => s.loc.clone(),
+            Stmt::EnumDecl(s) => s.loc.clone(),
+        }
+    }
+
+    // Short name of the variant, for diagnostics and tooling
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Stmt::Expr(_) => "expr",
+            Stmt::Print(_) => "print",
+            Stmt::VarDecl(_) => "var_decl",
+            Stmt::Block(_) => "block",
+            Stmt::If(_) => "if",
+            Stmt::While(_) => "while",
+            Stmt::WhileLet(_) => "while_let",
+            Stmt::For(_) => "for",
+            Stmt::FnDecl(_) => "fn_decl",
+            Stmt::Return(_) => "return",
+            Stmt::Import(_) => "import",
+            Stmt::EnumDecl(_) => "enum_decl",
+        }
+    }
+}
+
+impl Stmt {
+    // Structural equality that ignores locations, groupings and docs
+    pub fn semantic_eq(&self, other: &Stmt) -> bool {
+        fn all_eq(a: &[Stmt], b: &[Stmt]) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.semantic_eq(y))
+        }
+
+        fn opt_eq(a: Option<&Expr>, b: Option<&Expr>) -> bool {
+            match (a, b) {
+                (Some(x), Some(y)) => x.semantic_eq(y),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+        }
+
+        match (self, other) {
+            (Stmt::Expr(a), Stmt::Expr(b)) => a.expr.semantic_eq(&b.expr),
+            (Stmt::Print(a), Stmt::Print(b)) => a.expr.semantic_eq(&b.expr),
+            (Stmt::VarDecl(a), Stmt::VarDecl(b)) => {
+                a.name == b.name && opt_eq(a.value.as_ref(), b.value.as_ref())
+            }
+            (Stmt::Block(a), Stmt::Block(b)) => all_eq(&a.stmts, &b.stmts),
+            (Stmt::If(a), Stmt::If(b)) => {
+                let branch_eq = |x: &Option<Box<Stmt>>, y: &Option<Box<Stmt>>| match (x, y) {
+                    (Some(x), Some(y)) => x.semantic_eq(y),
+                    (x, y) => x.is_none() && y.is_none(),
+                };
+
+                a.condition.semantic_eq(&b.condition)
+                    && branch_eq(&a.then_branch, &b.then_branch)
+                    && branch_eq(&a.else_branch, &b.else_branch)
+            }
+            (Stmt::While(a), Stmt::While(b)) => {
+                a.condition.semantic_eq(&b.condition) && a.body.semantic_eq(&b.body)
+            }
+            (Stmt::WhileLet(a), Stmt::WhileLet(b)) => {
+                a.name == b.name && a.value.semantic_eq(&b.value) && a.body.semantic_eq(&b.body)
+            }
+            (Stmt::For(a), Stmt::For(b)) => {
+                a.placeholder.name == b.placeholder.name && a.range == b.range && a.body.semantic_eq(&b.body)
+            }
+            (Stmt::FnDecl(a), Stmt::FnDecl(b)) => {
+                a.name == b.name && a.params == b.params && all_eq(&a.body, &b.body)
+            }
+            (Stmt::Return(a), Stmt::Return(b)) => opt_eq(a.value.as_ref(), b.value.as_ref()),
+            (Stmt::Import(a), Stmt::Import(b)) => a.path == b.path,
+            (Stmt::EnumDecl(a), Stmt::EnumDecl(b)) => a.name == b.name && a.variants == b.variants,
+            _ => false,
+        }
+    }
+}
+
+// On a single line, statements of a block being separated by ';'. As
+// for expressions, the output parses back to the same tree
+impl Display for Stmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn block(f: &mut std::fmt::Formatter<'_>, stmts: &[Stmt]) -> std::fmt::Result {
+            if stmts.is_empty() {
+                return write!(f, "{{}}");
+            }
+
+            write!(f, "{{ ")?;
+            for (i, stmt) in stmts.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "; ")?;
+                }
+                write!(f, "{}", stmt)?;
+            }
+            write!(f, " }}")
+        }
+
+        match self {
+            Stmt::Expr(s) => write!(f, "{}", s.expr),
+            Stmt::Print(s) => write!(f, "print {}", s.expr),
+            Stmt::VarDecl(s) => match &s.value {
+                Some(v) => write!(f, "var {} = {}", s.name, v),
+                None => write!(f, "var {}", s.name),
+            },
+            Stmt::Block(s) => block(f, &s.stmts),
+            Stmt::If(s) => {
+                write!(f, "if {} ", s.condition)?;
+                match &s.then_branch {
+                    Some(t) => write!(f, "{{ {} }}", t)?,
+                    None => write!(f, "{{}}")?,
+                }
+                // 'else if' chains are nested if statements
+                match s.else_branch.as_deref() {
+                    Some(e @ Stmt::If(_)) => write!(f, " else {}", e),
+                    Some(e) => write!(f, " else {{ {} }}", e),
+                    None => Ok(()),
+                }
+            }
+            Stmt::While(s) => write!(f, "while {} {}", s.condition, s.body),
+            Stmt::WhileLet(s) => write!(f, "while var {} = {} {}", s.name, s.value, s.body),
+            Stmt::For(s) => match s.range.end {
+                Some(end) => write!(f, "for {} in {}..{} {}", s.placeholder.name, s.range.start, end, s.body),
+                None => write!(f, "for {} in {} {}", s.placeholder.name, s.range.start, s.body),
+            },
+            Stmt::FnDecl(s) => {
+                write!(f, "fn {}({}) ", s.name, s.params.join(", "))?;
+                block(f, &s.body)
+            }
+            Stmt::Return(s) => match &s.value {
+                Some(v) => write!(f, "return {}", v),
+                None => write!(f, "return"),
+            },
+            Stmt::Import(s) => write!(f, "import \"{}\"", escape_str(&s.path)),
+            Stmt::EnumDecl(s) => write!(f, "enum {} {{ {} }}", s.name, s.variants.join(", ")),
         }
     }
 }
@@ -112,9 +292,12 @@ pub trait VisitStmt<T, U: PhyReport> {
     fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Result<T, PhyResult<U>>;
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<T, PhyResult<U>>;
     fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Result<T, PhyResult<U>>;
+    fn visit_while_let_stmt(&mut self, stmt: &WhileLetStmt) -> Result<T, PhyResult<U>>;
     fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Result<T, PhyResult<U>>;
     fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> Result<T, PhyResult<U>>;
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<T, PhyResult<U>>;
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<T, PhyResult<U>>;
+    fn visit_enum_decl_stmt(&mut self, stmt: &EnumDeclStmt) -> Result<T, PhyResult<U>>;
 }
 
 // Into
@@ -123,6 +306,7 @@ impl From<&VarDeclStmt> for Stmt {
         Self::VarDecl(VarDeclStmt {
             name: value.name.clone(),
             value: value.value.clone(),
+            doc: value.doc.clone(),
             loc: value.loc.clone(),
         })
     }
@@ -0,0 +1,238 @@
+use tools::results::Loc;
+
+use super::expr::Expr;
+use super::stmt::Stmt;
+
+// Pre-order iterators over the AST, for analyses that only look for
+// patterns and don't need a full visitor
+
+// Every statement, nested ones included (blocks, branches, bodies)
+pub fn walk_stmts(stmts: &[Stmt]) -> StmtWalker<'_> {
+    StmtWalker {
+        stack: stmts.iter().rev().collect(),
+    }
+}
+
+// Every expression of the statements, sub-expressions included
+pub fn walk_exprs(stmts: &[Stmt]) -> impl Iterator<Item = &Expr> {
+    walk_stmts(stmts).flat_map(|s| stmt_exprs(s).into_iter().flat_map(walk_expr))
+}
+
+// The expression and all its sub-expressions
+pub fn walk_expr(expr: &Expr) -> ExprWalker<'_> {
+    ExprWalker { stack: vec![expr] }
+}
+
+// Location of every statement and expression, each statement being
+// followed by its own expressions. Lets tools map the executed nodes
+// back to the source
+pub fn collect_spans(stmts: &[Stmt]) -> Vec<Loc> {
+    walk_stmts(stmts)
+        .flat_map(|s| {
+            let exprs = stmt_exprs(s).into_iter().flat_map(walk_expr).map(Expr::get_loc);
+            std::iter::once(s.get_loc()).chain(exprs)
+        })
+        .collect()
+}
+
+// Depth of the expression tree, a literal being of depth 1. Computed
+// without recursion, to be usable on trees too deep to recurse into
+pub fn expr_depth(expr: &Expr) -> usize {
+    let mut stack = vec![(expr, 1)];
+    let mut depth = 0;
+
+    while let Some((expr, d)) = stack.pop() {
+        depth = depth.max(d);
+        stack.extend(expr_children(expr).into_iter().map(|e| (e, d + 1)));
+    }
+
+    depth
+}
+
+pub struct StmtWalker<'a> {
+    stack: Vec<&'a Stmt>,
+}
+
+impl<'a> Iterator for StmtWalker<'a> {
+    type Item = &'a Stmt;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let stmt = self.stack.pop()?;
+        self.stack.extend(stmt_children(stmt).into_iter().rev());
+
+        Some(stmt)
+    }
+}
+
+pub struct ExprWalker<'a> {
+    stack: Vec<&'a Expr>,
+}
+
+impl<'a> Iterator for ExprWalker<'a> {
+    type Item = &'a Expr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let expr = self.stack.pop()?;
+        self.stack.extend(expr_children(expr).into_iter().rev());
+
+        Some(expr)
+    }
+}
+
+fn stmt_children(stmt: &Stmt) -> Vec<&Stmt> {
+    // Statements of the branches of 'if' expressions are children of the
+    // statement holding the expression
+    let mut children: Vec<&Stmt> = stmt_exprs(stmt)
+        .into_iter()
+        .flat_map(walk_expr)
+        .filter_map(|e| match e {
+            Expr::If(e) => Some(e.then_branch.stmts.iter().chain(e.else_branch.stmts.iter())),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+
+    let nested: Vec<&Stmt> = match stmt {
+        Stmt::Block(s) => s.stmts.iter().collect(),
+        Stmt::If(s) => [&s.then_branch, &s.else_branch]
+            .into_iter()
+            .flatten()
+            .map(|b| b.as_ref())
+            .collect(),
+        Stmt::While(s) => vec![&s.body],
+        Stmt::WhileLet(s) => vec![&s.body],
+        Stmt::For(s) => vec![&s.body],
+        Stmt::FnDecl(s) => s.body.iter().collect(),
+        Stmt::Expr(_)
+        | Stmt::Print(_)
+        | Stmt::VarDecl(_)
+        | Stmt::Return(_)
+        | Stmt::Import(_)
+        | Stmt::EnumDecl(_) => vec![],
+    };
+
+    children.extend(nested);
+    children
+}
+
+// Expressions directly held by the statement, not by its children
+fn stmt_exprs(stmt: &Stmt) -> Vec<&Expr> {
+    match stmt {
+        Stmt::Expr(s) => vec![&s.expr],
+        Stmt::Print(s) => vec![&s.expr],
+        Stmt::VarDecl(s) => s.value.iter().collect(),
+        Stmt::If(s) => vec![&s.condition],
+        Stmt::While(s) => vec![&s.condition],
+        Stmt::WhileLet(s) => vec![&s.value],
+        Stmt::Return(s) => s.value.iter().collect(),
+        Stmt::Block(_)
+        | Stmt::For(_)
+        | Stmt::FnDecl(_)
+        | Stmt::Import(_)
+        | Stmt::EnumDecl(_) => vec![],
+    }
+}
+
+fn expr_children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Binary(e) => vec![&e.left, &e.right],
+        Expr::Grouping(e) => vec![&e.expr],
+        Expr::Unary(e) => vec![&e.right],
+        Expr::Assign(e) => vec![&e.value],
+        Expr::Logical(e) => vec![&e.left, &e.right],
+        Expr::Call(e) => std::iter::once(e.callee.as_ref()).chain(&e.args).collect(),
+        Expr::TypeCheck(e) => vec![&e.value],
+        Expr::Get(e) => vec![&e.object],
+        Expr::Index(e) => vec![&e.object, &e.index],
+        Expr::Slice(e) => vec![&e.object, &e.start, &e.end],
+        Expr::If(e) => vec![&e.condition, &e.then_branch.value, &e.else_branch.value],
+        Expr::IntLiteral(_)
+        | Expr::RealLiteral(_)
+        | Expr::StrLiteral(_)
+        | Expr::BoolLiteral(_)
+        | Expr::NullLiteral(_)
+        | Expr::Identifier(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_spans, expr_depth, walk_exprs, walk_stmts};
+    use crate::ast::expr::Expr;
+    use crate::ast::stmt::Stmt;
+    use crate::parser::utils::lex_and_parse;
+
+    #[test]
+    fn count_int_literals() {
+        let code = "
+var a = 1 + 2
+fn add(x, y) {
+    if x > 0 {
+        return x + y * 3
+    }
+    return len(\"ab\")[4]
+}
+while a < 10 { a = a + 5 }
+for i in 0..2 { print -6 }
+print add(7, if true { 8 } else { 9.5 })";
+        let nodes = lex_and_parse(code).unwrap();
+
+        let ints: Vec<i64> = walk_exprs(&nodes)
+            .filter_map(|e| match e {
+                Expr::IntLiteral(i) => Some(i.value),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(ints, vec![1, 2, 0, 3, 4, 10, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn walk_order() {
+        let nodes = lex_and_parse("{\n    print 1\n    {\n        var a\n    }\n}\nprint 2").unwrap();
+        let kinds: Vec<&str> = walk_stmts(&nodes)
+            .map(|s| match s {
+                Stmt::Block(_) => "block",
+                Stmt::Print(_) => "print",
+                Stmt::VarDecl(_) => "var",
+                _ => "other",
+            })
+            .collect();
+
+        assert_eq!(kinds, vec!["block", "print", "block", "var", "print"]);
+
+        let nodes = lex_and_parse("-(a + b)").unwrap();
+        assert_eq!(walk_exprs(&nodes).count(), 5);
+        assert!(matches!(walk_exprs(&nodes).next(), Some(Expr::Unary(_))));
+    }
+
+    #[test]
+    fn depth() {
+        let depth = |code: &str| expr_depth(&crate::parser::parse_expression(code).unwrap());
+
+        assert_eq!(depth("1"), 1);
+        assert_eq!(depth("1 + 2"), 2);
+        assert_eq!(depth("1 + 2 + 3"), 3);
+        assert_eq!(depth("f(1, -(2 * 3))"), 5);
+    }
+
+    #[test]
+    fn spans() {
+        let code = "var a = 1 + 2\nfn f(x) {\n    return x * a\n}\nprint f(3)";
+        let nodes = lex_and_parse(code).unwrap();
+        let spans = collect_spans(&nodes);
+
+        // 4 statements and 9 expressions
+        assert_eq!(spans.len(), 13);
+        assert_eq!(spans.len(), walk_stmts(&nodes).count() + walk_exprs(&nodes).count());
+
+        let len = code.chars().count();
+        assert!(spans.iter().all(|s| s.start <= s.end && s.end <= len), "{:?}", spans);
+
+        // 'var' statement first, then its expressions
+        assert_eq!(spans[0].start, 0);
+        assert_eq!(&code[spans[1].start..spans[1].end], "1 + 2");
+        assert_eq!(&code[spans[2].start..spans[2].end], "1");
+        assert_eq!(&code[spans[12].start..spans[12].end], "3");
+    }
+}
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, path::PathBuf, thread};
 use ecow::EcoString;
 use thiserror::Error;
 use colored::*;
 
-use tools::results::{PhyReport, PhyResult, Loc};
+use tools::results::{LineDirective, PhyReport, PhyResult, Loc};
 
 
 // ----------------
@@ -19,12 +19,25 @@ pub enum LexerErr {
     #[error("string literal never closed with '\"'")]
     StringNeverClosed,
 
+    #[error("invalid escape sequence '{0}'")]
+    InvalidEscape(String),
+
     // Numbers
     #[error("expected nothing after real number declaration, found: '{0}'")]
     NoSpaceAfterNumber(char),
 
     #[error("expected numbers or nothing after '.' in number literal, found: '{0}'")]
     NonNumericDecimal(char),
+
+    #[error("'_' separator must be between two digits")]
+    MisplacedSeparator,
+
+    #[error("expected digits after exponent in number literal")]
+    MissingExponent,
+
+    // Directives
+    #[error("malformed line directive, expected: #line <number> \"<file>\"")]
+    MalformedLineDirective,
 }
 
 impl PhyReport for LexerErr {
@@ -33,7 +46,8 @@ impl PhyReport for LexerErr {
     }
 }
 
-type PhyResLex = PhyResult<LexerErr>;
+pub type PhyResLex = PhyResult<LexerErr>;
+pub type LexerRes = Result<Vec<Token>, Vec<PhyResLex>>;
 
 
 // --------
@@ -46,7 +60,10 @@ pub enum TokenKind {
     CloseParen,
     OpenBrace,
     CloseBrace,
+    OpenBracket,
+    CloseBracket,
     Comma,
+    SemiColon,
     Dot,
     Minus,
     Plus,
@@ -64,6 +81,9 @@ pub enum TokenKind {
     Less,
     LessEqual,
     DotDot,
+    DotDotEqual,
+    QuestionQuestion,
+    QuestionDot,
 
     // Literals
     Identifier,
@@ -89,17 +109,105 @@ pub enum TokenKind {
     In,
     True,
     False,
+    Import,
+    Is,
+    Enum,
+
+    // Only produced when the lexer is built with 'keep_comments'
+    Comment,
+    // '///' comment on its own line, always produced. The value is the
+    // text of the comment
+    DocComment,
 
     NewLine,
     Eof,
 }
 
 
+// Categories overlap: 'and' is both a keyword and an operator, 'true'
+// both a keyword and a literal
+impl TokenKind {
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::String
+                | TokenKind::Int
+                | TokenKind::Real
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Null
+        )
+    }
+
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Struct
+                | TokenKind::Fn
+                | TokenKind::SelfKw
+                | TokenKind::Var
+                | TokenKind::Const
+                | TokenKind::Return
+                | TokenKind::If
+                | TokenKind::Else
+                | TokenKind::And
+                | TokenKind::Or
+                | TokenKind::Null
+                | TokenKind::Print
+                | TokenKind::For
+                | TokenKind::While
+                | TokenKind::In
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Import
+                | TokenKind::Is
+                | TokenKind::Enum
+        )
+    }
+
+    pub fn is_operator(&self) -> bool {
+        self.is_binary_operator()
+            || matches!(
+                self,
+                TokenKind::Bang
+                    | TokenKind::Equal
+                    | TokenKind::Dot
+                    | TokenKind::DotDot
+                    | TokenKind::DotDotEqual
+                    | TokenKind::QuestionDot
+                    | TokenKind::Is
+            )
+    }
+
+    // Operators between two expressions. '-' is also a unary one
+    pub fn is_binary_operator(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Star
+                | TokenKind::Slash
+                | TokenKind::Modulo
+                | TokenKind::EqualEqual
+                | TokenKind::BangEqual
+                | TokenKind::Less
+                | TokenKind::LessEqual
+                | TokenKind::Greater
+                | TokenKind::GreaterEqual
+                | TokenKind::And
+                | TokenKind::Or
+                | TokenKind::QuestionQuestion
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub kind: TokenKind,
     pub value: EcoString,
-    pub loc: Loc
+    pub loc: Loc,
+    // Only preceded by spaces on its line
+    pub line_start: bool,
 }
 
 impl Display for Token {
@@ -108,6 +216,121 @@ impl Display for Token {
     }
 }
 
+impl Token {
+    // First token of its line, indentation aside. For layout sensitive
+    // tooling
+    pub fn is_at_line_start(&self) -> bool {
+        self.line_start
+    }
+
+    // Kind, value and location, for error messages that embed a token
+    pub fn describe(&self) -> String {
+        format!(
+            "{:?} '{}' at {}..{}",
+            self.kind,
+            self.value.escape_debug(),
+            self.loc.start,
+            self.loc.end
+        )
+    }
+}
+
+// Optional behaviors of the lexer. The default one is what the
+// interpreter expects, tooling can opt into the others
+#[derive(Debug, Clone)]
+struct LexerOptions {
+    keep_comments: bool,
+    line_directives: bool,
+    shebang: bool,
+    strict_escapes: bool,
+}
+
+impl Default for LexerOptions {
+    fn default() -> Self {
+        Self {
+            keep_comments: false,
+            line_directives: true,
+            shebang: true,
+            strict_escapes: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LexerBuilder {
+    options: LexerOptions,
+}
+
+impl LexerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Emits a 'Comment' token for each comment instead of skipping them
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.options.keep_comments = keep;
+        self
+    }
+
+    // Handles '#line' directives. If disabled, '#' is an unexpected token
+    pub fn line_directives(mut self, enable: bool) -> Self {
+        self.options.line_directives = enable;
+        self
+    }
+
+    // Skips a '#!' line at the start of the code
+    pub fn shebang(mut self, enable: bool) -> Self {
+        self.options.shebang = enable;
+        self
+    }
+
+    // Unknown escape sequences are errors. If disabled, they are kept
+    // verbatim in the string, backslash included
+    pub fn strict_escapes(mut self, strict: bool) -> Self {
+        self.options.strict_escapes = strict;
+        self
+    }
+
+    pub fn build(self) -> Lexer {
+        let mut lex = Lexer {
+            options: self.options,
+            ..Default::default()
+        };
+
+        lex.generate_keywords();
+        lex
+    }
+}
+
+const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("var", TokenKind::Var),
+    ("const", TokenKind::Const),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
+    ("struct", TokenKind::Struct),
+    ("self", TokenKind::SelfKw),
+    ("fn", TokenKind::Fn),
+    ("return", TokenKind::Return),
+    ("if", TokenKind::If),
+    ("else", TokenKind::Else),
+    ("and", TokenKind::And),
+    ("or", TokenKind::Or),
+    ("for", TokenKind::For),
+    ("while", TokenKind::While),
+    ("in", TokenKind::In),
+    ("null", TokenKind::Null),
+    ("print", TokenKind::Print),
+    ("import", TokenKind::Import),
+    ("is", TokenKind::Is),
+    ("enum", TokenKind::Enum),
+];
+
+// Every keyword with the token kind it is lexed into, for tools that
+// need to know them (highlighting, completion, ...)
+pub fn keywords() -> &'static [(&'static str, TokenKind)] {
+    KEYWORDS
+}
+
 #[derive(Default)]
 pub struct Lexer {
     code: Vec<char>,
@@ -115,44 +338,38 @@ pub struct Lexer {
     keywords: HashMap<String, TokenKind>,
     start: usize,
     current: usize,
+    options: LexerOptions,
+    pub line_directives: Vec<LineDirective>,
 }
 
 impl Lexer {
     pub fn new() -> Self {
-        let mut lex = Lexer::default();
+        LexerBuilder::new().build()
+    }
 
-        lex.generate_keywords();
-        lex
+    pub fn builder() -> LexerBuilder {
+        LexerBuilder::new()
     }
 
     fn generate_keywords(&mut self) {
-        let mut map: HashMap<String, TokenKind> = HashMap::new();
-
-        map.insert("var".into(), TokenKind::Var);
-        map.insert("const".into(), TokenKind::Const);
-        map.insert("true".into(), TokenKind::True);
-        map.insert("false".into(), TokenKind::False);
-        map.insert("struct".into(), TokenKind::Struct);
-        map.insert("self".into(), TokenKind::SelfKw);
-        map.insert("fn".into(), TokenKind::Fn);
-        map.insert("return".into(), TokenKind::Return);
-        map.insert("if".into(), TokenKind::If);
-        map.insert("else".into(), TokenKind::Else);
-        map.insert("and".into(), TokenKind::And);
-        map.insert("or".into(), TokenKind::Or);
-        map.insert("for".into(), TokenKind::For);
-        map.insert("while".into(), TokenKind::While);
-        map.insert("in".into(), TokenKind::In);
-        map.insert("null".into(), TokenKind::Null);
-        map.insert("print".into(), TokenKind::Print);
-
-        self.keywords = map;
+        self.keywords = keywords()
+            .iter()
+            .map(|(kw, kind)| (kw.to_string(), kind.clone()))
+            .collect();
     }
 
     pub fn tokenize(&mut self, code: &str) -> Result<&Vec<Token>, Vec<PhyResLex>> {
         self.code = code.chars().collect();
 
         let mut errors: Vec<PhyResLex> = vec![];
+
+        // Shebang is only allowed on the first line, like a comment. The
+        // new line is kept so that the next line starts a statement
+        if self.options.shebang && code.starts_with("#!") {
+            while !self.eof() && !self.at_line_break() {
+                self.eat();
+            }
+        }
         
         while !self.eof() {
             self.start = self.current;
@@ -161,6 +378,9 @@ impl Lexer {
 
             match c {
                 // Skipable char
+                // A '\r' alone ends a line like a '\n', '\r\n' is a single
+                // line break
+                '\r' if self.at() != '\n' => self.add_token(TokenKind::NewLine),
                 '\r' | '\t' | ' ' => {},
                 '\n' => self.add_token(TokenKind::NewLine),
                 // Single char tokens
@@ -168,14 +388,22 @@ impl Lexer {
                 ')' => self.add_token(TokenKind::CloseParen),
                 '{' => self.add_token(TokenKind::OpenBrace),
                 '}' => self.add_token(TokenKind::CloseBrace),
+                '[' => self.add_token(TokenKind::OpenBracket),
+                ']' => self.add_token(TokenKind::CloseBracket),
                 ',' => self.add_token(TokenKind::Comma),
+                ';' => self.add_token(TokenKind::SemiColon),
                 '.' => {
                     if self.is_at('.') {
-                        self.add_token(TokenKind::DotDot);
+                        match self.is_at('=') {
+                            true => self.add_token(TokenKind::DotDotEqual),
+                            false => self.add_token(TokenKind::DotDot),
+                        }
                     } else {
                         self.add_token(TokenKind::Dot);
                     }
                 },
+                // 'i++' and 'i--' are two tokens, recognized by the parser
+                // at the end of a statement only: '5--3' is a subtraction
                 '-' => self.add_token(TokenKind::Minus),
                 '+' => self.add_token(TokenKind::Plus),
                 '*' => self.add_token(TokenKind::Star),
@@ -218,15 +446,32 @@ impl Lexer {
 
                     self.add_token(tk);
                 },
+                '?' => {
+                    if self.is_at('?') {
+                        self.add_token(TokenKind::QuestionQuestion);
+                    } else if self.is_at('.') {
+                        self.add_token(TokenKind::QuestionDot);
+                    } else {
+                        errors.push(self.trigger_error(LexerErr::UnexpectedToken(c)))
+                    }
+                },
 
                 // Longer tokens
                 '/' => {
                     if self.is_at('/') {
-                        self.lex_comment()
+                        if self.at() == '/' && self.only_spaces_before(self.start) {
+                            self.lex_doc_comment()
+                        } else {
+                            self.lex_comment()
+                        }
                     } else {
                         self.add_token(TokenKind::Slash)
                     }
                 },
+                '#' if self.options.line_directives && self.at_line_start() => match self.lex_line_directive() {
+                    Ok(_) => {},
+                    Err(e) => errors.push(e)
+                },
                 '\"' => match self.lex_string() {
                     Ok(_) => {},
                     Err(e) => errors.push(e)
@@ -238,7 +483,7 @@ impl Lexer {
                             Ok(_) => {},
                             Err(e) => errors.push(e)
                         }
-                    } else if c.is_alphabetic() {
+                    } else if c.is_alphabetic() || c == '_' {
                         match self.lex_identifier() {
                             Ok(_) => {},
                             Err(e) => errors.push(e)
@@ -257,7 +502,8 @@ impl Lexer {
             Token {
                 kind: TokenKind::Eof,
                 value: "eof".into(),
-                loc: Loc { start: self.code.len(), end: self.code.len() + 1 }
+                loc: Loc { start: self.code.len(), end: self.code.len() + 1 },
+                line_start: self.only_spaces_before(self.code.len()),
             }
         );
 
@@ -268,69 +514,254 @@ impl Lexer {
     }
 
     fn lex_comment(&mut self) {
-        while !self.eof() && self.at() != '\n' {
+        while !self.eof() && !self.at_line_break() {
             self.eat();
         }
+
+        if self.options.keep_comments {
+            self.add_token(TokenKind::Comment);
+        }
+    }
+
+    fn lex_doc_comment(&mut self) {
+        while !self.eof() && !self.at_line_break() {
+            self.eat();
+        }
+
+        let text: String = self.code[self.start + 3..self.current].iter().collect();
+        let text = text.strip_prefix(' ').unwrap_or(&text).trim_end();
+
+        self.add_value_token(TokenKind::DocComment, text.into());
+    }
+
+    // '#line <n> "<file>"', the file being optional. No token is produced,
+    // the directive is only used to report locations
+    fn lex_line_directive(&mut self) -> Result<(), PhyResLex> {
+        let keyword: String = self.code[self.current..].iter().take(4).collect();
+        if keyword != "line" {
+            return Err(self.trigger_error(LexerErr::UnexpectedToken('#')));
+        }
+        self.current += 4;
+
+        let directive = self.parse_line_directive();
+
+        // The whole line is skipped, even on error
+        while !self.eof() && !self.at_line_break() {
+            self.eat();
+        }
+
+        match directive {
+            Some((line, file)) => {
+                let src_line = (0..self.start).filter(|i| self.is_line_break(*i)).count() + 1;
+                self.line_directives.push(LineDirective { src_line, line, file });
+
+                Ok(())
+            }
+            None => Err(PhyResult::new(LexerErr::MalformedLineDirective, Some(self.get_loc()))),
+        }
+    }
+
+    fn parse_line_directive(&mut self) -> Option<(usize, Option<String>)> {
+        if !self.is_skippable() {
+            return None;
+        }
+        self.skip_spaces();
+
+        let digits_start = self.current;
+        while self.at().is_ascii_digit() {
+            self.eat();
+        }
+        let line: String = self.code[digits_start..self.current].iter().collect();
+        let line = line.parse::<usize>().ok()?;
+
+        self.skip_spaces();
+
+        let mut file = None;
+        if self.is_at('\"') {
+            let file_start = self.current;
+            while !self.eof() && self.at() != '\"' && !self.at_line_break() {
+                self.eat();
+            }
+
+            if !self.is_at('\"') {
+                return None;
+            }
+            file = Some(self.code[file_start..self.current - 1].iter().collect());
+        }
+
+        self.skip_spaces();
+
+        match self.eof() || self.at_line_break() {
+            true => Some((line, file)),
+            false => None,
+        }
     }
 
     fn lex_string(&mut self) -> Result<(), PhyResLex> {
+        let mut value = String::new();
+        // The string is lexed until its end even after a bad escape
+        let mut invalid_escape = None;
+
         while !self.eof() && self.at() != '\"' {
-            if self.at() == '\n' {
-                self.eat();
-                self.add_token(TokenKind::NewLine);
-            } else {
-                self.eat();
+            match self.at() {
+                // Kept as is in the value
+                _ if self.at_line_break() => {
+                    value.push(self.eat());
+                    self.add_token(TokenKind::NewLine);
+                }
+                '\\' => match self.lex_escape() {
+                    Ok(c) => value.push(c),
+                    Err(e) => {
+                        invalid_escape.get_or_insert(e);
+                    }
+                },
+                _ => value.push(self.eat()),
             }
         }
 
+        // Points at the opening quote. No synchronization, the whole end
+        // of the file is part of the string
         if self.eof() {
-            return Err(self.trigger_error(LexerErr::StringNeverClosed))
+            return Err(PhyResult::new(
+                LexerErr::StringNeverClosed,
+                Some(Loc::new(self.start, self.start + 1)),
+            ))
         }
 
-        // We create the token without the surronding quotes
-        let value: String = self.code.get(self.start + 1..self.current).unwrap().iter().collect();
         // We eat the "
         self.eat();
 
+        if let Some(e) = invalid_escape {
+            return Err(e)
+        }
+
+        // The token holds the decoded value, without the surronding quotes
         self.add_value_token(TokenKind::String, value.into());
         Ok(())
     }
 
-    fn lex_number(&mut self) -> Result<(), PhyResLex> {
-        while self.at().is_numeric() {
-            self.eat();
+    // Escape sequence in a string, from the '\\'. Hex ones are '\\xNN'
+    // and unicode ones '\\u{N...}', with up to 6 digits
+    fn lex_escape(&mut self) -> Result<char, PhyResLex> {
+        let start = self.current;
+        self.eat();
+
+        // The string is never closed, reported by the caller
+        if self.eof() {
+            return Ok('\\')
         }
-        
-        if self.at() == '.' {
-            if self.next() == '.' {
-                self.add_token(TokenKind::Int);
-                self.eat();
-                self.eat();
-                self.add_token(TokenKind::DotDot);
 
-                return Ok(())
+        let decoded = match self.eat() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '\"' => Some('\"'),
+            'x' => {
+                let digits = self.eat_hex_digits(2);
+                match digits.len() {
+                    2 => u8::from_str_radix(&digits, 16).ok().map(char::from),
+                    _ => None,
+                }
             }
+            'u' if self.is_at('{') => {
+                let digits = self.eat_hex_digits(6);
+                match !digits.is_empty() && self.is_at('}') {
+                    true => u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32),
+                    false => None,
+                }
+            }
+            // Only the backslash is eaten, the char is lexed on its own
+            _ if !self.options.strict_escapes => {
+                self.current -= 1;
+                Some('\\')
+            }
+            _ => None,
+        };
+
+        decoded.ok_or_else(|| {
+            let seq: String = self.code[start..self.current].iter().collect();
+            PhyResult::new(LexerErr::InvalidEscape(seq), Some(Loc::new(start, self.current)))
+        })
+    }
+
+    fn eat_hex_digits(&mut self, max: usize) -> String {
+        let mut digits = String::new();
+
+        while digits.len() < max && self.at().is_ascii_hexdigit() {
+            digits.push(self.eat());
+        }
+
+        digits
+    }
+
+    fn lex_number(&mut self) -> Result<(), PhyResLex> {
+        self.lex_digits()?;
 
+        let mut kind = TokenKind::Int;
+        
+        // A range, the '..' is lexed on its own
+        if self.at() == '.' && self.next() != '.' {
+            kind = TokenKind::Real;
             self.eat();
 
-            if self.eof() || self.is_skippable() || self.at() == '\n' {
+            if self.eof() || self.is_skippable() || self.at_line_break() {
                // Nothing 
+            } else if self.at() == '_' {
+                return Err(self.trigger_error(LexerErr::MisplacedSeparator))
             } else if !self.at().is_numeric() {
                 return Err(self.trigger_error(LexerErr::NonNumericDecimal(self.at())))
             } else {
-                while self.at().is_numeric() {
-                    self.eat();
-                }
+                self.lex_digits()?;
+            }
+        }
 
-                // After all the numbers, we expect a white space
-                if !self.eof() && !self.is_skippable() && self.at() != '\n' {
-                    return Err(self.trigger_error(LexerErr::NoSpaceAfterNumber(self.at())))
-                }
+        if self.at() == 'e' || self.at() == 'E' {
+            kind = TokenKind::Real;
+            self.eat();
+
+            if self.at() == '+' || self.at() == '-' {
+                self.eat();
             }
-            self.add_token(TokenKind::Real);
 
-        } else {
-            self.add_token(TokenKind::Int);
+            if self.at() == '_' {
+                return Err(self.trigger_error(LexerErr::MisplacedSeparator))
+            } else if !self.at().is_numeric() {
+                return Err(self.trigger_error(LexerErr::MissingExponent))
+            }
+
+            self.lex_digits()?;
+        }
+
+        if kind == TokenKind::Real {
+            // After all the numbers, we expect a white space, a range or
+            // the end of the statement
+            let at_range = self.at() == '.' && self.next() == '.';
+            if !self.eof() && !self.is_skippable() && !self.at_line_break() && self.at() != ';' && !at_range {
+                return Err(self.trigger_error(LexerErr::NoSpaceAfterNumber(self.at())))
+            }
+        }
+
+        // Separators are only for reading, the value is kept without them
+        let value: String = self.code[self.start..self.current]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
+        self.add_value_token(kind, value.into());
+
+        Ok(())
+    }
+
+    // Eats digits and '_' separators. The first digit is already eaten
+    // and a separator must be between two digits
+    fn lex_digits(&mut self) -> Result<(), PhyResLex> {
+        while self.at().is_numeric() || self.at() == '_' {
+            if self.at() == '_' && !self.next().is_numeric() {
+                return Err(self.trigger_error(LexerErr::MisplacedSeparator))
+            }
+
+            self.eat();
         }
 
         Ok(())
@@ -376,8 +807,40 @@ impl Lexer {
         *self.code.get(self.current - 1).unwrap()
     }
 
+    // The '\r' of a '\r\n'
     fn is_skippable(&self) -> bool {
-        matches!(self.at(), ' ' | '\t' | '\r')
+        matches!(self.at(), ' ' | '\t') || (self.at() == '\r' && self.next() == '\n')
+    }
+
+    // '\n', or a '\r' alone. '\r\n' is a single line break, its '\n'
+    fn is_line_break(&self, pos: usize) -> bool {
+        match self.code.get(pos) {
+            Some('\n') => true,
+            Some('\r') => self.code.get(pos + 1) != Some(&'\n'),
+            _ => false,
+        }
+    }
+
+    fn at_line_break(&self) -> bool {
+        self.is_line_break(self.current)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.is_skippable() {
+            self.eat();
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.start == 0 || self.is_line_break(self.start - 1)
+    }
+
+    // Indentation allowed
+    fn only_spaces_before(&self, pos: usize) -> bool {
+        (0..pos)
+            .rev()
+            .take_while(|i| !self.is_line_break(*i))
+            .all(|i| matches!(self.code[i], ' ' | '\t' | '\r'))
     }
 
     fn eat(&mut self) -> char {
@@ -404,7 +867,7 @@ impl Lexer {
         // We rewind
         self.current = self.start;
         // Until white space, we skip
-        while !self.is_skippable() && self.at() != '\n' && !self.eof() {
+        while !self.is_skippable() && !self.at_line_break() && !self.eof() {
             self.current += 1;
         }
     }
@@ -415,7 +878,8 @@ impl Lexer {
         self.tokens.push(Token {
             kind,
             value: code.into(),
-            loc: self.get_loc()
+            loc: self.get_loc(),
+            line_start: self.only_spaces_before(self.start),
         });
     }
 
@@ -424,7 +888,8 @@ impl Lexer {
         self.tokens.push(Token {
             kind,
             value,
-            loc: self.get_loc()
+            loc: self.get_loc(),
+            line_start: self.only_spaces_before(self.start),
         });
     }
 
@@ -433,13 +898,64 @@ impl Lexer {
     }
 }
 
+/// Tokenizes the code with a default lexer
+///
+/// ```
+/// use frontend::lexer::{lex, TokenKind};
+///
+/// let tokens = lex("1 + 2").unwrap();
+/// let kinds: Vec<TokenKind> = tokens.into_iter().map(|t| t.kind).collect();
+///
+/// assert_eq!(
+///     kinds,
+///     [TokenKind::Int, TokenKind::Plus, TokenKind::Int, TokenKind::Eof]
+/// );
+/// ```
+pub fn lex(source: &str) -> LexerRes {
+    let mut lexer = Lexer::new();
+    lexer.tokenize(source).cloned()
+}
+
+// Each file gets its own lexer, so they can be tokenized at the same
+// time. The files are split in one chunk per available core, each lexed
+// by a thread. Results are returned in the same order as the input files
+pub fn tokenize_many(files: &[(PathBuf, String)]) -> Vec<(PathBuf, LexerRes)> {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = files.len().div_ceil(workers).max(1);
+
+    thread::scope(|s| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(path, code)| {
+                            let mut lexer = Lexer::new();
+                            (path.clone(), lexer.tokenize(code).cloned())
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("lexer thread panicked"))
+            .collect()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use ecow::EcoString;
 
-    use crate::lexer::{ LexerErr, Loc, TokenKind };
+    use std::path::PathBuf;
+
+    use crate::lexer::{ tokenize_many, LexerErr, Loc, TokenKind };
+    use tools::results::LineDirective;
 
-    use super::Lexer;
+    use super::{Lexer, Token};
 
     #[test]
     fn tokenize_single_char() {
@@ -475,7 +991,7 @@ mod tests {
 
     #[test]
     fn tokenize_double_char() {
-        let code: String = "!= <= >= == ..".into();
+        let code: String = "!= <= >= == .. ?? ?.".into();
         let mut lexer = Lexer::new(); 
         let tokens = lexer.tokenize(&code).unwrap();
 
@@ -489,6 +1005,8 @@ mod tests {
                 TokenKind::GreaterEqual,
                 TokenKind::EqualEqual,
                 TokenKind::DotDot,
+                TokenKind::QuestionQuestion,
+                TokenKind::QuestionDot,
                 TokenKind::Eof,
             ]
         );
@@ -568,6 +1086,41 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn separators_and_exponent() {
+        let cases = [
+            ("1_000", TokenKind::Int, "1000"),
+            ("1_2_3", TokenKind::Int, "123"),
+            ("1_000.000_1", TokenKind::Real, "1000.0001"),
+            ("1e5", TokenKind::Real, "1e5"),
+            ("2.5E-3", TokenKind::Real, "2.5E-3"),
+            ("1_0.5e+1_0", TokenKind::Real, "10.5e+10"),
+        ];
+
+        for (code, kind, value) in cases {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(code).unwrap();
+
+            assert_eq!(tokens[0].kind, kind, "{}", code);
+            assert_eq!(tokens[0].value, value, "{}", code);
+            assert_eq!(tokens[0].loc, Loc::new(0, code.len()), "{}", code);
+        }
+
+        for code in ["1_.0", "1._0", "1.0_e5", "1e_5", "1_", "1__0", "1e-_5", "1.5_"] {
+            let mut lexer = Lexer::new();
+            let errs = lexer.tokenize(code).err().unwrap();
+
+            assert_eq!(errs.len(), 1, "{}", code);
+            assert!(matches!(errs[0].err, LexerErr::MisplacedSeparator), "{}", code);
+        }
+
+        for code in ["1e", "1.5e+", "1ex"] {
+            let mut lexer = Lexer::new();
+            let errs = lexer.tokenize(code).err().unwrap();
+            assert!(matches!(errs[0].err, LexerErr::MissingExponent), "{}", code);
+        }
+    }
+
     #[test]
     fn string_errors() {
         let code: String = "\"foo".into();
@@ -578,6 +1131,14 @@ mod tests {
             tokens.err().unwrap()[0].err,
             LexerErr::StringNeverClosed
         ));
+
+        let code = "var a = 1\nprint \"foo\nbar baz 12.";
+        let mut lexer = Lexer::new();
+        let errs = lexer.tokenize(code).err().unwrap();
+
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(errs[0].err, LexerErr::StringNeverClosed));
+        assert_eq!(errs[0].loc, Some(Loc::new(16, 17)));
     }
 
     #[test]
@@ -613,4 +1174,392 @@ break 45+7".into();
             ]
         );
     }
+
+    #[test]
+    fn tokenize_many_files() {
+        let mut files: Vec<(PathBuf, String)> = vec![
+            ("a.rz".into(), "var a = 5\nprint a".into()),
+            ("b.rz".into(), "fn add(a, b) { return a + b }".into()),
+            ("c.rz".into(), "\"foo".into()),
+            ("d.rz".into(), "for i in 2..5 {}".into()),
+        ];
+
+        // More files than cores, shared between the threads
+        files.extend((0..500).map(|i| (format!("gen_{}.rz", i).into(), format!("var v{} = {}", i, i))));
+        assert!(tokenize_many(&[]).is_empty());
+
+        let res = tokenize_many(&files);
+        assert_eq!(res.len(), files.len());
+
+        for ((path, code), (res_path, res)) in files.iter().zip(res) {
+            assert_eq!(path, &res_path);
+
+            let mut lexer = Lexer::new();
+            match (lexer.tokenize(code), res) {
+                (Ok(seq), Ok(par)) => assert_eq!(seq, &par),
+                (Err(seq), Err(par)) => assert_eq!(seq.len(), par.len()),
+                _ => panic!("parallel and sequential lexing differ for {:?}", path),
+            }
+        }
+    }
+
+    #[test]
+    fn line_directive() {
+        let code = "var a = 1\n#line 100 \"gen.src\"\nvar b = 2\nvar c = ?\n";
+        let mut lexer = Lexer::new();
+        let errs = lexer.tokenize(code).err().unwrap();
+
+        assert_eq!(
+            lexer.line_directives,
+            vec![LineDirective { src_line: 2, line: 100, file: Some("gen.src".into()) }]
+        );
+        assert_eq!(
+            errs[0].get_report_location("main.rz", code, &lexer.line_directives),
+            Some(("gen.src".into(), 101))
+        );
+
+        // Without file, the previous one is kept
+        let code = "#line 10 \"a.src\"\n#line 50\n\n  ?";
+        let mut lexer = Lexer::new();
+        let errs = lexer.tokenize(code).err().unwrap();
+        assert_eq!(
+            errs[0].get_report_location("main.rz", code, &lexer.line_directives),
+            Some(("a.src".into(), 51))
+        );
+
+        // Errors
+        let code = "#line\n#line abc\n#line 3 \"a\" b\na #line 4";
+        let mut lexer = Lexer::new();
+        let errs = lexer.tokenize(code).err().unwrap();
+
+        assert!(matches!(errs[0].err, LexerErr::MalformedLineDirective));
+        assert!(matches!(errs[1].err, LexerErr::MalformedLineDirective));
+        assert!(matches!(errs[2].err, LexerErr::MalformedLineDirective));
+        assert!(matches!(errs[3].err, LexerErr::UnexpectedToken('#')));
+        assert!(lexer.line_directives.is_empty());
+    }
+
+    #[test]
+    fn shebang() {
+        let code = "#!/usr/bin/env raze\nvar a = 1";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+        let tk_kind: Vec<TokenKind> = tokens.iter().map(|tk| tk.kind.clone()).collect();
+
+        assert_eq!(
+            tk_kind,
+            vec![
+                TokenKind::NewLine,
+                TokenKind::Var,
+                TokenKind::Identifier,
+                TokenKind::Equal,
+                TokenKind::Int,
+                TokenKind::Eof
+            ]
+        );
+
+        // Only on the first line
+        let code = "var a = 1\n#!/usr/bin/env raze";
+        let mut lexer = Lexer::new();
+        let errs = lexer.tokenize(code).err().unwrap();
+        assert!(matches!(errs[0].err, LexerErr::UnexpectedToken('#')));
+
+        let code = " #!/usr/bin/env raze";
+        let mut lexer = Lexer::new();
+        assert!(lexer.tokenize(code).is_err());
+    }
+
+    #[test]
+    fn token_describe() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("a += 2\n").unwrap();
+
+        assert_eq!(tokens[1].describe(), "Plus '+' at 2..3");
+        assert_eq!(tokens[2].describe(), "Equal '=' at 3..4");
+        assert_eq!(tokens[4].describe(), "NewLine '\\n' at 6..7");
+    }
+
+    #[test]
+    fn builder_options() {
+        let code = "var a = 1 // the answer\n// done";
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+        assert!(tokens.iter().all(|t| t.kind != TokenKind::Comment));
+
+        let mut lexer = Lexer::builder().keep_comments(true).build();
+        let tokens = lexer.tokenize(code).unwrap();
+        let comments: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Comment)
+            .collect();
+
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].value, "// the answer");
+        assert_eq!(comments[0].loc, Loc { start: 10, end: 23 });
+        assert_eq!(comments[1].value, "// done");
+
+        let mut lexer = Lexer::builder().line_directives(false).build();
+        assert!(lexer.tokenize("#line 10 \"a.rz\"").is_err());
+
+        let mut lexer = Lexer::builder().shebang(false).build();
+        assert!(lexer.tokenize("#!/usr/bin/env raze").is_err());
+    }
+
+    #[test]
+    fn longest_match() {
+        use TokenKind::*;
+
+        let cases = [
+            ("===", vec![EqualEqual, Equal]),
+            ("!==", vec![BangEqual, Equal]),
+            ("<==", vec![LessEqual, Equal]),
+            (">==", vec![GreaterEqual, Equal]),
+            ("!!=", vec![Bang, BangEqual]),
+            ("....", vec![DotDot, DotDot]),
+            ("..==", vec![DotDotEqual, Equal]),
+            ("+++", vec![Plus, Plus, Plus]),
+            ("--3", vec![Minus, Minus, Int]),
+            ("???.", vec![QuestionQuestion, QuestionDot]),
+        ];
+
+        for (code, expected) in cases {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(code).unwrap();
+            let kinds: Vec<TokenKind> = tokens[..tokens.len() - 1].iter().map(|t| t.kind.clone()).collect();
+
+            assert_eq!(kinds, expected, "{}", code);
+        }
+    }
+
+    #[test]
+    fn number_before_range() {
+        let cases = [
+            ("1..5", vec![(TokenKind::Int, "1"), (TokenKind::DotDot, ".."), (TokenKind::Int, "5")]),
+            ("1.0..2.0", vec![(TokenKind::Real, "1.0"), (TokenKind::DotDot, ".."), (TokenKind::Real, "2.0")]),
+            ("1.5..3", vec![(TokenKind::Real, "1.5"), (TokenKind::DotDot, ".."), (TokenKind::Int, "3")]),
+            ("1..=5", vec![(TokenKind::Int, "1"), (TokenKind::DotDotEqual, "..="), (TokenKind::Int, "5")]),
+        ];
+
+        for (code, expected) in cases {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(code).unwrap();
+            let kinds: Vec<(TokenKind, &str)> = tokens[..tokens.len() - 1]
+                .iter()
+                .map(|t| (t.kind.clone(), t.value.as_str()))
+                .collect();
+
+            assert_eq!(kinds, expected, "{}", code);
+        }
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("12..3").unwrap();
+        assert_eq!(tokens[1].loc, Loc::new(2, 4));
+
+        let mut lexer = Lexer::new();
+        let errs = lexer.tokenize("1.5.3").err().unwrap();
+        assert!(matches!(errs[0].err, LexerErr::NoSpaceAfterNumber('.')));
+    }
+
+    #[test]
+    fn keyword_list() {
+        use super::keywords;
+
+        assert_eq!(keywords().len(), 20);
+        assert!(keywords().contains(&("print", TokenKind::Print)));
+        assert!(keywords().iter().all(|(_, kind)| kind.is_keyword()));
+
+        for (kw, kind) in keywords() {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(kw).unwrap();
+            assert_eq!(&tokens[0].kind, kind);
+        }
+    }
+
+    #[test]
+    fn keyword_locs() {
+        let mut lexer = Lexer::new();
+        let mut keywords: Vec<String> = lexer.keywords.keys().cloned().collect();
+        keywords.sort();
+
+        // Locations count chars, the string before checks they aren't bytes
+        let code = format!("\"é€\" {}\n\tif(else{{while", keywords.join("  \t"));
+        let chars: Vec<char> = code.chars().collect();
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let kw_tokens: Vec<&Token> = tokens.iter().filter(|t| t.kind.is_keyword()).collect();
+        assert_eq!(kw_tokens.len(), keywords.len() + 3);
+
+        for tk in kw_tokens {
+            let text: String = chars[tk.loc.start..tk.loc.end].iter().collect();
+
+            assert_eq!(text, tk.value.as_str());
+            assert_eq!(tk.loc.get_len(), tk.value.chars().count());
+            assert!(keywords.contains(&text));
+        }
+    }
+
+    #[test]
+    fn token_kind_categories() {
+        assert!(TokenKind::Int.is_literal());
+        assert!(TokenKind::String.is_literal());
+        assert!(TokenKind::Null.is_literal());
+        assert!(!TokenKind::Identifier.is_literal());
+
+        assert!(TokenKind::While.is_keyword());
+        assert!(TokenKind::True.is_keyword());
+        assert!(!TokenKind::Identifier.is_keyword());
+        assert!(!TokenKind::Plus.is_keyword());
+
+        assert!(TokenKind::Bang.is_operator());
+        assert!(TokenKind::Equal.is_operator());
+        assert!(TokenKind::Star.is_operator());
+        assert!(!TokenKind::OpenParen.is_operator());
+
+        assert!(TokenKind::Minus.is_binary_operator());
+        assert!(TokenKind::And.is_binary_operator());
+        assert!(TokenKind::QuestionQuestion.is_binary_operator());
+        assert!(!TokenKind::Bang.is_binary_operator());
+        assert!(!TokenKind::Equal.is_binary_operator());
+
+        // All the keywords are lexed from their name
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("var x = true and null").unwrap();
+        let keywords: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.kind.is_keyword())
+            .map(|t| t.value.as_str())
+            .collect();
+        assert_eq!(keywords, ["var", "true", "and", "null"]);
+    }
+
+    #[test]
+    fn doc_comment() {
+        let code = "/// Adds one\n  ///   to a\nvar a = 1 /// not a doc\n//// four";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+        let docs: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::DocComment)
+            .map(|t| t.value.as_str())
+            .collect();
+
+        assert_eq!(docs, ["Adds one", "  to a", "/ four"]);
+        assert_eq!(tokens[0].loc, Loc::new(0, 12));
+    }
+
+    #[test]
+    fn underscore_identifiers() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("_ _a a_ __ _1").unwrap();
+
+        assert!(tokens[..5].iter().all(|tk| tk.kind == TokenKind::Identifier));
+        let names: Vec<&str> = tokens[..5].iter().map(|tk| tk.value.as_str()).collect();
+        assert_eq!(names, vec!["_", "_a", "a_", "__", "_1"]);
+        assert_eq!(tokens[0].loc, Loc::new(0, 1));
+
+        // Still a separator inside numbers
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("1_000").unwrap();
+        assert_eq!(tokens[0].value, "1000");
+    }
+
+    #[test]
+    fn carriage_return_lines() {
+        let kinds = |code: &str| {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(code).unwrap();
+            tokens.iter().map(|tk| tk.kind.clone()).collect::<Vec<TokenKind>>()
+        };
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("a\rb").unwrap();
+        assert_eq!(tokens[1].kind, TokenKind::NewLine);
+        assert_eq!(tokens[1].loc, Loc::new(1, 2));
+        assert_eq!(tokens[2].value, "b");
+        assert_eq!(tokens[2].loc, Loc::new(2, 3));
+
+        use TokenKind::{Eof, Identifier, NewLine};
+        assert_eq!(kinds("a\r\nb"), vec![Identifier, NewLine, Identifier, Eof]);
+        assert_eq!(kinds("a\r\rb"), vec![Identifier, NewLine, NewLine, Identifier, Eof]);
+        assert_eq!(kinds("a\r"), vec![Identifier, NewLine, Eof]);
+
+        // Comments end with the line
+        assert_eq!(kinds("// comment\rb"), vec![NewLine, Identifier, Eof]);
+
+        // Strings keep their raw '\r'
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("\"a\rb\r\nc\"\rd").unwrap();
+        let string = tokens.iter().find(|tk| tk.kind == TokenKind::String).unwrap();
+        assert_eq!(string.value, "a\rb\r\nc");
+        assert_eq!(tokens.last().unwrap().kind, Eof);
+        assert_eq!(tokens[tokens.len() - 2].value, "d");
+        assert_eq!(tokens[tokens.len() - 3].kind, NewLine);
+
+        // Numbers end with the line too
+        assert_eq!(kinds("1.\r2."), vec![TokenKind::Real, NewLine, TokenKind::Real, Eof]);
+    }
+
+    #[test]
+    fn string_escapes() {
+        let code = r#""\x41" "\u{1F600}" "a\tb\\\"c\n" "\u{e9}t\x65""#;
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+        let values = tokens[..4].iter().map(|t| t.value.as_str()).collect::<Vec<_>>();
+        assert_eq!(values, vec!["A", "😀", "a\tb\\\"c\n", "éte"]);
+
+        let cases = [
+            (r#""\u{110000}""#, r"\u{110000}", Loc::new(1, 11)),
+            (r#""\u{D800}""#, r"\u{D800}", Loc::new(1, 9)),
+            (r#""ab\xG1""#, r"\x", Loc::new(3, 5)),
+            (r#""\u41""#, r"\u", Loc::new(1, 3)),
+            (r#""\q" + 1"#, r"\q", Loc::new(1, 3)),
+        ];
+
+        for (code, seq, loc) in cases {
+            let mut lexer = Lexer::new();
+            let errs = lexer.tokenize(code).err().unwrap();
+            assert_eq!(errs.len(), 1, "{}", code);
+            assert!(matches!(&errs[0].err, LexerErr::InvalidEscape(s) if s == seq), "{}", code);
+            assert_eq!(errs[0].loc, Some(loc), "{}", code);
+        }
+    }
+
+    #[test]
+    fn line_start() {
+        let code = "var a = 1\n  if a {\n\tprint \"x\ny\" // c\n}\n";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        let starts: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.is_at_line_start())
+            .map(|t| t.value.as_str())
+            .collect();
+
+        // The string goes on on the third line but starts on the second
+        assert_eq!(starts, vec!["var", "if", "print", "}", "eof"]);
+        assert!(!tokens[1].is_at_line_start());
+    }
+
+    #[test]
+    fn lenient_escapes() {
+        let code = r#""\q" "\x4""#;
+
+        // Strict by default
+        let mut lexer = Lexer::new();
+        let errs = lexer.tokenize(code).err().unwrap();
+        assert!(matches!(&errs[0].err, LexerErr::InvalidEscape(s) if s == r"\q"));
+
+        // Malformed known escapes are still errors
+        let mut lexer = Lexer::builder().strict_escapes(false).build();
+        let errs = lexer.tokenize(code).err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(&errs[0].err, LexerErr::InvalidEscape(s) if s == r"\x4"));
+
+        let mut lexer = Lexer::builder().strict_escapes(false).build();
+        let tokens = lexer.tokenize(r#""\q" "a\\b\n\"\z""#).unwrap();
+        let values = tokens[..2].iter().map(|t| t.value.as_str()).collect::<Vec<_>>();
+        assert_eq!(values, vec![r"\q", "a\\b\n\"\\z"]);
+    }
 }
@@ -1,5 +1,6 @@
 pub mod lexer;
 pub mod parser;
+pub mod token_stream;
 pub mod ast;
 
 extern crate tools;
@@ -1,15 +1,17 @@
 use std::rc::Rc;
 
 use colored::*;
-use ecow::EcoString;
+use ecow::{eco_format, EcoString};
 use thiserror::Error;
 
 use crate::ast::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr, LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr
+    AssignExpr, BinaryExpr, BoolLiteralExpr, CallExpr, Expr, GetExpr, GroupingExpr, IdentifierExpr, IfExpr, IfExprBranch, IndexExpr, IntLiteralExpr, LogicalExpr, NullLiteralExpr, RealLiteralExpr, SliceExpr, StrLiteralExpr, TypeCheckExpr, UnaryExpr
 };
-use crate::lexer::{Token, TokenKind};
+use crate::lexer::{lex, Token, TokenKind};
+use crate::token_stream::{TokenStream, TokenStreamErr};
+use crate::ast::walk;
 use crate::ast::stmt::{
-    BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, WhileStmt
+    BlockStmt, EnumDeclStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ImportStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, WhileLetStmt, WhileStmt
 };
 use tools::results::{PhyReport, PhyResult, Loc};
 
@@ -21,16 +23,16 @@ pub mod test_parser;
 // ----------------
 // Error managment
 // ----------------
-#[derive(Debug, Error, PartialEq)]
+#[derive(Debug, Error, PartialEq, Clone)]
 pub enum ParserErr {
     // Primary
     #[error("unexpected end of line")]
     UnexpectedEol,
 
-    #[error("missing left hand side of binary expression")]
-    MissingLhsInBinop,
+    #[error("missing left-hand side of '{0}'")]
+    MissingLhsInBinop(String),
 
-    #[error("unexpected token to parse: '{0}'")]
+    #[error("unexpected token to parse: {0}")]
     UnexpectedToken(String),
 
     #[error("error parsing int")]
@@ -46,6 +48,9 @@ pub enum ParserErr {
     #[error("missing variable name after 'var' keyword in declaration")]
     VarDeclNoName,
 
+    #[error("'{0}' is a reserved keyword and can't be used as a name")]
+    ReservedKeywordAsIdentifier(String),
+
     #[error("value assigned during declaration is incorrect: {0}")]
     IncorrectVarDeclVal(String),
 
@@ -59,6 +64,9 @@ pub enum ParserErr {
     #[error("invalid assignment target")]
     InvalidAssignTarget,
 
+    #[error("'{0}' can only be applied to a variable")]
+    InvalidIncrementTarget(String),
+
     // Assignment
     #[error("expected '}}' after block statement")]
     UnclosedBlock,
@@ -85,12 +93,21 @@ pub enum ParserErr {
     #[error("missing right expression in 'and' statement")]
     AndWithNoCond,
 
+    #[error("missing right expression after '??'")]
+    CoalesceWithNoRhs,
+
     #[error("variable declaration inside 'if' block is not allowed")]
     VarDeclInIf,
 
     #[error("'else' branch can't have a condition")]
     ElseWithCond,
 
+    #[error("'if' expression must have an 'else' branch")]
+    IfExprWithNoElse,
+
+    #[error("'if' expression branch must produce a value")]
+    IfExprEmptyBranch,
+
     // While
     #[error("'while' statement with no condition")]
     WhileWithNoCond,
@@ -98,6 +115,12 @@ pub enum ParserErr {
     #[error("missing block start '{{' after 'while' condition")]
     MissingWhileOpenBrace,
 
+    #[error("missing variable name after 'while var'")]
+    MissingVarNameWhile,
+
+    #[error("missing '=' and value after variable name in 'while var'")]
+    MissingValueWhile,
+
     // For
     #[error("missing variable name in 'for' loop")]
     MissingVarNameFor,
@@ -111,6 +134,9 @@ pub enum ParserErr {
     #[error("only 'ints' are supported as 'for' ranges")]
     NonIntForRange,
 
+    #[error("range mixes an 'int' and a 'real' bound")]
+    MixedRangeTypes,
+
     #[error("range can only be positive")]
     NegativeForRange,
 
@@ -136,6 +162,17 @@ pub enum ParserErr {
     #[error("missing comma to seperate arguments")]
     MissingArgsComma,
 
+    // Property
+    #[error("expected property name after '{0}'")]
+    MissingPropertyName(String),
+
+    // Index
+    #[error("missing ']' after index")]
+    MissingIndexCloseBracket,
+
+    #[error("missing end of slice after range operator")]
+    MissingSliceEnd,
+
     // Function declaration
     #[error("missing function name after 'fn' keyword")]
     MissingFnName,
@@ -146,18 +183,66 @@ pub enum ParserErr {
     #[error("can't have more than 255 parameters")]
     MaxFnArgs,
 
+    #[error("code nested too deeply, the limit is {0} levels")]
+    MaxDepth(usize),
+
     #[error("function paramters must be identifiers")]
     WrongFnArgType,
 
     #[error("missing '{{' before function body")]
     MissingFnOpenBrace,
 
+    // Type check
+    #[error("expected a type name after 'is'")]
+    MissingTypeName,
+
+    // Enum
+    #[error("missing enum name after 'enum' keyword")]
+    MissingEnumName,
+
+    #[error("missing '{{' before enum variants")]
+    MissingEnumOpenBrace,
+
+    #[error("enum variants must be identifiers")]
+    WrongEnumVariant,
+
+    #[error("missing comma to separate enum variants")]
+    MissingEnumComma,
+
+    #[error("enum variant '{0}' is declared twice")]
+    DuplicateEnumVariant(String),
+
+    // Import
+    #[error("expected a file path string after 'import'")]
+    MissingImportPath,
+
     // Others
     #[error("unexpected end of file")]
     UnexpectedEof,
 
-    #[error("expected token type '{0:?}', found: {1:?}")]
+    #[error("token stream must end with an end of file token")]
+    MissingEof,
+
+    #[error("unexpected '{0}' after the end of the expression")]
+    TrailingTokens(String),
+
+    // Statement terminators
+    #[error("expected {0} at the end of the statement")]
+    MissingStmtEnd(String),
+
+    #[error("statements are separated by new lines, not ';'")]
+    UnexpectedSemiColon,
+
+    #[error("expected token type '{0}', found: {1}")]
     ExpectedToken(String, String),
+
+    // Only produced by 'parse', which lexes the code itself
+    #[error("invalid code: {0}")]
+    Lexing(String),
+
+    // A warning, raised as an error in strict mode
+    #[error("{0}")]
+    Strict(ParserWarning),
 }
 
 impl PhyReport for ParserErr {
@@ -166,22 +251,209 @@ impl PhyReport for ParserErr {
     }
 }
 
-pub(crate) type PhyResParser = PhyResult<ParserErr>;
+// Code that parses but is likely a mistake. Warnings don't stop the
+// parsing, they are collected in the parser
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum ParserWarning {
+    #[error("assignment to '{0}' used as a condition, did you mean '=='? Wrap it in parentheses if it's intended")]
+    AssignInCondition(String),
+
+    #[error("real literal '{0}' can't be represented exactly, its value is rounded")]
+    RealPrecisionLoss(String),
+
+    #[error("empty '{0}' body")]
+    EmptyBody(String),
+
+    #[error("expression statement has no effect")]
+    NoEffectExpr,
+}
+
+impl PhyReport for ParserWarning {
+    fn get_err_msg(&self) -> String {
+        format!("{} {}", "Parser warning:".yellow(), self)
+    }
+}
+
+pub type PhyResParser = PhyResult<ParserErr>;
+pub type PhyResParserWarning = PhyResult<ParserWarning>;
 pub(crate) type ParserStmtRes = Result<Stmt, PhyResParser>;
 pub(crate) type ParserExprRes = Result<Expr, PhyResParser>;
 
+// Deepest nesting of parentheses, arguments, blocks and unary operators.
+// Deeper trees would overflow the stack when parsed or interpreted, as
+// they are walked recursively
+pub const MAX_DEPTH: usize = 500;
+
+/// Lexes and parses the code. Lexer errors are returned as
+/// 'ParserErr::Lexing' at the same location
+///
+/// ```
+/// use frontend::{ast::stmt::Stmt, parser::parse};
+///
+/// let stmts = parse("var a = 1\nprint a").unwrap();
+///
+/// assert_eq!(stmts.len(), 2);
+/// assert!(matches!(stmts[0], Stmt::VarDecl(_)));
+/// assert!(parse("print (1").is_err());
+/// ```
+pub fn parse(source: &str) -> Result<Vec<Stmt>, Vec<PhyResParser>> {
+    let tokens = lex(source).map_err(|errs| {
+        errs.into_iter()
+            .map(|e| PhyResult::new(ParserErr::Lexing(e.err.to_string()), e.loc))
+            .collect::<Vec<_>>()
+    })?;
+
+    let mut parser = Parser::default();
+    parser.parse(&tokens)
+}
+
+/// Lexes and parses the code as a single expression, like the
+/// one given to the CLI '-e' option
+///
+/// ```
+/// use frontend::parser::{parse_expression, ParserErr};
+///
+/// assert!(parse_expression("1 + 2").is_ok());
+///
+/// let errs = parse_expression("1 + 2 3").unwrap_err();
+/// assert_eq!(errs[0].err, ParserErr::TrailingTokens("3".into()));
+/// ```
+pub fn parse_expression(source: &str) -> Result<Expr, Vec<PhyResParser>> {
+    let tokens = lex(source).map_err(|errs| {
+        errs.into_iter()
+            .map(|e| PhyResult::new(ParserErr::Lexing(e.err.to_string()), e.loc))
+            .collect::<Vec<_>>()
+    })?;
+
+    let mut parser = Parser::default();
+    parser.parse_expression(&tokens)
+}
+
+// A real is a finite binary fraction, so printing it with enough
+// decimals gives its exact value to compare with the literal's
+fn is_exact_real(literal: &str, value: f64) -> bool {
+    // 1074 decimals are enough for the smallest subnormal
+    value.is_finite() && normalize_decimal(literal) == normalize_decimal(&format!("{:.1074}", value))
+}
+
+// Significant digits and the position of the decimal point in them,
+// so that '1.50', '015e-1' and '0.15e1' are the same
+fn normalize_decimal(number: &str) -> (String, i64) {
+    let (mantissa, exp) = match number.find(['e', 'E']) {
+        Some(idx) => (&number[..idx], number[idx + 1..].parse::<i64>().unwrap_or(0)),
+        None => (number, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits = format!("{}{}", int, frac);
+    let trimmed = digits.trim_start_matches('0');
+    let point = int.len() as i64 + exp - (digits.len() - trimmed.len()) as i64;
+    let trimmed = trimmed.trim_end_matches('0');
+
+    match trimmed.is_empty() {
+        true => (String::new(), 0),
+        false => (trimmed.to_string(), point),
+    }
+}
+
 enum FnKind {
     Fn,
 }
 
+// -------------------
+//  Infix operators
+// -------------------
+struct InfixOp {
+    kind: TokenKind,
+    // Binding power, higher binds tighter
+    bp: u8,
+    // 'and', 'or' and '??' only evaluate their right side if needed
+    logical: bool,
+    // 'a and b and c' isn't allowed
+    chainable: bool,
+    missing_rhs_err: Option<ParserErr>,
+}
+
+// 'is' isn't in the table as it's followed by a type name, not an
+// expression. It binds between equality and comparison
+const BP_IS: u8 = 5;
+
+const INFIX_OPS: [InfixOp; 14] = [
+    InfixOp { kind: TokenKind::Or, bp: 1, logical: true, chainable: false, missing_rhs_err: Some(ParserErr::OrWithNoCond) },
+    InfixOp { kind: TokenKind::QuestionQuestion, bp: 2, logical: true, chainable: true, missing_rhs_err: Some(ParserErr::CoalesceWithNoRhs) },
+    InfixOp { kind: TokenKind::And, bp: 3, logical: true, chainable: false, missing_rhs_err: Some(ParserErr::AndWithNoCond) },
+    InfixOp { kind: TokenKind::EqualEqual, bp: 4, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::BangEqual, bp: 4, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::Less, bp: 6, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::LessEqual, bp: 6, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::Greater, bp: 6, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::GreaterEqual, bp: 6, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::Minus, bp: 7, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::Plus, bp: 7, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::Star, bp: 8, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::Slash, bp: 8, logical: false, chainable: true, missing_rhs_err: None },
+    InfixOp { kind: TokenKind::Modulo, bp: 8, logical: false, chainable: true, missing_rhs_err: None },
+];
+
 // ---------
 //  Parsing
 // ---------
+// What ends a statement. Statements ending with a block, like 'if' or
+// 'fn', never need one
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum StatementTerminator {
+    Newline,
+    Semicolon,
+    #[default]
+    Either,
+}
+
+impl StatementTerminator {
+    fn describe(&self) -> &'static str {
+        match self {
+            StatementTerminator::Newline => "a new line",
+            StatementTerminator::Semicolon => "';'",
+            StatementTerminator::Either => "a new line or ';'",
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Parser<'a> {
-    tokens: &'a [Token],
+    stream: TokenStream<'a>,
     start_loc: usize,
-    current: usize,
+    pub warnings: Vec<PhyResParserWarning>,
+    // Integer literals too big for an int are parsed as reals
+    pub int_overflow_to_real: bool,
+    // Warnings are reported as errors, failing the parsing
+    pub strict: bool,
+    // Warns about real literals rounded when stored
+    pub warn_precision_loss: bool,
+    // Warns about control flow bodies without statements, scoping
+    // blocks can be empty
+    pub warn_empty_bodies: bool,
+    // Warns about expression statements without any call or assignment
+    pub warn_no_effect: bool,
+    pub terminator: StatementTerminator,
+    // Doc comments skipped since the last declaration
+    pending_doc: Option<EcoString>,
+    // Current recursion depth of the parsing
+    nesting: usize,
+    // Deepest nesting reached during the parsing
+    max_nesting: usize,
+}
+
+// Figures about a parsing, for tooling
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ParseStats {
+    // Tokens consumed, the final Eof excluded
+    pub tokens: usize,
+    // Statements and expressions of the statements successfully parsed
+    pub nodes: usize,
+    // Deepest recursion of the parser, the one limited by MAX_DEPTH
+    pub max_depth: usize,
+    // Errors the parser recovered from to go on parsing
+    pub errors: usize,
 }
 
 // TODO: Faire des localisation plus specifique. PAr exemple, si on parse :
@@ -191,7 +463,17 @@ pub struct Parser<'a> {
 //  remonter.
 impl<'a> Parser<'a> {
     pub fn parse(&mut self, tokens: &'a [Token]) -> Result<Vec<Stmt>, Vec<PhyResParser>> {
-        self.tokens = tokens;
+        self.parse_with_stats(tokens).0
+    }
+
+    pub fn parse_with_stats(
+        &mut self,
+        tokens: &'a [Token],
+    ) -> (Result<Vec<Stmt>, Vec<PhyResParser>>, ParseStats) {
+        if let Err(e) = self.set_tokens(tokens) {
+            return (Err(e), ParseStats::default());
+        }
+        self.max_nesting = 0;
 
         let mut stmts: Vec<Stmt> = vec![];
         let mut errors: Vec<PhyResParser> = vec![];
@@ -204,33 +486,165 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            match self.parse_declarations() {
+            match self
+                .parse_declarations()
+                .and_then(|stmt| self.end_stmt(&stmt).map(|_| stmt))
+            {
                 Ok(stmt) => stmts.push(stmt),
                 Err(e) => errors.push(e),
             }
         }
 
+        // The last expression is the value of the program
+        if let Some(Stmt::Expr(last)) = stmts.last() {
+            self.keep_value(last);
+        }
+
+        let stats = ParseStats {
+            tokens: self.stream.position(),
+            nodes: walk::walk_stmts(&stmts).count() + walk::walk_exprs(&stmts).count(),
+            max_depth: self.max_nesting,
+            errors: errors.len(),
+        };
+
+        self.promote_warnings(&mut errors);
+
         if !errors.is_empty() {
-            return Err(errors);
+            Self::sort_errors(&mut errors);
+            return (Err(errors), stats);
         }
 
-        Ok(stmts)
+        (Ok(stmts), stats)
+    }
+
+    // Parses a single expression, surrounded by new lines at most. Any
+    // token left after it is an error instead of another statement
+    pub fn parse_expression(&mut self, tokens: &'a [Token]) -> Result<Expr, Vec<PhyResParser>> {
+        self.set_tokens(tokens)?;
+        self.skip_new_lines();
+
+        let mut errors: Vec<PhyResParser> = vec![];
+
+        let expr = match self.parse_expr() {
+            Ok(expr) => {
+                self.skip_new_lines();
+
+                if !self.eof() {
+                    errors.push(PhyResult::new(
+                        ParserErr::TrailingTokens(self.at().value.to_string()),
+                        Some(self.at().loc.clone()),
+                    ));
+                }
+
+                Some(expr)
+            }
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        };
+
+        self.promote_warnings(&mut errors);
+        Self::sort_errors(&mut errors);
+
+        match expr {
+            Some(expr) if errors.is_empty() => Ok(expr),
+            _ => Err(errors),
+        }
+    }
+
+    fn set_tokens(&mut self, tokens: &'a [Token]) -> Result<(), Vec<PhyResParser>> {
+        // The parser always expects a token to look at, Eof being the last
+        self.stream = TokenStream::new(tokens)
+            .map_err(|e| vec![PhyResult::new(ParserErr::MissingEof, e.loc)])?;
+
+        Ok(())
+    }
+
+    // In strict mode, warnings are moved to the errors
+    fn promote_warnings(&mut self, errors: &mut Vec<PhyResParser>) {
+        if self.strict {
+            errors.extend(
+                self.warnings
+                    .drain(..)
+                    .map(|w| PhyResult::new(ParserErr::Strict(w.err), w.loc)),
+            );
+        }
+    }
+
+    // Errors are reported in source order, whatever the order in which
+    // they were found
+    fn sort_errors(errors: &mut [PhyResParser]) {
+        errors.sort_by_key(|e| e.loc.as_ref().map(|l| l.start));
+    }
+
+    // Checks what follows a statement against the terminator policy.
+    // The new lines after the statement are already skipped
+    fn end_stmt(&mut self, stmt: &Stmt) -> Result<(), PhyResParser> {
+        if self.is_at(TokenKind::SemiColon) {
+            if self.terminator == StatementTerminator::Newline {
+                let loc = self.at().loc.clone();
+                self.synchronize();
+
+                return Err(PhyResult::new(ParserErr::UnexpectedSemiColon, Some(loc)));
+            }
+
+            self.eat()?;
+            self.skip_new_lines();
+
+            return Ok(());
+        }
+
+        let block_like = matches!(
+            stmt,
+            Stmt::Block(_) | Stmt::If(_) | Stmt::While(_) | Stmt::WhileLet(_) | Stmt::For(_) | Stmt::FnDecl(_) | Stmt::EnumDecl(_)
+        );
+        let after_new_line = matches!(self.prev().kind, TokenKind::NewLine | TokenKind::DocComment);
+        let at_end = self.eof() || self.is_at(TokenKind::CloseBrace);
+
+        let ended = match self.terminator {
+            StatementTerminator::Semicolon => block_like,
+            _ => block_like || after_new_line || at_end,
+        };
+
+        if !ended {
+            // A token left on the line of an expression, like the second
+            // '5' of '5 5', isn't a forgotten separator but a stray token
+            let err = match stmt {
+                Stmt::Expr(_) if !after_new_line && !at_end => {
+                    ParserErr::UnexpectedToken(self.at().describe())
+                }
+                _ => ParserErr::MissingStmtEnd(self.terminator.describe().into()),
+            };
+            let loc = self.at().loc.clone();
+            self.synchronize();
+
+            return Err(PhyResult::new(err, Some(loc)));
+        }
+
+        Ok(())
     }
 
     fn parse_declarations(&mut self) -> ParserStmtRes {
-        match self.at().kind {
+        let doc = self.pending_doc.take();
+
+        let mut stmt = match self.at().kind {
             TokenKind::Var => self.parse_var_declaration(),
             _ => self.parse_stmt(),
+        }?;
+
+        match &mut stmt {
+            Stmt::VarDecl(s) => s.doc = doc,
+            Stmt::FnDecl(s) => s.doc = doc,
+            _ => {}
         }
+
+        Ok(stmt)
     }
 
     fn parse_var_declaration(&mut self) -> ParserStmtRes {
         self.expect(TokenKind::Var)?;
-        let name = self
-            .expect(TokenKind::Identifier)
-            .map_err(|_| self.trigger_error(ParserErr::VarDeclNoName, true))?
-            .value
-            .clone();
+        let name = self.expect_name(ParserErr::VarDeclNoName)?;
 
         let mut value: Option<Expr> = None;
 
@@ -254,17 +668,15 @@ impl<'a> Parser<'a> {
                     },
                 }
             }
-            TokenKind::NewLine | TokenKind::Eof => {}
+            TokenKind::NewLine | TokenKind::SemiColon | TokenKind::CloseBrace | TokenKind::Eof => {}
             _ => return Err(self.trigger_error(ParserErr::WrongRhsVarDecl, true)),
         }
 
+        // Skipping the new lines moves the start of the location
+        let loc = self.get_loc();
         self.skip_new_lines();
 
-        Ok(Stmt::VarDecl(VarDeclStmt {
-            name,
-            value,
-            loc: self.get_loc(),
-        }))
+        Ok(Stmt::VarDecl(VarDeclStmt { name, value, doc: None, loc }))
     }
 
     fn parse_stmt(&mut self) -> ParserStmtRes {
@@ -276,6 +688,8 @@ impl<'a> Parser<'a> {
             TokenKind::For => self.parse_for_stmt(),
             TokenKind::Fn => self.parse_fn_decl_stmt(FnKind::Fn),
             TokenKind::Return => self.parse_return_stmt(),
+            TokenKind::Import => self.parse_import_stmt(),
+            TokenKind::Enum => self.parse_enum_decl_stmt(),
             _ => self.parse_expr_stmt(),
         };
 
@@ -285,38 +699,57 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_print_stmt(&mut self) -> ParserStmtRes {
-        self.expect(TokenKind::Print)?;
+        let start = self.start_loc;
+        let keyword_loc = self.expect(TokenKind::Print)?.loc;
 
+        // The expression starts after the keyword
+        self.start_loc = self.at().loc.start;
         let expr = self.parse_expr()?;
 
         Ok(Stmt::Print(PrintStmt {
             expr,
-            loc: self.get_loc(),
+            keyword_loc,
+            loc: Loc::new(start, self.at().loc.start),
         }))
     }
 
     fn parse_block_stmt(&mut self) -> ParserStmtRes {
+        let start = self.start_loc;
         self.expect_and_skip(TokenKind::OpenBrace)?;
 
         let stmts = self.parse_block()?;
+        let loc = Loc::new(start, self.prev().loc.end);
+        self.skip_new_lines();
 
-        Ok(Stmt::Block(BlockStmt {
-            stmts,
-            loc: self.get_loc(),
-        }))
+        Ok(Stmt::Block(BlockStmt { stmts, loc }))
     }
 
+    // Stops right after the closing brace, so that the caller can get
+    // its location before skipping the new lines
     fn parse_block(&mut self) -> Result<Vec<Stmt>, PhyResParser> {
+        self.nested(Self::parse_block_content)
+    }
+
+    fn parse_block_content(&mut self) -> Result<Vec<Stmt>, PhyResParser> {
+        let stmts = self.parse_stmts_until_brace()?;
+
+        self.expect(TokenKind::CloseBrace)
+            .map_err(|_| self.trigger_error(ParserErr::UnclosedBlock, true))?;
+
+        Ok(stmts)
+    }
+
+    // Stops before the closing brace
+    fn parse_stmts_until_brace(&mut self) -> Result<Vec<Stmt>, PhyResParser> {
         let mut stmts: Vec<Stmt> = vec![];
 
         while !self.is_at(TokenKind::CloseBrace) && !self.eof() {
-            stmts.push(self.parse_declarations()?);
+            let stmt = self.parse_declarations()?;
+            self.end_stmt(&stmt)?;
+            stmts.push(stmt);
             self.skip_new_lines();
         }
 
-        self.expect_and_skip(TokenKind::CloseBrace)
-            .map_err(|_| self.trigger_error(ParserErr::UnclosedBlock, true))?;
-
         Ok(stmts)
     }
 
@@ -325,13 +758,21 @@ impl<'a> Parser<'a> {
         self.is_at_brace_or_end_of(ParserErr::IfWithNoCond)?;
 
         let condition = self.parse_expr()?;
+        self.check_condition(&condition);
+        // Branches move the start of the location, the statement is
+        // located at its condition
+        let loc = self.get_loc();
 
+        self.skip_new_lines();
+        let open = self.at().loc.start;
         self.skip_expect_and_skip(TokenKind::OpenBrace)
             .map_err(|_| self.trigger_error(ParserErr::MissingIfOpenBrace, true))?;
 
         let mut then_branch = None;
 
-        if !self.is_at(TokenKind::CloseBrace) {
+        if self.is_at(TokenKind::CloseBrace) {
+            self.warn_empty_body("if", Loc::new(open, self.at().loc.end));
+        } else {
             if self.is_at(TokenKind::Var) {
                 return Err(self.trigger_error(ParserErr::VarDeclInIf, true));
             }
@@ -347,13 +788,29 @@ impl<'a> Parser<'a> {
         if self.is_at(TokenKind::Else) {
             self.eat()?;
             self.skip_new_lines();
+
+            // 'else if' is an if statement as the else branch, so that
+            // chains are nested if statements
+            if self.is_at(TokenKind::If) {
+                else_branch = Some(Box::new(self.parse_if_stmt()?));
+
+                return Ok(Stmt::If(IfStmt {
+                    condition,
+                    then_branch,
+                    else_branch,
+                    loc,
+                }));
+            }
+
             self.is_not_at_brace_or_end_of(ParserErr::ElseWithCond)?;
 
+            let open = self.at().loc.start;
             self.expect_and_skip(TokenKind::OpenBrace)
                 .map_err(|_| self.trigger_error(ParserErr::MissingElseOpenBrace, true))?;
 
             match self.at().kind {
                 TokenKind::CloseBrace => {
+                    self.warn_empty_body("else", Loc::new(open, self.at().loc.end));
                     self.eat()?;
                 }
                 _ => {
@@ -369,7 +826,7 @@ impl<'a> Parser<'a> {
             condition,
             then_branch,
             else_branch,
-            loc: self.get_loc(),
+            loc,
         }))
     }
 
@@ -377,20 +834,46 @@ impl<'a> Parser<'a> {
         self.eat()?;
         self.is_at_brace_or_end_of(ParserErr::WhileWithNoCond)?;
 
+        if self.is_at(TokenKind::Var) {
+            return self.parse_while_let_stmt();
+        }
+
         let condition = self.parse_expr()?;
+        self.check_condition(&condition);
+        let loc = self.get_loc();
         self.skip_new_lines();
 
         if !self.is_at(TokenKind::OpenBrace) {
             return Err(self.trigger_error(ParserErr::MissingWhileOpenBrace, true));
         }
 
-        let body = Box::new(self.parse_stmt()?);
+        let body = Box::new(self.parse_body("while")?);
 
-        Ok(Stmt::While(WhileStmt {
-            condition,
-            body,
-            loc: self.get_loc(),
-        }))
+        Ok(Stmt::While(WhileStmt { condition, body, loc }))
+    }
+
+    fn parse_while_let_stmt(&mut self) -> ParserStmtRes {
+        self.eat()?;
+
+        let name = self
+            .expect(TokenKind::Identifier)
+            .map_err(|_| self.trigger_error(ParserErr::MissingVarNameWhile, true))?
+            .value;
+
+        self.expect(TokenKind::Equal)
+            .map_err(|_| self.trigger_error(ParserErr::MissingValueWhile, true))?;
+
+        let value = self.parse_expr()?;
+        let loc = self.get_loc();
+        self.skip_new_lines();
+
+        if !self.is_at(TokenKind::OpenBrace) {
+            return Err(self.trigger_error(ParserErr::MissingWhileOpenBrace, true));
+        }
+
+        let body = Box::new(self.parse_body("while")?);
+
+        Ok(Stmt::WhileLet(WhileLetStmt { name, value, body, loc }))
     }
 
     fn parse_for_stmt(&mut self) -> ParserStmtRes {
@@ -401,7 +884,7 @@ impl<'a> Parser<'a> {
             .map_err(|_| self.trigger_error(ParserErr::MissingVarNameFor, true))?
             .value;
 
-        let placeholder = VarDeclStmt { name: var_name, value: None, loc: self.get_loc() };
+        let placeholder = VarDeclStmt { name: var_name, value: None, doc: None, loc: self.get_loc() };
 
         self.expect(TokenKind::In)
             .map_err(|_| self.trigger_error(ParserErr::MissingInFor, true))?;
@@ -415,6 +898,23 @@ impl<'a> Parser<'a> {
             return Err(self.trigger_error(ParserErr::NegativeForRange, true))
         }
 
+        let range_start = self.at().loc.start;
+
+        // A real start followed by an int end is reported on the range
+        if self.is_at(TokenKind::Real) {
+            self.eat()?;
+
+            if self.is_at(TokenKind::DotDot) {
+                self.eat()?;
+
+                if self.is_at(TokenKind::Int) {
+                    return Err(self.mixed_range_error(range_start))
+                }
+            }
+
+            return Err(self.trigger_error(ParserErr::NonIntForRange, true))
+        }
+
         let start = self
             .expect(TokenKind::Int)
             .map_err(|_| self.trigger_error(ParserErr::NonIntForRange, true))?
@@ -428,6 +928,10 @@ impl<'a> Parser<'a> {
 
             self.is_at_brace_or_end_of(ParserErr::MissingEndForRange)?;
 
+            if self.is_at(TokenKind::Real) {
+                return Err(self.mixed_range_error(range_start))
+            }
+
             end = Some(
                 self.expect(TokenKind::Int)
                     .map_err(|_| self.trigger_error(ParserErr::NonIntForRange, true))?
@@ -446,7 +950,7 @@ impl<'a> Parser<'a> {
             return Err(self.trigger_error(ParserErr::MissingForOpenBrace, true))
         }
 
-        let body = Box::new(self.parse_stmt()?);
+        let body = Box::new(self.parse_body("for")?);
 
         Ok(Stmt::For(ForStmt {
             placeholder,
@@ -456,12 +960,30 @@ impl<'a> Parser<'a> {
         }))
     }
 
-    fn parse_fn_decl_stmt(&mut self, kind: FnKind) -> ParserStmtRes {
+    // Body block of a loop
+    fn parse_body(&mut self, kind: &str) -> ParserStmtRes {
+        let body = self.parse_stmt()?;
+
+        if let Stmt::Block(b) = &body {
+            if b.stmts.is_empty() {
+                self.warn_empty_body(kind, b.loc.clone());
+            }
+        }
+
+        Ok(body)
+    }
+
+    fn warn_empty_body(&mut self, kind: &str, loc: Loc) {
+        if self.warn_empty_bodies {
+            self.warnings.push(PhyResult::new(ParserWarning::EmptyBody(kind.into()), Some(loc)));
+        }
+    }
+
+    fn parse_fn_decl_stmt(&mut self, _kind: FnKind) -> ParserStmtRes {
+        let start = self.start_loc;
         self.eat()?;
 
-        let name = self.expect(TokenKind::Identifier)
-            .map_err(|_| self.trigger_error(ParserErr::MissingFnName, true))?
-            .value;
+        let name = self.expect_name(ParserErr::MissingFnName)?;
 
         self.expect(TokenKind::OpenParen)
             .map_err(|_| self.trigger_error(ParserErr::NoOpenParenAfterFnName, true))?;
@@ -475,10 +997,7 @@ impl<'a> Parser<'a> {
                     return Err(self.trigger_error(ParserErr::MaxFnArgs, true))
                 }
 
-                params.push(self.expect(TokenKind::Identifier)
-                    .map_err(|_| self.trigger_error(ParserErr::WrongFnArgType, true))?
-                    .value
-                );
+                params.push(self.expect_name(ParserErr::WrongFnArgType)?);
 
                 self.skip_new_lines();
 
@@ -506,12 +1025,20 @@ impl<'a> Parser<'a> {
         self.skip_new_lines();
 
         let body = Rc::new(self.parse_block()?);
+        let loc = Loc::new(start, self.prev().loc.end);
+
+        // Implicitly returned
+        if let Some(Stmt::Expr(last)) = body.last() {
+            self.keep_value(last);
+        }
+        self.skip_new_lines();
 
         Ok(Stmt::FnDecl(FnDeclStmt {
             name,
             params: Rc::new(params),
             body,
-            loc: self.get_loc(),
+            doc: None,
+            loc,
         }))
     }
 
@@ -519,28 +1046,123 @@ impl<'a> Parser<'a> {
         let _ = self.eat();
 
         let mut value = None;
-        if !self.is_at(TokenKind::NewLine) {
+        if !matches!(
+            self.at().kind,
+            TokenKind::NewLine | TokenKind::SemiColon | TokenKind::CloseBrace | TokenKind::Eof
+        ) {
             value = Some(self.parse_expr()?);
         }
         
         Ok(Stmt::Return(ReturnStmt { value, loc: self.get_loc() }))
     }
 
+    fn parse_import_stmt(&mut self) -> ParserStmtRes {
+        self.eat()?;
+
+        let path = self
+            .expect(TokenKind::String)
+            .map_err(|_| self.trigger_error(ParserErr::MissingImportPath, true))?
+            .value;
+
+        Ok(Stmt::Import(ImportStmt { path, loc: self.get_loc() }))
+    }
+
+    fn parse_enum_decl_stmt(&mut self) -> ParserStmtRes {
+        self.eat()?;
+
+        let name = self
+            .expect(TokenKind::Identifier)
+            .map_err(|_| self.trigger_error(ParserErr::MissingEnumName, true))?
+            .value;
+
+        self.skip_expect_and_skip(TokenKind::OpenBrace)
+            .map_err(|_| self.trigger_error(ParserErr::MissingEnumOpenBrace, true))?;
+
+        let mut variants: Vec<EcoString> = vec![];
+        while !self.is_at(TokenKind::CloseBrace) {
+            let variant = self
+                .expect(TokenKind::Identifier)
+                .map_err(|_| self.trigger_error(ParserErr::WrongEnumVariant, true))?
+                .value;
+
+            if variants.contains(&variant) {
+                return Err(self.trigger_error(
+                    ParserErr::DuplicateEnumVariant(variant.to_string()),
+                    true,
+                ));
+            }
+            variants.push(variant);
+
+            self.skip_new_lines();
+
+            if self.is_at(TokenKind::Comma) {
+                self.eat()?;
+                self.skip_new_lines();
+            } else if !self.is_at(TokenKind::CloseBrace) {
+                return Err(self.trigger_error(ParserErr::MissingEnumComma, true));
+            }
+        }
+
+        self.eat()?;
+
+        Ok(Stmt::EnumDecl(EnumDeclStmt { name, variants, loc: self.get_loc() }))
+    }
+
     fn parse_expr_stmt(&mut self) -> ParserStmtRes {
-        let expr = self.parse_expr()?;
+        let mut expr = self.parse_expr()?;
+
+        // 'i++' and 'i--' are sugar for 'i = i + 1' and 'i = i - 1'
+        if self.is_at_postfix_step() {
+            let op_tk = self.eat()?.clone();
+            let op_loc = op_tk.loc.merge(&self.eat()?.loc);
+
+            let Expr::Identifier(ident) = expr else {
+                return Err(self.trigger_error(
+                    ParserErr::InvalidIncrementTarget(format!("{0}{0}", op_tk.value)),
+                    true,
+                ));
+            };
+
+            let operator = op_tk.value.clone();
+
+            expr = Expr::Assign(AssignExpr {
+                name: ident.name.clone(),
+                value: Box::new(Expr::Binary(BinaryExpr {
+                    left: Box::new(Expr::Identifier(ident)),
+                    operator,
+                    right: Box::new(Expr::IntLiteral(IntLiteralExpr { value: 1, loc: op_loc })),
+                    loc: self.get_loc(),
+                })),
+                loc: self.get_loc(),
+            });
+        }
 
-        Ok(Stmt::Expr(ExprStmt {
-            expr,
-            loc: self.get_loc(),
-        }))
+        let loc = self.get_loc();
+
+        // Statements of the 'if' expression branches aren't sub-expressions,
+        // they are assumed to have an effect
+        let has_effect = |e: &Expr| match e {
+            Expr::Call(_) | Expr::Assign(_) => true,
+            Expr::If(e) => !e.then_branch.stmts.is_empty() || !e.else_branch.stmts.is_empty(),
+            _ => false,
+        };
+
+        if self.warn_no_effect && !walk::walk_expr(&expr).any(has_effect) {
+            self.warnings.push(PhyResult::new(ParserWarning::NoEffectExpr, Some(loc.clone())));
+        }
+
+        Ok(Stmt::Expr(ExprStmt { expr, loc }))
     }
 
+    // Chained operators are parsed in a loop and don't count in the
+    // nesting, only parentheses, arguments, blocks and unary operators do
     fn parse_expr(&mut self) -> ParserExprRes {
-        self.parse_assign()
+        self.nested(Self::parse_assign)
     }
 
     fn parse_assign(&mut self) -> ParserExprRes {
-        let assigne = self.parse_or()?;
+        let target_loc = self.at().loc.clone();
+        let assigne = self.parse_binary(0)?;
 
         if self.is_at(TokenKind::Equal) {
             self.eat()?;
@@ -549,8 +1171,10 @@ impl<'a> Parser<'a> {
             if let Expr::Identifier(e) = assigne {
                 return Ok(Expr::Assign(AssignExpr {
                     name: e.name.clone(),
+                    // Sub-expressions start at the statement start, only
+                    // the end of the value is kept
+                    loc: Loc::new(target_loc.start, value.get_loc().end),
                     value: Box::new(value),
-                    loc: self.get_loc(),
                 }));
             } else {
                 return Err(self.trigger_error(ParserErr::InvalidAssignTarget, true));
@@ -560,142 +1184,108 @@ impl<'a> Parser<'a> {
         Ok(assigne)
     }
 
-    fn parse_or(&mut self) -> ParserExprRes {
-        let left = self.parse_and()?;
+    // Precedence climbing over the infix operators table. Operands are
+    // parsed by 'parse_unary', an operator is only taken if it binds at
+    // least as tight as 'min_bp'
+    fn parse_binary(&mut self, min_bp: u8) -> ParserExprRes {
+        let mut expr = self.parse_unary()?;
 
-        if self.is_at(TokenKind::Or) {
-            self.eat()?;
+        // Once a non chainable operator or a type check has been parsed,
+        // operators binding as tight as it can't follow
+        let mut max_bp = u8::MAX;
 
-            if self.is_at(TokenKind::OpenBrace)
-                || self.is_at(TokenKind::Eof)
-                || self.is_at(TokenKind::NewLine)
-            {
-                return Err(self.trigger_error(ParserErr::OrWithNoCond, true));
-            }
+        loop {
+            if self.is_at(TokenKind::Is) {
+                if BP_IS < min_bp || BP_IS >= max_bp {
+                    break;
+                }
 
-            let right = self.parse_and()?;
+                expr = self.parse_type_check(expr)?;
+                max_bp = BP_IS;
+                continue;
+            }
 
-            return Ok(Expr::Logical(LogicalExpr {
-                left: Box::new(left),
-                operator: EcoString::from("or"),
-                right: Box::new(right),
-                loc: self.get_loc(),
-            }));
-        }
+            if self.is_at_postfix_step() {
+                break;
+            }
 
-        Ok(left)
-    }
+            let Some(op) = INFIX_OPS.iter().find(|op| self.is_at(op.kind.clone())) else {
+                break;
+            };
 
-    fn parse_and(&mut self) -> ParserExprRes {
-        let left = self.parse_equality()?;
+            if op.bp < min_bp || op.bp >= max_bp {
+                break;
+            }
 
-        if self.is_at(TokenKind::And) {
-            self.eat()?;
+            let operator = self.eat()?.value.clone();
 
-            if self.is_at(TokenKind::OpenBrace)
-                || self.is_at(TokenKind::Eof)
-                || self.is_at(TokenKind::NewLine)
-            {
-                return Err(self.trigger_error(ParserErr::AndWithNoCond, true));
+            // A line ending with an operator goes on with the next one. A
+            // line starting with an operator is a new statement
+            while self.is_at(TokenKind::NewLine) {
+                self.eat()?;
             }
 
-            let right = self.parse_equality()?;
-
-            return Ok(Expr::Logical(LogicalExpr {
-                left: Box::new(left),
-                operator: EcoString::from("and"),
-                right: Box::new(right),
-                loc: self.get_loc(),
-            }));
-        }
-
-        Ok(left)
-    }
-
-    fn parse_equality(&mut self) -> ParserExprRes {
-        let mut expr = self.parse_comparison()?;
-
-        while self.is_at(TokenKind::EqualEqual) || self.is_at(TokenKind::BangEqual) {
-            let operator = self.eat()?.value.clone();
-            let right = self.parse_comparison()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                loc: self.get_loc(),
-            });
-        }
-
-        Ok(expr)
-    }
-
-    fn parse_comparison(&mut self) -> ParserExprRes {
-        let mut expr = self.parse_term()?;
-
-        while self.is_at(TokenKind::Less)
-            || self.is_at(TokenKind::LessEqual)
-            || self.is_at(TokenKind::Greater)
-            || self.is_at(TokenKind::GreaterEqual)
-        {
-            let operator = self.eat()?.value.clone();
-            let right = self.parse_term()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                loc: self.get_loc(),
-            });
-        }
-
-        Ok(expr)
-    }
-
-    fn parse_term(&mut self) -> ParserExprRes {
-        let mut expr = self.parse_factor()?;
+            if let Some(err) = &op.missing_rhs_err {
+                if self.is_at(TokenKind::OpenBrace) || self.is_at(TokenKind::Eof) {
+                    return Err(self.trigger_error(err.clone(), true));
+                }
+            }
 
-        while self.is_at(TokenKind::Minus) || self.is_at(TokenKind::Plus) {
-            let operator = self.eat()?.value.clone();
-            let right = self.parse_factor()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                loc: self.get_loc(),
-            });
+            // All operators are left associative
+            let right = self.parse_binary(op.bp + 1)?;
+
+            // Spans the operands only, the expression can be nested in
+            // a grouping or a call started before
+            let loc = expr.get_loc().merge(&right.get_loc());
+
+            expr = match op.logical {
+                true => Expr::Logical(LogicalExpr {
+                    left: Box::new(expr),
+                    operator,
+                    right: Box::new(right),
+                    loc,
+                }),
+                false => Expr::Binary(BinaryExpr {
+                    left: Box::new(expr),
+                    operator,
+                    right: Box::new(right),
+                    loc,
+                }),
+            };
+
+            if !op.chainable {
+                max_bp = op.bp;
+            }
         }
 
         Ok(expr)
     }
 
-    fn parse_factor(&mut self) -> ParserExprRes {
-        let mut expr = self.parse_unary()?;
+    fn parse_type_check(&mut self, value: Expr) -> ParserExprRes {
+        self.expect(TokenKind::Is)?;
 
-        while self.is_at(TokenKind::Star)
-            || self.is_at(TokenKind::Slash)
-            || self.is_at(TokenKind::Modulo)
-        {
-            let operator = self.eat()?.value.clone();
-            let right = self.parse_unary()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                loc: self.get_loc(),
-            });
-        }
+        // 'null' and 'fn' are keywords but also name a type
+        let type_tk = match self.at().kind {
+            TokenKind::Identifier | TokenKind::Null | TokenKind::Fn => self.eat()?,
+            _ => return Err(self.trigger_error(ParserErr::MissingTypeName, true)),
+        };
 
-        Ok(expr)
+        Ok(Expr::TypeCheck(TypeCheckExpr {
+            loc: value.get_loc().merge(&type_tk.loc),
+            value: Box::new(value),
+            type_name: type_tk.value.clone(),
+        }))
     }
 
     fn parse_unary(&mut self) -> ParserExprRes {
         if self.is_at(TokenKind::Bang) || self.is_at(TokenKind::Minus) {
-            let operator = self.eat()?.value.clone();
-            let right = self.parse_primary()?;
+            let op_tk = self.eat()?;
+            let right = self.nested(Self::parse_unary)?;
 
             return Ok(Expr::Unary(UnaryExpr {
-                operator,
+                operator: op_tk.value.clone(),
+                loc: op_tk.loc.merge(&right.get_loc()),
                 right: Box::new(right),
-                loc: self.get_loc(),
             }));
         }
 
@@ -711,6 +1301,64 @@ impl<'a> Parser<'a> {
                 self.skip_new_lines();
 
                 expr = self.finish_call(expr)?;
+            } else if self.is_at(TokenKind::Dot) || self.is_at(TokenKind::QuestionDot) {
+                let optional = self.eat()?.kind == TokenKind::QuestionDot;
+
+                let name_tk = self
+                    .expect(TokenKind::Identifier)
+                    .map_err(|_| {
+                        let op = if optional { "?." } else { "." };
+                        self.trigger_error(ParserErr::MissingPropertyName(op.into()), true)
+                    })?;
+
+                expr = Expr::Get(GetExpr {
+                    loc: expr.get_loc().merge(&name_tk.loc),
+                    object: Box::new(expr),
+                    name: name_tk.value,
+                    optional,
+                });
+            } else if self.is_at(TokenKind::OpenBracket) {
+                // Parsing the index moves the start of the location
+                let start = expr.get_loc().start;
+                self.eat()?;
+                self.skip_new_lines();
+
+                let index = self.parse_expr()?;
+                self.skip_new_lines();
+
+                // A range of indexes is a slice
+                let mut range = None;
+                if self.is_at(TokenKind::DotDot) || self.is_at(TokenKind::DotDotEqual) {
+                    let inclusive = self.eat()?.kind == TokenKind::DotDotEqual;
+                    self.skip_new_lines();
+
+                    if self.is_at(TokenKind::CloseBracket) {
+                        return Err(self.trigger_error(ParserErr::MissingSliceEnd, true));
+                    }
+
+                    range = Some((self.parse_expr()?, inclusive));
+                    self.skip_new_lines();
+                }
+
+                let close_tk = self
+                    .expect(TokenKind::CloseBracket)
+                    .map_err(|_| self.trigger_error(ParserErr::MissingIndexCloseBracket, true))?;
+
+                let loc = Loc::new(start, close_tk.loc.end);
+                expr = match range {
+                    Some((end, inclusive)) => Expr::Slice(SliceExpr {
+                        object: Box::new(expr),
+                        start: Box::new(index),
+                        end: Box::new(end),
+                        inclusive,
+                        loc,
+                    }),
+                    None => Expr::Index(IndexExpr {
+                        object: Box::new(expr),
+                        index: Box::new(index),
+                        loc,
+                    }),
+                };
             } else {
                 break
             }
@@ -748,64 +1396,133 @@ impl<'a> Parser<'a> {
             self.skip_new_lines();
         }
 
-        self.expect(TokenKind::CloseParen)
+        let close_tk = self
+            .expect(TokenKind::CloseParen)
             .map_err(|_| self.trigger_error(ParserErr::MissingCallCloseParen, true))?;
 
         Ok(Expr::Call(CallExpr {
+            loc: callee.get_loc().merge(&close_tk.loc),
             callee: Box::new(callee),
             args,
-            loc: self.get_loc()
         }))
     }
 
     fn parse_primary(&mut self) -> ParserExprRes {
         match &self.eat()?.kind {
-            TokenKind::Identifier | TokenKind::True | TokenKind::False | TokenKind::Null => {
-                Ok(Expr::Identifier(IdentifierExpr {
-                    name: self.prev().value.clone(),
-                    loc: self.get_loc(),
-                }))
-            }
+            TokenKind::Identifier => Ok(Expr::Identifier(IdentifierExpr {
+                name: self.prev().value.clone(),
+                loc: self.prev().loc.clone(),
+            })),
+            TokenKind::True | TokenKind::False => Ok(Expr::BoolLiteral(BoolLiteralExpr {
+                value: self.prev().kind == TokenKind::True,
+                loc: self.prev().loc.clone(),
+            })),
+            TokenKind::Null => Ok(Expr::NullLiteral(NullLiteralExpr { loc: self.prev().loc.clone() })),
             TokenKind::Int => self.parse_int_literal(),
             TokenKind::Real => self.parse_real_literal(),
             TokenKind::String => self.parse_str_literal(),
             TokenKind::OpenParen => self.parse_grouping(),
+            TokenKind::If => self.parse_if_expr(),
             TokenKind::NewLine => Err(self.trigger_error(ParserErr::UnexpectedEol, false)),
-            tk => {
-                match tk {
-                    TokenKind::Star | TokenKind::Plus | TokenKind::Slash | TokenKind::Modulo => {
-                        Err(self.trigger_error(ParserErr::MissingLhsInBinop, true))
-                    }
-                    _ => Err(self
-                        .trigger_error(ParserErr::UnexpectedToken(self.prev().to_string()), true)),
-                }
+            // '-' can also be a unary operator, found where a primary is
+            // expected in '- -a'
+            tk if tk.is_binary_operator() && *tk != TokenKind::Minus => {
+                let op = self.prev().value.to_string();
+                Err(self.trigger_error(ParserErr::MissingLhsInBinop(op), true))
             }
+            _ => Err(self.trigger_error(ParserErr::UnexpectedToken(self.prev().describe()), true)),
+        }
+    }
+
+    // 'if' token has already been eaten
+    fn parse_if_expr(&mut self) -> ParserExprRes {
+        let if_loc = self.prev().loc.clone();
+        self.is_at_brace_or_end_of(ParserErr::IfWithNoCond)?;
+
+        let condition = self.parse_expr()?;
+        self.check_condition(&condition);
+
+        self.skip_expect_and_skip(TokenKind::OpenBrace)
+            .map_err(|_| self.trigger_error(ParserErr::MissingIfOpenBrace, true))?;
+
+        let then_branch = self.parse_if_expr_branch()?;
+
+        self.expect_and_skip(TokenKind::CloseBrace)
+            .map_err(|_| self.trigger_error(ParserErr::MissingIfCloseBrace, true))?;
+
+        if !self.is_at(TokenKind::Else) {
+            return Err(self.trigger_error(ParserErr::IfExprWithNoElse, true));
+        }
+
+        self.eat()?;
+        self.skip_new_lines();
+        self.is_not_at_brace_or_end_of(ParserErr::ElseWithCond)?;
+
+        self.expect_and_skip(TokenKind::OpenBrace)
+            .map_err(|_| self.trigger_error(ParserErr::MissingElseOpenBrace, true))?;
+
+        let else_branch = self.parse_if_expr_branch()?;
+
+        // We don't skip the new lines after the closing brace, they end
+        // the statement holding the expression
+        let close_tk = self
+            .expect(TokenKind::CloseBrace)
+            .map_err(|_| self.trigger_error(ParserErr::MissingElseCloseBrace, true))?;
+
+        Ok(Expr::If(IfExpr {
+            condition: Box::new(condition),
+            then_branch,
+            else_branch,
+            loc: if_loc.merge(&close_tk.loc),
+        }))
+    }
+
+    // Statements of the branch, the last one being the expression
+    // giving its value
+    fn parse_if_expr_branch(&mut self) -> Result<IfExprBranch, PhyResParser> {
+        let mut stmts = self.nested(Self::parse_stmts_until_brace)?;
+
+        match stmts.pop() {
+            Some(Stmt::Expr(value)) => {
+                self.keep_value(&value);
+
+                Ok(IfExprBranch { stmts: Rc::new(stmts), value: Box::new(value.expr) })
+            }
+            _ => Err(self.trigger_error(ParserErr::IfExprEmptyBranch, true)),
         }
     }
 
     fn parse_int_literal(&mut self) -> ParserExprRes {
         let tk = self.prev();
-        let value = tk
-            .value
-            .parse::<i64>()
-            .map_err(|_| self.trigger_error(ParserErr::ParsingInt, true))?;
+        let value = match tk.value.parse::<i64>() {
+            Ok(v) => v,
+            Err(_) if self.int_overflow_to_real => return self.parse_real_literal(),
+            Err(_) => return Err(self.trigger_error(ParserErr::ParsingInt, true)),
+        };
 
         Ok(Expr::IntLiteral(IntLiteralExpr {
             value,
-            loc: self.get_loc(),
+            loc: tk.loc.clone(),
         }))
     }
 
     fn parse_real_literal(&mut self) -> ParserExprRes {
-        let tk = self.prev();
+        let tk = self.prev().clone();
         let value = tk
             .value
             .parse::<f64>()
             .map_err(|_| self.trigger_error(ParserErr::ParsingReal, true))?;
 
+        if self.warn_precision_loss && !is_exact_real(&tk.value, value) {
+            self.warnings.push(PhyResult::new(
+                ParserWarning::RealPrecisionLoss(tk.value.to_string()),
+                Some(tk.loc.clone()),
+            ));
+        }
+
         Ok(Expr::RealLiteral(RealLiteralExpr {
             value,
-            loc: self.get_loc(),
+            loc: tk.loc,
         }))
     }
 
@@ -814,11 +1531,13 @@ impl<'a> Parser<'a> {
 
         Ok(Expr::StrLiteral(StrLiteralExpr {
             value: tk.value.clone(),
-            loc: self.get_loc(),
+            loc: tk.loc.clone(),
         }))
     }
 
     fn parse_grouping(&mut self) -> ParserExprRes {
+        let open_loc = self.prev().loc.clone();
+
         let expr = match self.parse_expr() {
             Ok(expr) => expr,
             Err(e) => match e.err {
@@ -832,41 +1551,55 @@ impl<'a> Parser<'a> {
             },
         };
 
-        self.expect(TokenKind::CloseParen)
-            .map_err(|_| PhyResult::new(ParserErr::ParenNeverClosed, Some(self.get_loc())))?;
+        let close_loc = self
+            .expect(TokenKind::CloseParen)
+            .map_err(|_| PhyResult::new(ParserErr::ParenNeverClosed, Some(self.get_loc())))?
+            .loc
+            .clone();
 
         Ok(Expr::Grouping(GroupingExpr {
             expr: Box::new(expr),
-            loc: self.get_loc(),
+            loc: open_loc.merge(&close_loc),
         }))
     }
 
-    fn at(&self) -> &Token {
-        self.tokens.get(self.current).unwrap()
+    fn at(&self) -> &'a Token {
+        self.stream.peek()
     }
 
-    fn eat(&mut self) -> Result<&Token, PhyResParser> {
-        if self.eof() {
-            return Err(PhyResult::new(
-                ParserErr::UnexpectedEof,
-                Some(self.get_loc()),
-            ));
-        }
-
-        self.current += 1;
-        Ok(self.prev())
+    fn eat(&mut self) -> Result<&'a Token, PhyResParser> {
+        self.stream.next().map_err(|e| self.stream_error(e.err))
     }
 
     fn expect(&mut self, kind: TokenKind) -> Result<Token, PhyResParser> {
-        let tk = self.eat()?;
-
-        match tk.kind == kind {
-            true => Ok(self.prev().clone()),
-            false => Err(PhyResult::new(
-                ParserErr::ExpectedToken(format!("{:?}", kind), format!("{:?}", tk.kind)),
-                Some(self.get_loc()),
-            )),
+        self.stream
+            .expect(kind)
+            .cloned()
+            .map_err(|e| self.stream_error(e.err))
+    }
+
+    // Stream errors are located on the statement being parsed
+    fn stream_error(&self, err: TokenStreamErr) -> PhyResParser {
+        let err = match err {
+            TokenStreamErr::MissingEof => ParserErr::MissingEof,
+            TokenStreamErr::UnexpectedEof => ParserErr::UnexpectedEof,
+            TokenStreamErr::ExpectedToken(kind, found) => ParserErr::ExpectedToken(kind, found),
+        };
+
+        PhyResult::new(err, Some(self.get_loc()))
+    }
+
+    // Name of a declaration. A keyword there gets its own error, the
+    // lexer having turned it into another token kind
+    fn expect_name(&mut self, missing: ParserErr) -> Result<EcoString, PhyResParser> {
+        if self.at().kind.is_keyword() {
+            let err = ParserErr::ReservedKeywordAsIdentifier(self.at().value.to_string());
+            return Err(self.trigger_error(err, true));
         }
+
+        self.expect(TokenKind::Identifier)
+            .map(|tk| tk.value)
+            .map_err(|_| self.trigger_error(missing, true))
     }
 
     fn expect_and_skip(&mut self, kind: TokenKind) -> Result<(), PhyResParser> {
@@ -885,22 +1618,41 @@ impl<'a> Parser<'a> {
     }
 
     fn is_at(&self, kind: TokenKind) -> bool {
-        self.at().kind == kind
+        self.stream.is_at(kind)
+    }
+
+    // '++' or '--', without space in between, ending the statement
+    fn is_at_postfix_step(&self) -> bool {
+        let (op, next, after) = (self.at(), self.stream.peek_nth(1), self.stream.peek_nth(2));
+
+        matches!(op.kind, TokenKind::Plus | TokenKind::Minus)
+            && next.kind == op.kind
+            && next.loc.start == op.loc.end
+            && matches!(after.kind, TokenKind::NewLine | TokenKind::CloseBrace | TokenKind::Eof)
     }
 
-    fn prev(&self) -> &Token {
-        self.tokens.get(self.current - 1).unwrap()
+    fn prev(&self) -> &'a Token {
+        self.stream.prev()
     }
 
     fn eof(&self) -> bool {
-        self.is_at(TokenKind::Eof)
+        self.stream.eof()
     }
 
     fn skip_new_lines(&mut self) {
         // If we have a new line to begin a statement/expr parsing,
         // we skip it. There are important only in parsing steps
-        while !self.eof() && self.is_at(TokenKind::NewLine) {
-            self.current += 1;
+        while !self.eof() && (self.is_at(TokenKind::NewLine) || self.is_at(TokenKind::DocComment)) {
+            if self.is_at(TokenKind::DocComment) {
+                let line = self.at().value.clone();
+
+                self.pending_doc = Some(match self.pending_doc.take() {
+                    Some(doc) => eco_format!("{}\n{}", doc, line),
+                    None => line,
+                });
+            }
+
+            let _ = self.stream.next();
         }
 
         self.start_loc = self.at().loc.start;
@@ -917,6 +1669,15 @@ impl<'a> Parser<'a> {
         PhyResult::new(err, Some(self.get_loc()))
     }
 
+    // Eats the last bound to locate the error on the whole range
+    fn mixed_range_error(&mut self, range_start: usize) -> PhyResParser {
+        let end = self.at().loc.end;
+        let _ = self.eat();
+        self.synchronize();
+
+        PhyResult::new(ParserErr::MixedRangeTypes, Some(Loc::new(range_start, end)))
+    }
+
     fn is_at_brace_or_end_of(&mut self, err: ParserErr) -> Result<(), PhyResParser> {
         if self.is_at(TokenKind::OpenBrace)
             || self.is_at(TokenKind::Eof)
@@ -956,6 +1717,10 @@ impl<'a> Parser<'a> {
         while !self.eof() {
             match self.at().kind {
                 TokenKind::NewLine => return,
+                TokenKind::SemiColon => {
+                    let _ = self.eat();
+                    return;
+                }
                 //| TokenKind::Struct
                 //| TokenKind::Fn
                 //| TokenKind::Var
@@ -972,14 +1737,48 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // The expression of the statement is used as a value, so it has an
+    // effect after all
+    fn keep_value(&mut self, stmt: &ExprStmt) {
+        self.warnings.retain(|w| {
+            !(matches!(w.err, ParserWarning::NoEffectExpr) && w.loc.as_ref() == Some(&stmt.loc))
+        });
+    }
+
+    // Runs a parsing step one level deeper, failing past the limit
+    fn nested<T>(&mut self, step: fn(&mut Self) -> Result<T, PhyResParser>) -> Result<T, PhyResParser> {
+        if self.nesting >= MAX_DEPTH {
+            return Err(self.trigger_error(ParserErr::MaxDepth(MAX_DEPTH), true));
+        }
+
+        self.nesting += 1;
+        self.max_nesting = self.max_nesting.max(self.nesting);
+        let res = step(self);
+        self.nesting -= 1;
+
+        res
+    }
+
     fn get_loc(&self) -> Loc {
         Loc::new(self.start_loc, self.at().loc.start)
     }
+
+    // 'if a = b' is most likely a typo for 'if a == b'. A grouping around
+    // the assignment marks it as intended
+    fn check_condition(&mut self, condition: &Expr) {
+        if let Expr::Assign(a) = condition {
+            self.warnings.push(PhyResult::new(
+                ParserWarning::AssignInCondition(a.name.to_string()),
+                Some(condition.get_loc()),
+            ));
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use tools::results::Loc;
+    use crate::ast::ast_pretty_print::AstPrinter;
     use crate::parser::{ParserErr, utils::*};
     use ecow::EcoString;
 
@@ -1000,14 +1799,8 @@ mod tests {
             vec![EcoString::from("foo bar! 5-{6}")]
         );
 
-        assert_eq!(
-            infos.get_grp_values()[0].get_ident_values(),
-            vec![EcoString::from("true")]
-        );
-        assert_eq!(
-            infos.get_grp_values()[1].get_grp_values()[0].get_ident_values(),
-            vec![EcoString::from("null")]
-        );
+        assert_eq!(infos.get_grp_values()[0].get_bool_values(), vec![&true]);
+        assert_eq!(infos.get_grp_values()[1].get_grp_values()[0].null.len(), 1);
 
         // Errors
         let code = "(art + 
@@ -1056,14 +1849,43 @@ mod tests {
         assert_eq!(op, EcoString::from("+"));
         assert_eq!(right, &4i64);
 
-        // Errors
+        // Errors, the operand is looked for on the next lines
         let code = "5 +
 ";
 
         let errs = lex_and_parse(code).err().unwrap();
         let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
 
-        assert_eq!(e, vec![&ParserErr::UnexpectedEol]);
+        assert_eq!(e, vec![&ParserErr::UnexpectedEof]);
+    }
+
+    #[test]
+    fn operator_continues_line() {
+        let code = "var a = 1 +
+    2 *
+
+    3 -
+    4
+print a";
+        let nodes = lex_and_parse(code).unwrap();
+        let one_line = lex_and_parse("var a = 1 + 2 * 3 - 4").unwrap();
+        let mut printer = AstPrinter {};
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(printer.print(&nodes[0]).unwrap(), printer.print(&one_line[0]).unwrap());
+
+        let infos = get_stmt_nodes_infos("if a and\n  b or\n  c {}");
+        assert_eq!(infos.if_stmt.len(), 1);
+
+        // An operator starting a line isn't a continuation
+        let errs = lex_and_parse("var b = 1\n+ 2").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingLhsInBinop("+".into()));
+        assert_eq!(lex_and_parse("var b = 1\n- 2").unwrap().len(), 2);
+
+        // Other tokens don't continue the line
+        let errs = lex_and_parse("var c =\n 1").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::NoExprAssign);
+        let errs = lex_and_parse("if a and\n{}").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::AndWithNoCond);
     }
 
     #[test]
@@ -1086,10 +1908,7 @@ mod tests {
         assert_eq!(infos.unary[2].expr.get_real_values(), vec![&54.67]);
         assert_eq!(infos.unary[2].op, EcoString::from("-"));
 
-        assert_eq!(
-            infos.unary[3].expr.get_ident_values(),
-            vec![EcoString::from("true")]
-        );
+        assert_eq!(infos.unary[3].expr.get_bool_values(), vec![&true]);
         assert_eq!(infos.unary[3].op, EcoString::from("!"));
 
         // Errors
@@ -1104,12 +1923,18 @@ mod tests {
         assert_eq!(
             e,
             vec![
-                &ParserErr::MissingLhsInBinop,
-                &ParserErr::MissingLhsInBinop,
-                &ParserErr::MissingLhsInBinop,
-                &ParserErr::MissingLhsInBinop,
+                &ParserErr::MissingLhsInBinop("+".into()),
+                &ParserErr::MissingLhsInBinop("*".into()),
+                &ParserErr::MissingLhsInBinop("/".into()),
+                &ParserErr::MissingLhsInBinop("%".into()),
             ]
         );
+
+        // The operand is itself a unary expression, calls bind tighter
+        use crate::parser::parse_expression;
+        assert_eq!(parse_expression("- -5").unwrap().to_string(), "-(-5)");
+        assert_eq!(parse_expression("!!a").unwrap().to_string(), "!(!a)");
+        assert_eq!(parse_expression("-f(1)").unwrap().to_string(), "-(f(1))");
     }
 
     #[test]
@@ -1264,10 +2089,7 @@ if a {} else {}
         let cond_binop = &if_stmt.condition.binop[0];
         assert_eq!(cond_binop.left.get_ident_values()[0], EcoString::from("b"));
         assert_eq!(cond_binop.op, EcoString::from("=="));
-        assert_eq!(
-            cond_binop.right.get_ident_values()[0],
-            EcoString::from("true")
-        );
+        assert_eq!(cond_binop.right.get_bool_values()[0], &true);
 
         let then_branch = &if_stmt.then_branch.as_ref().unwrap().expr.assign[0];
         assert_eq!(then_branch.name, EcoString::from("a"));
@@ -1361,10 +2183,7 @@ while a or true
         let while_cond = &while_stmt.condition.logical[0];
         assert_eq!(while_cond.left.get_ident_values()[0], EcoString::from("a"));
         assert_eq!(while_cond.op, EcoString::from("or"));
-        assert_eq!(
-            while_cond.right.get_ident_values()[0],
-            EcoString::from("true")
-        );
+        assert_eq!(while_cond.right.get_bool_values()[0], &true);
 
         let body = &while_stmt.body.block[0].expr.assign[0];
         assert_eq!(body.name, EcoString::from("a"));
@@ -1386,6 +2205,44 @@ while a
         assert!(e[1] == &ParserErr::MissingWhileOpenBrace);
     }
 
+    #[test]
+    fn while_let_stmt() {
+        let code = "
+while var line = input()
+{
+    print line
+}
+";
+        let infos = get_stmt_nodes_infos(code);
+        let while_let = &infos.while_let[0];
+        assert_eq!(while_let.name, EcoString::from("line"));
+        assert_eq!(
+            while_let.value.call[0].callee.get_ident_values(),
+            vec![EcoString::from("input")]
+        );
+        assert_eq!(while_let.body.block[0].print[0], String::from("line"));
+        assert!(infos.while_stmt.is_empty());
+
+        let nodes = lex_and_parse("while var a = b ?? c {}").unwrap();
+        assert_eq!(
+            AstPrinter {}.print(&nodes[0]).unwrap(),
+            "(while var a = (?? b c) (block))"
+        );
+
+        // Errors
+        let code = "
+while var = 1 {}
+while var a {}
+while var a = 1
+print a
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::MissingVarNameWhile);
+        assert_eq!(e[1], &ParserErr::MissingValueWhile);
+        assert_eq!(e[2], &ParserErr::MissingWhileOpenBrace);
+    }
+
     #[test]
     fn for_stmt() {
         let code = "
@@ -1413,6 +2270,11 @@ for foo_b4r in 5..10
             (5, Some(10))
         );
 
+        // The discard name is a regular identifier for the parser
+        let infos = get_stmt_nodes_infos("for _ in 0..3 {}\nvar _ = f()");
+        assert_eq!(infos.for_stmt[0].placeholder, EcoString::from("_"));
+        assert_eq!(infos.var_decl[0].0, EcoString::from("_"));
+
         // Errors
         let code = "
 for in 5 {}
@@ -1439,6 +2301,24 @@ for a in 5..0 {}
         assert!(e[8] == &ParserErr::LesserEndForRange);
     }
 
+    #[test]
+    fn mixed_range_types() {
+        let code = "
+for i in 0..10.5 {}
+for i in 0.5..10 {}
+for i in 0.5..10.5 {}
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        assert_eq!(errs.len(), 3);
+        assert_eq!(errs[0].err, ParserErr::MixedRangeTypes);
+        assert_eq!(errs[0].loc, Some(Loc::new(10, 17)));
+        assert_eq!(errs[1].err, ParserErr::MixedRangeTypes);
+        assert_eq!(errs[1].loc, Some(Loc::new(30, 37)));
+        assert_eq!(errs[2].err, ParserErr::NonIntForRange);
+
+        assert!(lex_and_parse("for i in 0..10 {}").is_ok());
+    }
+
     #[test]
     fn call() {
         let code = "
@@ -1531,4 +2411,1053 @@ return 4
         assert_eq!(&infos.return_stmt[0], &None);
         assert_eq!(&infos.return_stmt[1].as_ref().unwrap().get_int_values()[0], &&4);
     }
+
+    #[test]
+    fn import_stmt() {
+        let code = "
+import \"lib/math.rz\"
+import \"utils.rz\"
+";
+        let infos = get_stmt_nodes_infos(code);
+        assert_eq!(
+            infos.import,
+            vec![EcoString::from("lib/math.rz"), EcoString::from("utils.rz")]
+        );
+
+        // Errors
+        let code = "
+import
+import utils
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::MissingImportPath);
+        assert!(e[1] == &ParserErr::MissingImportPath);
+    }
+
+    #[test]
+    fn type_check() {
+        let code = "
+a is int
+1 + 2 is real
+b is null
+";
+        let infos = get_expr_nodes_infos(code);
+        let check = &infos.type_check[0];
+        assert_eq!(check.value.get_ident_values()[0], EcoString::from("a"));
+        assert_eq!(check.type_name, EcoString::from("int"));
+
+        let check = &infos.type_check[1];
+        assert_eq!(check.value.get_binop_values()[0].1, EcoString::from("+"));
+        assert_eq!(check.type_name, EcoString::from("real"));
+
+        let check = &infos.type_check[2];
+        assert_eq!(check.type_name, EcoString::from("null"));
+
+        // Errors
+        let code = "
+a is
+a is 5
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::MissingTypeName);
+        assert!(e[1] == &ParserErr::MissingTypeName);
+    }
+
+    #[test]
+    fn coalesce() {
+        let code = "
+a ?? 5
+a ?? b ?? c
+a ?? b or c
+";
+        let infos = get_expr_nodes_infos(code);
+        let logical = &infos.logical[0];
+        assert_eq!(logical.left.get_ident_values()[0], EcoString::from("a"));
+        assert_eq!(logical.op, EcoString::from("??"));
+        assert_eq!(logical.right.get_int_values()[0], &5);
+
+        // Left associative
+        let logical = &infos.logical[1];
+        assert_eq!(logical.left.logical[0].op, EcoString::from("??"));
+        assert_eq!(logical.right.get_ident_values()[0], EcoString::from("c"));
+
+        // Binds tighter than 'or'
+        let logical = &infos.logical[2];
+        assert_eq!(logical.op, EcoString::from("or"));
+        assert_eq!(logical.left.logical[0].op, EcoString::from("??"));
+
+        // Errors
+        let code = "
+a ??
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::CoalesceWithNoRhs);
+    }
+
+    #[test]
+    fn get() {
+        let code = "
+a.b
+a?.b?.c
+";
+        let infos = get_expr_nodes_infos(code);
+        let get = &infos.get[0];
+        assert_eq!(get.object.get_ident_values()[0], EcoString::from("a"));
+        assert_eq!(get.name, EcoString::from("b"));
+        assert!(!get.optional);
+
+        let get = &infos.get[1];
+        assert_eq!(get.name, EcoString::from("c"));
+        assert!(get.optional);
+        assert_eq!(get.object.get[0].name, EcoString::from("b"));
+        assert!(get.object.get[0].optional);
+
+        // Errors
+        let code = "
+a.
+a?.5
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::MissingPropertyName(".".into()));
+        assert!(e[1] == &ParserErr::MissingPropertyName("?.".into()));
+    }
+
+    #[test]
+    fn increment() {
+        let code = "
+i++
+foo--
+";
+        let infos = get_expr_nodes_infos(code);
+        let assign = infos.get_assign_values();
+        assert_eq!(assign[0].0, EcoString::from("i"));
+        let binop = &assign[0].1.get_binop_values()[0];
+        assert_eq!(binop.0.get_ident_values()[0], EcoString::from("i"));
+        assert_eq!(binop.1, EcoString::from("+"));
+        assert_eq!(binop.2.get_int_values()[0], &1);
+
+        assert_eq!(assign[1].0, EcoString::from("foo"));
+        let binop = &assign[1].1.get_binop_values()[0];
+        assert_eq!(binop.0.get_ident_values()[0], EcoString::from("foo"));
+        assert_eq!(binop.1, EcoString::from("-"));
+        assert_eq!(binop.2.get_int_values()[0], &1);
+
+        // Errors
+        let code = "
+5++
+foo()--
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::InvalidIncrementTarget("++".into()));
+        assert!(e[1] == &ParserErr::InvalidIncrementTarget("--".into()));
+    }
+
+    #[test]
+    fn enum_decl() {
+        let code = "
+enum Color { Red, Green, Blue }
+enum Dir {
+    Up,
+    Down,
+}
+enum Empty {}
+";
+        let infos = get_stmt_nodes_infos(code);
+        assert_eq!(
+            infos.enum_decl[0],
+            (
+                EcoString::from("Color"),
+                vec![EcoString::from("Red"), EcoString::from("Green"), EcoString::from("Blue")]
+            )
+        );
+        assert_eq!(
+            infos.enum_decl[1],
+            (EcoString::from("Dir"), vec![EcoString::from("Up"), EcoString::from("Down")])
+        );
+        assert!(infos.enum_decl[2].1.is_empty());
+
+        // Errors
+        let code = "
+enum { A }
+enum Color A, B
+enum Color { A B }
+enum Color { A, 5 }
+enum Color { A, A }
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::MissingEnumName);
+        assert!(e[1] == &ParserErr::MissingEnumOpenBrace);
+        assert!(e[2] == &ParserErr::MissingEnumComma);
+        assert!(e[3] == &ParserErr::WrongEnumVariant);
+        assert!(e[4] == &ParserErr::DuplicateEnumVariant("A".into()));
+    }
+
+    #[test]
+    fn if_expr() {
+        use crate::ast::{expr::Expr, stmt::Stmt};
+
+        let code = "
+var a = if c { 1 } else { 2 }
+var b = if a > 0 {
+    a + 1
+} else {
+    0
+}
+";
+        let infos = get_stmt_nodes_infos(code);
+        let if_expr = &infos.var_decl[0].1.as_ref().unwrap().if_expr[0];
+        assert_eq!(if_expr.condition.get_ident_values()[0], EcoString::from("c"));
+        assert_eq!(if_expr.then_branch.get_int_values(), vec![&1]);
+        assert_eq!(if_expr.else_branch.get_int_values(), vec![&2]);
+
+        let if_expr = &infos.var_decl[1].1.as_ref().unwrap().if_expr[0];
+        assert_eq!(if_expr.condition.binop.len(), 1);
+        assert_eq!(if_expr.then_branch.binop.len(), 1);
+        assert_eq!(if_expr.else_branch.get_int_values(), vec![&0]);
+
+        // Statements before the value
+        let code = "var x = if c { var y = 2\n y * 3 } else { print 1; 0 }";
+        let Stmt::VarDecl(decl) = &lex_and_parse(code).unwrap()[0] else { panic!() };
+        let Some(Expr::If(if_expr)) = &decl.value else { panic!() };
+        assert!(matches!(if_expr.then_branch.stmts[..], [Stmt::VarDecl(_)]));
+        assert_eq!(if_expr.then_branch.value.to_string(), "y * 3");
+        assert!(matches!(if_expr.else_branch.stmts[..], [Stmt::Print(_)]));
+        assert_eq!(if_expr.else_branch.value.to_string(), "0");
+
+        // Errors
+        let code = "
+print if c { 1 }
+print if c { 1 } else {}
+print if c {} else { 2 }
+print if c { 1 } else { var a = 2 }
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::IfExprWithNoElse);
+        assert_eq!(e[1], &ParserErr::IfExprEmptyBranch);
+        assert_eq!(e[2], &ParserErr::IfExprEmptyBranch);
+        assert_eq!(e[3], &ParserErr::IfExprEmptyBranch);
+    }
+
+    #[test]
+    fn precedence() {
+        let cases = [
+            ("1 + 2 * 3 - 4 / 2", "(- (+ 1 (* 2 3)) (/ 4 2))"),
+            ("a - b - c % 2", "(- (- a b) (% c 2))"),
+            ("1 + 2 == 3 * 1", "(== (+ 1 2) (* 3 1))"),
+            ("a < b == c >= d", "(== (< a b) (>= c d))"),
+            ("a or b and c", "(or a (and b c))"),
+            ("a and b or c ?? d", "(or (and a b) (?? c d))"),
+            ("a ?? b ?? c and d", "(?? (?? a b) (and c d))"),
+            ("-a * (b + 1)", "(* (- a) (group (+ b 1)))"),
+            ("a + 1 is int == true", "(== (is int (+ a 1)) true)"),
+            ("x = a + b * c", "(assign (+ a (* b c)) to x)"),
+        ];
+
+        for (code, expected) in cases {
+            let nodes = lex_and_parse(code).unwrap();
+            let mut printer = AstPrinter {};
+            assert_eq!(printer.print(&nodes[0]).unwrap(), expected);
+        }
+
+        // Errors
+        let code = "
+* 2
+a and b and c
+a is int is bool
+1 +
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::MissingLhsInBinop("*".into()));
+        assert_eq!(e[3], &ParserErr::UnexpectedEof);
+        assert_eq!(e.len(), 4);
+    }
+
+    #[test]
+    fn unexpected_token() {
+        let errs = lex_and_parse("var a = 1 + )").err().unwrap();
+
+        assert_eq!(
+            errs[0].err,
+            ParserErr::IncorrectVarDeclVal(
+                "unexpected token to parse: CloseParen ')' at 12..13".into()
+            )
+        );
+    }
+
+    #[test]
+    fn empty_input() {
+        use crate::lexer::{Lexer, Token, TokenKind};
+        use crate::parser::Parser;
+
+        for code in ["", "\n\n  \n", "  \t\r\n// comment\n"] {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(code).unwrap();
+
+            assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
+            assert!(tokens.iter().all(|t| matches!(t.kind, TokenKind::NewLine | TokenKind::Eof)));
+
+            let mut parser = Parser::default();
+            assert!(parser.parse(tokens).unwrap().is_empty());
+        }
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("").unwrap();
+        assert_eq!(tokens.len(), 1);
+
+        // Only Eof, and no token at all
+        let eof = [Token { kind: TokenKind::Eof, value: "eof".into(), loc: Loc::new(0, 1), line_start: true }];
+        let mut parser = Parser::default();
+        assert!(parser.parse(&eof).unwrap().is_empty());
+
+        // A token stream must end with Eof
+        let mut parser = Parser::default();
+        let errs = parser.parse(&[]).err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingEof);
+        assert_eq!(errs[0].loc, None);
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("1 +").unwrap();
+        let mut parser = Parser::default();
+        let errs = parser.parse(&tokens[..tokens.len() - 1]).err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingEof);
+        assert_eq!(errs[0].loc, Some(Loc::new(3, 3)));
+    }
+
+    #[test]
+    fn parse_source() {
+        use crate::parser::parse;
+
+        assert_eq!(parse("print 1 + 2\nvar a").unwrap().len(), 2);
+
+        let errs = parse("var a = \"abc").err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(
+            errs[0].err,
+            ParserErr::Lexing("string literal never closed with '\"'".into())
+        );
+        assert_eq!(errs[0].loc, Some(Loc::new(8, 9)));
+    }
+
+    #[test]
+    fn bool_null_literals() {
+        use crate::ast::expr::{BoolLiteralExpr, Expr, NullLiteralExpr};
+        use crate::ast::stmt::Stmt;
+
+        let nodes = lex_and_parse("true\nfalse\nnull\ntruth").unwrap();
+        let exprs: Vec<&Expr> = nodes
+            .iter()
+            .map(|n| match n {
+                Stmt::Expr(s) => &s.expr,
+                _ => panic!("expected an expression statement"),
+            })
+            .collect();
+
+        assert_eq!(
+            exprs[0],
+            &Expr::BoolLiteral(BoolLiteralExpr { value: true, loc: Loc::new(0, 4) })
+        );
+        assert_eq!(
+            exprs[1],
+            &Expr::BoolLiteral(BoolLiteralExpr { value: false, loc: Loc::new(5, 10) })
+        );
+        assert_eq!(exprs[2], &Expr::NullLiteral(NullLiteralExpr { loc: Loc::new(11, 15) }));
+        assert!(matches!(exprs[3], Expr::Identifier(_)));
+    }
+
+    #[test]
+    fn index_expr() {
+        let code = "
+s[0]
+a.b[i + 1][2]
+";
+        let infos = get_expr_nodes_infos(code);
+        let index = &infos.index[0];
+        assert_eq!(index.object.get_ident_values()[0], EcoString::from("s"));
+        assert_eq!(index.index.get_int_values(), vec![&0]);
+        assert_eq!(index.loc, Loc::new(1, 5));
+
+        let outer = &infos.index[1];
+        assert_eq!(outer.index.get_int_values(), vec![&2]);
+        let inner = &outer.object.index[0];
+        assert_eq!(inner.object.get[0].name, EcoString::from("b"));
+        assert_eq!(inner.index.get_binop_values()[0].1, EcoString::from("+"));
+
+        // Errors
+        let code = "print s[0";
+        let errs = lex_and_parse(code).err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingIndexCloseBracket);
+    }
+
+    #[test]
+    fn slice_expr() {
+        let code = "
+s[1..3]
+s[i..=n - 1][0..2]
+";
+        let infos = get_expr_nodes_infos(code);
+        let slice = &infos.slice[0];
+        assert_eq!(slice.object.get_ident_values()[0], EcoString::from("s"));
+        assert_eq!(slice.start.get_int_values(), vec![&1]);
+        assert_eq!(slice.end.get_int_values(), vec![&3]);
+        assert!(!slice.inclusive);
+        assert_eq!(slice.loc, Loc::new(1, 8));
+
+        let outer = &infos.slice[1];
+        assert!(!outer.inclusive);
+        let inner = &outer.object.slice[0];
+        assert!(inner.inclusive);
+        assert_eq!(inner.end.get_binop_values()[0].1, EcoString::from("-"));
+        assert!(infos.index.is_empty());
+
+        // Errors
+        let errs = lex_and_parse("print s[0..]").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingSliceEnd);
+        let errs = lex_and_parse("print s[0..2").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingIndexCloseBracket);
+    }
+
+    #[test]
+    fn assign_in_condition() {
+        use crate::lexer::Lexer;
+        use crate::parser::{Parser, ParserWarning};
+
+        let code = "
+if a = b { }
+while a = true { }
+var c = if a = b { 1 } else { 2 }
+if (a = b) { }
+while (a = b) == true { }
+if a == b { }
+";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+        let mut parser = Parser::default();
+        assert!(parser.parse(tokens).is_ok());
+
+        let warnings: Vec<&ParserWarning> = parser.warnings.iter().map(|w| &w.err).collect();
+        assert_eq!(
+            warnings,
+            vec![&ParserWarning::AssignInCondition("a".into()); 3]
+        );
+    }
+
+    #[test]
+    fn trailing_new_line() {
+        let codes = [
+            "print 1",
+            "var a = 1 + 2",
+            "var a",
+            "a = b(1)",
+            "if a { print a } else { print b }",
+            "while a { a = a - 1 }",
+            "fn f(a) { return a }",
+            "return",
+            "fn f() { return }",
+            "enum A { B, C }",
+            "{ print 1 }",
+        ];
+
+        for code in codes {
+            let expected = lex_and_parse(code).unwrap();
+
+            for suffix in ["\n", "\n\n", "\n  \n"] {
+                let with_new_line = format!("{}{}", code, suffix);
+                assert_eq!(lex_and_parse(&with_new_line).unwrap(), expected, "{:?}", with_new_line);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_lhs() {
+        for (code, op) in [("print * 2", "*"), ("print == 1", "=="), ("print and a", "and"), ("print ?? a", "??")] {
+            let errs = lex_and_parse(code).err().unwrap();
+            assert_eq!(errs[0].err, ParserErr::MissingLhsInBinop(op.into()), "{}", code);
+        }
+
+        // The full operator text is part of the message
+        let errs = lex_and_parse("* 5").err().unwrap();
+        assert_eq!(errs[0].err.to_string(), "missing left-hand side of '*'");
+        let errs = lex_and_parse("<= 5").err().unwrap();
+        assert_eq!(errs[0].err.to_string(), "missing left-hand side of '<='");
+    }
+
+    #[test]
+    fn doc_comments() {
+        use crate::ast::stmt::Stmt;
+
+        let code = "
+/// Returns the answer
+/// to everything
+fn foo() {
+    /// Local
+    var a = 42
+    return a
+}
+
+/// Not attached
+print 1
+var b
+/// Attached
+var c = 1
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let Stmt::FnDecl(foo) = &nodes[0] else { panic!("expected a function") };
+        assert_eq!(foo.doc, Some(EcoString::from("Returns the answer\nto everything")));
+
+        let Stmt::VarDecl(a) = &foo.body[0] else { panic!("expected a variable") };
+        assert_eq!(a.doc, Some(EcoString::from("Local")));
+
+        let Stmt::VarDecl(b) = &nodes[2] else { panic!("expected a variable") };
+        assert_eq!(b.doc, None);
+        let Stmt::VarDecl(c) = &nodes[3] else { panic!("expected a variable") };
+        assert_eq!(c.doc, Some(EcoString::from("Attached")));
+
+        // Doc comments don't change the rest of the tree
+        let without_docs: String = code
+            .lines()
+            .filter(|l| !l.trim_start().starts_with("///"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(lex_and_parse(&without_docs).unwrap().len(), nodes.len());
+    }
+
+    #[test]
+    fn int_overflow_to_real() {
+        use crate::ast::expr::{Expr, RealLiteralExpr};
+        use crate::ast::stmt::{ExprStmt, Stmt};
+        use crate::lexer::Lexer;
+        use crate::parser::Parser;
+
+        let code = "100000000000000000000";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        let mut parser = Parser::default();
+        let errs = parser.parse(tokens).err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::ParsingInt);
+
+        let mut parser = Parser {
+            int_overflow_to_real: true,
+            ..Default::default()
+        };
+        let nodes = parser.parse(tokens).unwrap();
+        assert_eq!(
+            nodes[0],
+            Stmt::Expr(ExprStmt {
+                expr: Expr::RealLiteral(RealLiteralExpr {
+                    value: 1e20,
+                    loc: Loc::new(0, 21),
+                }),
+                loc: Loc::new(0, 21),
+            })
+        );
+
+        // Ints that fit stay ints
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("9223372036854775807").unwrap();
+        let mut parser = Parser {
+            int_overflow_to_real: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            &parser.parse(tokens).unwrap()[0],
+            Stmt::Expr(ExprStmt { expr: Expr::IntLiteral(_), .. })
+        ));
+    }
+
+    #[test]
+    fn strict_mode() {
+        use crate::lexer::Lexer;
+        use crate::parser::{Parser, ParserWarning};
+
+        let code = "var a\nif a = 1 { }\nprint 1 +\n";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        let mut parser = Parser::default();
+        assert_eq!(parser.parse(tokens).err().unwrap().len(), 1);
+        assert_eq!(parser.warnings.len(), 1);
+
+        let mut parser = Parser {
+            strict: true,
+            ..Default::default()
+        };
+        let errs = parser.parse(tokens).err().unwrap();
+        assert!(parser.warnings.is_empty());
+        assert_eq!(errs.len(), 2);
+        assert_eq!(
+            errs[0].err,
+            ParserErr::Strict(ParserWarning::AssignInCondition("a".into()))
+        );
+
+        // Only warnings fail in strict mode
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("var a\nif a == 1 { }").unwrap();
+        let mut parser = Parser {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(parser.parse(tokens).is_ok());
+    }
+
+    #[test]
+    fn grouping_loc() {
+        use crate::ast::expr::Expr;
+        use crate::ast::stmt::Stmt;
+        use crate::parser::parse;
+
+        let nodes = parse("(1 + 2)").unwrap();
+        let Stmt::Expr(s) = &nodes[0] else { panic!() };
+        assert_eq!(s.expr.get_loc(), Loc::new(0, 7));
+
+        let nodes = parse("print 3 * ( (a) - 2 )").unwrap();
+        let Stmt::Print(s) = &nodes[0] else { panic!() };
+        let Expr::Binary(b) = &s.expr else { panic!() };
+        let Expr::Grouping(g) = b.right.as_ref() else { panic!() };
+        assert_eq!(g.loc, Loc::new(10, 21));
+        let Expr::Binary(inner) = g.expr.as_ref() else { panic!() };
+        assert_eq!(inner.left.get_loc(), Loc::new(12, 15));
+
+        // Expressions span their own tokens, not the start of the statement
+        assert_eq!(b.loc, Loc::new(6, 21));
+        assert_eq!(inner.loc, Loc::new(12, 19));
+        assert_eq!(inner.right.get_loc(), Loc::new(18, 19));
+    }
+
+    #[test]
+    fn trailing_tokens() {
+        use crate::ast::expr::Expr;
+        use crate::parser::parse_expression;
+
+        assert!(matches!(parse_expression("1 + 2").unwrap(), Expr::Binary(_)));
+        assert!(matches!(parse_expression("\n  a = 4\n\n").unwrap(), Expr::Assign(_)));
+
+        let errs = parse_expression("1 + 2 3").err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].err, ParserErr::TrailingTokens("3".into()));
+        assert_eq!(errs[0].loc, Some(Loc::new(6, 7)));
+
+        let errs = parse_expression("f()\nprint 1").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::TrailingTokens("print".into()));
+        assert_eq!(errs[0].loc, Some(Loc::new(4, 9)));
+
+        // A terminator doesn't start another expression
+        let errs = parse_expression("1 + 2; 3").err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].err, ParserErr::TrailingTokens(";".into()));
+        assert_eq!(errs[0].loc, Some(Loc::new(5, 6)));
+
+        // Statements aren't expressions
+        assert!(parse_expression("var a = 1").is_err());
+    }
+
+    #[test]
+    fn else_if_chain() {
+        use crate::ast::expr::Expr;
+        use crate::ast::stmt::{IfStmt, Stmt};
+        use crate::parser::parse;
+
+        let code = "
+if a == 1 { print 1 } else if a == 2 { print 2 }
+else if a == 3 { print 3 } else { print 4 }
+print 5";
+        let nodes = parse(code).unwrap();
+        assert_eq!(nodes.len(), 2);
+
+        // Each 'else if' is the else branch of the previous if
+        let mut branch = Some(&nodes[0]);
+        let mut rhs = vec![];
+        while let Some(Stmt::If(IfStmt { condition, else_branch, .. })) = branch {
+            let Expr::Binary(b) = condition else { panic!() };
+            let Expr::IntLiteral(i) = b.right.as_ref() else { panic!() };
+            rhs.push(i.value);
+            branch = else_branch.as_deref();
+        }
+
+        assert_eq!(rhs, vec![1, 2, 3]);
+        assert!(matches!(branch, Some(Stmt::Print(_))));
+
+        let nodes = parse("if a { } else if b { }").unwrap();
+        let Stmt::If(s) = &nodes[0] else { panic!() };
+        let Some(Stmt::If(nested)) = s.else_branch.as_deref() else { panic!() };
+        assert!(nested.else_branch.is_none());
+
+        let errs = parse("if a { } else if { }").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::IfWithNoCond);
+    }
+
+    #[test]
+    fn real_precision_loss() {
+        use crate::lexer::Lexer;
+        use crate::parser::{Parser, ParserWarning};
+
+        let code = "0.1\n0.5\n2.\n1.25e2\n3e-1\n0.000_976_562_5\n1e400\n9007199254740993.0";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        let mut parser = Parser::default();
+        parser.parse(tokens).unwrap();
+        assert!(parser.warnings.is_empty());
+
+        let mut parser = Parser {
+            warn_precision_loss: true,
+            ..Default::default()
+        };
+        parser.parse(tokens).unwrap();
+
+        let warnings: Vec<ParserWarning> = parser.warnings.into_iter().map(|w| w.err).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                ParserWarning::RealPrecisionLoss("0.1".into()),
+                ParserWarning::RealPrecisionLoss("3e-1".into()),
+                ParserWarning::RealPrecisionLoss("1e400".into()),
+                ParserWarning::RealPrecisionLoss("9007199254740993.0".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn statement_terminators() {
+        use crate::lexer::Lexer;
+        use crate::parser::{Parser, StatementTerminator};
+
+        let parse_with = |code: &str, terminator: StatementTerminator| {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(code).unwrap().clone();
+            let mut parser = Parser {
+                terminator,
+                ..Default::default()
+            };
+
+            parser.parse(&tokens).map(|s| s.len()).map_err(|e| {
+                e.into_iter().map(|e| (e.err, e.loc.unwrap())).collect::<Vec<_>>()
+            })
+        };
+
+        let new_lines = "var a = 1\nprint a\nif a > 0 { a = 0 } print a\n";
+        let semicolons = "var a = 1; print a;\nfn f() { return; } { print 1; }\nprint 2.5;";
+        let mixed = "var a = 1; print a\nreturn\n{ var b; print b }";
+
+        // Either is the default
+        assert_eq!(parse_with(new_lines, StatementTerminator::default()), Ok(4));
+        assert_eq!(parse_with(semicolons, StatementTerminator::Either), Ok(5));
+        assert_eq!(parse_with(mixed, StatementTerminator::Either), Ok(4));
+
+        assert_eq!(parse_with(new_lines, StatementTerminator::Newline), Ok(4));
+        assert_eq!(
+            parse_with("var a = 1; print a", StatementTerminator::Newline),
+            Err(vec![(ParserErr::UnexpectedSemiColon, Loc::new(9, 10))])
+        );
+
+        assert_eq!(parse_with(semicolons, StatementTerminator::Semicolon), Ok(5));
+        assert_eq!(
+            parse_with("var a = 1 print a;", StatementTerminator::Semicolon),
+            Err(vec![(ParserErr::MissingStmtEnd("';'".into()), Loc::new(10, 15))])
+        );
+        assert_eq!(
+            parse_with("print 1\nprint 2", StatementTerminator::Semicolon),
+            Err(vec![
+                (ParserErr::MissingStmtEnd("';'".into()), Loc::new(8, 13)),
+                (ParserErr::MissingStmtEnd("';'".into()), Loc::new(15, 16)),
+            ])
+        );
+
+        // Several statements on one line need a separator
+        assert_eq!(
+            parse_with("print 1 print 2\nprint 3", StatementTerminator::Either),
+            Err(vec![(ParserErr::MissingStmtEnd("a new line or ';'".into()), Loc::new(8, 13))])
+        );
+    }
+
+    #[test]
+    fn errors_sorted_by_loc() {
+        use crate::lexer::Lexer;
+        use crate::parser::{Parser, ParserWarning};
+
+        let code = "if a = 1 { }\nprint )\nif b = 2 { }\nprint (1";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        // Promoted warnings are found after the errors but are reported
+        // at their place in the code
+        let mut parser = Parser {
+            strict: true,
+            ..Default::default()
+        };
+        let errs = parser.parse(tokens).err().unwrap();
+        let starts: Vec<usize> = errs.iter().map(|e| e.loc.as_ref().unwrap().start).collect();
+
+        assert_eq!(errs.len(), 4);
+        assert!(starts.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(errs[0].err, ParserErr::Strict(ParserWarning::AssignInCondition("a".into())));
+        assert!(matches!(errs[1].err, ParserErr::UnexpectedToken(_)));
+        assert_eq!(errs[2].err, ParserErr::Strict(ParserWarning::AssignInCondition("b".into())));
+        assert_eq!(errs[3].err, ParserErr::ParenNeverClosed);
+    }
+
+    #[test]
+    fn leftover_tokens() {
+        use crate::parser::parse;
+
+        let errs = parse("5 5").err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].err, ParserErr::UnexpectedToken("Int '5' at 2..3".into()));
+        assert_eq!(errs[0].loc, Some(Loc::new(2, 3)));
+
+        let errs = parse("var a\na = 1 b\nprint a").err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].err, ParserErr::UnexpectedToken("Identifier 'b' at 12..13".into()));
+
+        // Other statements are missing their terminator
+        let errs = parse("print 5 5").err().unwrap();
+        assert!(matches!(errs[0].err, ParserErr::MissingStmtEnd(_)));
+
+        assert!(parse("5; 5").is_ok());
+        assert!(parse("{ 5 }\n5\n5").is_ok());
+    }
+
+    #[test]
+    fn max_depth() {
+        use crate::parser::{parse, parse_expression, MAX_DEPTH};
+
+        // Chained operators aren't nesting, however long
+        let chains = [
+            vec!["1"; 10_000].join(" + "),
+            vec!["2"; 10_000].join(" * "),
+            format!("f{}", "(1)".repeat(10_000)),
+        ];
+
+        for code in &chains {
+            assert_eq!(&parse_expression(code).unwrap().to_string(), code);
+        }
+
+        // Test threads have a smaller stack than the main thread
+        let limits = std::thread::Builder::new().stack_size(8 << 20).spawn(|| {
+            // Up to the limit, nested expressions are fine
+            let parens = format!("{}1{}", "(".repeat(MAX_DEPTH - 1), ")".repeat(MAX_DEPTH - 1));
+            assert!(parse_expression(&parens).is_ok());
+
+            // Past it, they are rejected instead of aborting later
+            let too_deep = [
+                format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)),
+                format!("{}1", "- ".repeat(10_000)),
+                format!("f{}1{}", "(".repeat(10_000), ")".repeat(10_000)),
+            ];
+
+            for code in too_deep {
+                let errs = parse_expression(&code).err().unwrap();
+                assert_eq!(errs[0].err, ParserErr::MaxDepth(MAX_DEPTH));
+            }
+
+            let code = format!("{}\nprint 1\n{}\nprint 2", "{".repeat(10_000), "}".repeat(10_000));
+            let errs = parse(&code).err().unwrap();
+            assert_eq!(errs[0].err, ParserErr::MaxDepth(MAX_DEPTH));
+
+            // The limit is per expression
+            let code = format!("var a = {}\nprint {}", parens, parens);
+            assert!(parse(&code).is_ok());
+        });
+        limits.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn assign_loc() {
+        use crate::ast::expr::Expr;
+        use crate::parser::parse_expression;
+
+        let Expr::Assign(assign) = parse_expression("foo = 1 + 2").unwrap() else { panic!() };
+        assert_eq!(assign.loc, Loc::new(0, 11));
+
+        // Chained assignments each cover their own target
+        let Expr::Assign(outer) = parse_expression("a = bc = 12").unwrap() else { panic!() };
+        assert_eq!(outer.loc, Loc::new(0, 11));
+        let Expr::Assign(inner) = *outer.value else { panic!() };
+        assert_eq!(inner.loc, Loc::new(4, 11));
+    }
+
+    #[test]
+    fn reserved_keywords() {
+        use crate::parser::parse;
+
+        let code = "
+var while = 1
+fn if() {}
+fn f(a, for) {}
+var
+var ok = 1
+";
+        let errs = parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+
+        assert_eq!(
+            e,
+            vec![
+                &ParserErr::ReservedKeywordAsIdentifier("while".into()),
+                &ParserErr::ReservedKeywordAsIdentifier("if".into()),
+                &ParserErr::ReservedKeywordAsIdentifier("for".into()),
+                &ParserErr::VarDeclNoName,
+            ]
+        );
+    }
+
+    #[test]
+    fn print_keyword_loc() {
+        use crate::ast::stmt::Stmt;
+        use crate::parser::parse;
+
+        let nodes = parse("print 1 + 2\n{\n  print \"a\"\n}").unwrap();
+
+        let Stmt::Print(s) = &nodes[0] else { panic!() };
+        assert_eq!(s.keyword_loc, Loc::new(0, 5));
+        assert_eq!(s.expr.get_loc(), Loc::new(6, 11));
+        assert_eq!(s.loc, Loc::new(0, 11));
+
+        let Stmt::Block(b) = &nodes[1] else { panic!() };
+        let Stmt::Print(s) = &b.stmts[0] else { panic!() };
+        assert_eq!(s.keyword_loc, Loc::new(16, 21));
+    }
+
+    #[test]
+    fn empty_bodies() {
+        use crate::lexer::Lexer;
+        use crate::parser::{Parser, ParserWarning};
+
+        let code = "
+if x { }
+if x { print 1 } else {}
+while x {
+
+}
+while var a = f() {}
+for i in 3 {}
+{}
+fn f() {}
+if x { print 1 } else { print 2 }
+while x { print 1 }
+";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        // Opt-in
+        let mut parser = Parser::default();
+        parser.parse(tokens).unwrap();
+        assert!(parser.warnings.is_empty());
+
+        let mut parser = Parser {
+            warn_empty_bodies: true,
+            ..Default::default()
+        };
+        parser.parse(tokens).unwrap();
+
+        let warnings: Vec<(ParserWarning, Loc)> =
+            parser.warnings.into_iter().map(|w| (w.err, w.loc.unwrap())).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (ParserWarning::EmptyBody("if".into()), Loc::new(6, 9)),
+                (ParserWarning::EmptyBody("else".into()), Loc::new(32, 34)),
+                (ParserWarning::EmptyBody("while".into()), Loc::new(43, 47)),
+                (ParserWarning::EmptyBody("while".into()), Loc::new(66, 68)),
+                (ParserWarning::EmptyBody("for".into()), Loc::new(80, 82)),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_effect_exprs() {
+        use crate::lexer::Lexer;
+        use crate::parser::{Parser, ParserWarning};
+
+        // Values of the 'if' expression branches are used, not the
+        // statements before them
+        let code = "1 + 2\nf()\na = 1\na ?? g()\n-a.b\nx[h()]\nvar v = if a { 1 } else { a; 2 }\n";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        // Opt-in
+        let mut parser = Parser::default();
+        parser.parse(tokens).unwrap();
+        assert!(parser.warnings.is_empty());
+
+        let mut parser = Parser {
+            warn_no_effect: true,
+            ..Default::default()
+        };
+        parser.parse(tokens).unwrap();
+
+        let warnings: Vec<(ParserWarning, Loc)> =
+            parser.warnings.into_iter().map(|w| (w.err, w.loc.unwrap())).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (ParserWarning::NoEffectExpr, Loc::new(0, 5)),
+                (ParserWarning::NoEffectExpr, Loc::new(25, 29)),
+                (ParserWarning::NoEffectExpr, Loc::new(63, 64)),
+            ]
+        );
+
+        // Implicit returns and the value of the program are used
+        let code = "fn add(a, b) {\n    a\n    a + b\n}\nadd(1, 2)\n2 * 3\n-1\n";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        let mut parser = Parser {
+            warn_no_effect: true,
+            ..Default::default()
+        };
+        parser.parse(tokens).unwrap();
+
+        let warnings: Vec<(ParserWarning, Loc)> =
+            parser.warnings.into_iter().map(|w| (w.err, w.loc.unwrap())).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (ParserWarning::NoEffectExpr, Loc::new(19, 20)),
+                (ParserWarning::NoEffectExpr, Loc::new(43, 48)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_stats() {
+        use crate::lexer::Lexer;
+        use crate::parser::{ParseStats, Parser};
+
+        let code = "var a = 1 + 2\nif a > 2 { print a }\n";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+
+        let mut parser = Parser::default();
+        let (res, stats) = parser.parse_with_stats(tokens);
+        assert!(res.is_ok());
+
+        // Statements: var, if, print. Expressions: 1 + 2, 1, 2, a > 2, a, 2, a
+        assert_eq!(stats.nodes, 10);
+        assert_eq!(stats.tokens, tokens.len() - 1);
+        assert_eq!(stats.errors, 0);
+        assert!(stats.max_depth > 0);
+
+        // Deeper code, deeper parsing
+        let tokens = Lexer::new().tokenize("((((1))))").unwrap().to_vec();
+        let (_, deep) = Parser::default().parse_with_stats(&tokens);
+        assert!(deep.max_depth > stats.max_depth);
+
+        // Statements with errors don't count
+        let tokens = Lexer::new().tokenize("var a = 1\nvar = 2\nprint +").unwrap().to_vec();
+        let (res, stats) = Parser::default().parse_with_stats(&tokens);
+        assert_eq!(res.unwrap_err().len(), 2);
+        assert_eq!(stats.nodes, 2);
+        assert_eq!(stats.errors, 2);
+
+        assert_eq!(Parser::default().parse_with_stats(&[]).1, ParseStats::default());
+    }
 }
@@ -2,11 +2,11 @@ use ecow::EcoString;
 
 use crate::ast::{
     expr::{
-        AssignExpr, BinaryExpr, CallExpr, GroupingExpr, IdentifierExpr, IntLiteralExpr,
-        LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr,
+        AssignExpr, BinaryExpr, BoolLiteralExpr, CallExpr, GetExpr, GroupingExpr, IfExpr, IdentifierExpr, IndexExpr, IntLiteralExpr,
+        LogicalExpr, NullLiteralExpr, RealLiteralExpr, SliceExpr, StrLiteralExpr, TypeCheckExpr, UnaryExpr, VisitExpr,
     },
     stmt::{
-        BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileStmt
+        BlockStmt, EnumDeclStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ImportStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileLetStmt, WhileStmt
     },
 };
 
@@ -34,9 +34,12 @@ pub struct StmtInfos {
     pub block: Vec<StmtInfos>,
     pub if_stmt: Vec<IfInfos>,
     pub while_stmt: Vec<WhileInfos>,
+    pub while_let: Vec<WhileLetInfos>,
     pub for_stmt: Vec<ForInfos>,
     pub fn_decl: Vec<FnDeclInfos>,
     pub return_stmt: Vec<Option<ExprInfos>>,
+    pub import: Vec<EcoString>,
+    pub enum_decl: Vec<(EcoString, Vec<EcoString>)>,
 }
 
 #[derive(Clone, Debug, PartialEq, Default)]
@@ -52,6 +55,13 @@ pub struct WhileInfos {
     pub body: StmtInfos,
 }
 
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct WhileLetInfos {
+    pub name: EcoString,
+    pub value: ExprInfos,
+    pub body: StmtInfos,
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct ForInfos {
     pub placeholder: EcoString,
@@ -74,9 +84,12 @@ impl StmtInfos {
         self.block.append(&mut other.block);
         self.if_stmt.append(&mut other.if_stmt);
         self.while_stmt.append(&mut other.while_stmt);
+        self.while_let.append(&mut other.while_let);
         self.for_stmt.append(&mut other.for_stmt);
         self.fn_decl.append(&mut other.fn_decl);
         self.return_stmt.append(&mut other.return_stmt);
+        self.import.append(&mut other.import);
+        self.enum_decl.append(&mut other.enum_decl);
     }
 }
 
@@ -100,6 +113,10 @@ impl VisitStmt<StmtInfos, ParserTestErr> for TestParser {
             infos.print = vec![format!("{}", v)];
         } else if let Some(v) = expr.get_str_values().first() {
             infos.print = vec![format!("{}", v)];
+        } else if let Some(v) = expr.get_bool_values().first() {
+            infos.print = vec![format!("{}", v)];
+        } else if !expr.null.is_empty() {
+            infos.print = vec!["null".into()];
         } else if let Some(v) = expr.get_ident_values().first() {
             infos.print = vec![format!("{}", v)];
         }
@@ -170,6 +187,16 @@ impl VisitStmt<StmtInfos, ParserTestErr> for TestParser {
         })
     }
 
+    fn visit_while_let_stmt(&mut self, stmt: &WhileLetStmt) -> Result<StmtInfos, PhyResult<ParserTestErr>> {
+        let value = stmt.value.accept(self)?;
+        let body = stmt.body.accept(self)?;
+
+        Ok(StmtInfos {
+            while_let: vec![WhileLetInfos { name: stmt.name.clone(), value, body }],
+            ..Default::default()
+        })
+    }
+
     fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Result<StmtInfos, PhyResult<ParserTestErr>> {
         let placeholder = stmt.placeholder.name.clone();
         let range = (stmt.range.start, stmt.range.end);
@@ -211,6 +238,17 @@ impl VisitStmt<StmtInfos, ParserTestErr> for TestParser {
 
         Ok(StmtInfos { return_stmt: vec![value], ..Default::default() })
     }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<StmtInfos, PhyResult<ParserTestErr>> {
+        Ok(StmtInfos { import: vec![stmt.path.clone()], ..Default::default() })
+    }
+
+    fn visit_enum_decl_stmt(&mut self, stmt: &EnumDeclStmt) -> Result<StmtInfos, PhyResult<ParserTestErr>> {
+        Ok(StmtInfos {
+            enum_decl: vec![(stmt.name.clone(), stmt.variants.clone())],
+            ..Default::default()
+        })
+    }
 }
 
 #[derive(Default, Debug, PartialEq, Clone)]
@@ -219,6 +257,7 @@ pub struct ExprInfos {
     pub real: Vec<RealInfo>,
     pub str: Vec<StrInfo>,
     pub bool: Vec<BoolInfo>,
+    pub null: Vec<NullInfo>,
     pub binop: Vec<BinopInfo>,
     pub grouping: Vec<GroupingInfo>,
     pub ident: Vec<IdentifierInfo>,
@@ -226,6 +265,11 @@ pub struct ExprInfos {
     pub assign: Vec<AssignInfo>,
     pub logical: Vec<LogicalInfo>,
     pub call: Vec<CallInfo>,
+    pub type_check: Vec<TypeCheckInfo>,
+    pub get: Vec<GetInfo>,
+    pub index: Vec<IndexInfo>,
+    pub slice: Vec<SliceInfo>,
+    pub if_expr: Vec<IfExprInfo>,
 }
 
 impl ExprInfos {
@@ -241,6 +285,10 @@ impl ExprInfos {
         self.str.iter().map(|i| i.value.clone()).collect()
     }
 
+    pub fn get_bool_values(&self) -> Vec<&bool> {
+        self.bool.iter().map(|b| &b.value).collect()
+    }
+
     pub fn get_grp_values(&self) -> Vec<&ExprInfos> {
         self.grouping.iter().map(|g| &g.expr).collect()
     }
@@ -278,12 +326,19 @@ impl ExprInfos {
         self.int.append(&mut other.int);
         self.real.append(&mut other.real);
         self.str.append(&mut other.str);
+        self.bool.append(&mut other.bool);
+        self.null.append(&mut other.null);
         self.binop.append(&mut other.binop);
         self.grouping.append(&mut other.grouping);
         self.unary.append(&mut other.unary);
         self.assign.append(&mut other.assign);
         self.logical.append(&mut other.logical);
         self.call.append(&mut other.call);
+        self.type_check.append(&mut other.type_check);
+        self.get.append(&mut other.get);
+        self.index.append(&mut other.index);
+        self.slice.append(&mut other.slice);
+        self.if_expr.append(&mut other.if_expr);
     }
 }
 
@@ -319,6 +374,11 @@ pub struct BoolInfo {
     pub loc: Loc,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct NullInfo {
+    pub loc: Loc,
+}
+
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct GroupingInfo {
     pub expr: ExprInfos,
@@ -359,6 +419,45 @@ pub struct CallInfo {
     pub loc: Loc,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeCheckInfo {
+    pub value: ExprInfos,
+    pub type_name: EcoString,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetInfo {
+    pub object: ExprInfos,
+    pub name: EcoString,
+    pub optional: bool,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexInfo {
+    pub object: ExprInfos,
+    pub index: ExprInfos,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SliceInfo {
+    pub object: ExprInfos,
+    pub start: ExprInfos,
+    pub end: ExprInfos,
+    pub inclusive: bool,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IfExprInfo {
+    pub condition: ExprInfos,
+    pub then_branch: ExprInfos,
+    pub else_branch: ExprInfos,
+    pub loc: Loc,
+}
+
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct TestParser {
     pub infos: StmtInfos,
@@ -442,6 +541,29 @@ impl VisitExpr<ExprInfos, ParserTestErr> for TestParser {
         Ok(infos)
     }
 
+    fn visit_bool_literal_expr(
+        &mut self,
+        expr: &BoolLiteralExpr,
+    ) -> Result<ExprInfos, PhyResParserTestErr> {
+        let mut infos = ExprInfos::default();
+        infos.bool.push(BoolInfo {
+            value: expr.value,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_null_literal_expr(
+        &mut self,
+        expr: &NullLiteralExpr,
+    ) -> Result<ExprInfos, PhyResParserTestErr> {
+        let mut infos = ExprInfos::default();
+        infos.null.push(NullInfo { loc: expr.loc.clone() });
+
+        Ok(infos)
+    }
+
     fn visit_identifier_expr(
         &mut self,
         expr: &IdentifierExpr,
@@ -511,4 +633,63 @@ impl VisitExpr<ExprInfos, ParserTestErr> for TestParser {
 
         Ok(infos)
     }
+
+    fn visit_type_check_expr(&mut self, expr: &TypeCheckExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+        infos.type_check.push(TypeCheckInfo {
+            value: expr.value.accept(self)?,
+            type_name: expr.type_name.clone(),
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+        infos.get.push(GetInfo {
+            object: expr.object.accept(self)?,
+            name: expr.name.clone(),
+            optional: expr.optional,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+        infos.index.push(IndexInfo {
+            object: expr.object.accept(self)?,
+            index: expr.index.accept(self)?,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_slice_expr(&mut self, expr: &SliceExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+        infos.slice.push(SliceInfo {
+            object: expr.object.accept(self)?,
+            start: expr.start.accept(self)?,
+            end: expr.end.accept(self)?,
+            inclusive: expr.inclusive,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_if_expr(&mut self, expr: &IfExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+        infos.if_expr.push(IfExprInfo {
+            condition: expr.condition.accept(self)?,
+            then_branch: expr.then_branch.value.accept(self)?,
+            else_branch: expr.else_branch.value.accept(self)?,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
 }
@@ -1,16 +1,12 @@
 use crate::{
     ast::stmt::Stmt,
-    lexer::Lexer,
-    parser::{Parser, PhyResParser},
+    parser::{parse, PhyResParser},
 };
 
 use super::test_parser::{StmtInfos, ExprInfos, TestParser};
 
 pub fn lex_and_parse(code: &str) -> Result<Vec<Stmt>, Vec<PhyResParser>> {
-    let mut lexer = Lexer::new();
-    let tokens = lexer.tokenize(code).unwrap();
-    let mut parser = Parser::default();
-    parser.parse(tokens)
+    parse(code)
 }
 
 pub fn get_nodes_infos(code: &str) -> StmtInfos {
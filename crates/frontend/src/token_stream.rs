@@ -0,0 +1,175 @@
+use colored::*;
+use thiserror::Error;
+
+use crate::lexer::{Token, TokenKind};
+use tools::results::{Loc, PhyReport, PhyResult};
+
+
+// ----------------
+// Error managment
+// ----------------
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum TokenStreamErr {
+    #[error("token stream must end with an end of file token")]
+    MissingEof,
+
+    #[error("unexpected end of file")]
+    UnexpectedEof,
+
+    #[error("expected token type '{0}', found: {1}")]
+    ExpectedToken(String, String),
+}
+
+impl PhyReport for TokenStreamErr {
+    fn get_err_msg(&self) -> String {
+        format!("{} {}", "Token stream error:".red(), self)
+    }
+}
+
+pub type PhyResStream = PhyResult<TokenStreamErr>;
+
+
+// --------------
+//  Token stream
+// --------------
+// Cursor over the lexer's tokens. The last token is always Eof: it is
+// never consumed and every look-ahead past the end returns it
+#[derive(Debug, Default, Clone)]
+pub struct TokenStream<'a> {
+    tokens: &'a [Token],
+    current: usize,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(tokens: &'a [Token]) -> Result<Self, PhyResStream> {
+        match tokens.last() {
+            Some(tk) if tk.kind == TokenKind::Eof => Ok(Self { tokens, current: 0 }),
+            last => {
+                let loc = last.map(|tk| Loc::new(tk.loc.end, tk.loc.end));
+                Err(PhyResult::new(TokenStreamErr::MissingEof, loc))
+            }
+        }
+    }
+
+    // Token to be consumed next
+    pub fn peek(&self) -> &'a Token {
+        self.peek_nth(0)
+    }
+
+    // n-th token after the next one
+    pub fn peek_nth(&self, n: usize) -> &'a Token {
+        let idx = (self.current + n).min(self.tokens.len() - 1);
+        &self.tokens[idx]
+    }
+
+    // Last consumed token
+    pub fn prev(&self) -> &'a Token {
+        &self.tokens[self.current - 1]
+    }
+
+    // Number of tokens consumed
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    pub fn is_at(&self, kind: TokenKind) -> bool {
+        self.peek().kind == kind
+    }
+
+    pub fn eof(&self) -> bool {
+        self.current >= self.tokens.len() || self.is_at(TokenKind::Eof)
+    }
+
+    // Not an Iterator: reaching the end is an error, not the end
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<&'a Token, PhyResStream> {
+        if self.eof() {
+            return Err(PhyResult::new(
+                TokenStreamErr::UnexpectedEof,
+                self.tokens.last().map(|tk| tk.loc.clone()),
+            ));
+        }
+
+        self.current += 1;
+        Ok(self.prev())
+    }
+
+    // Consumes the next token, even if it isn't of the expected kind
+    pub fn expect(&mut self, kind: TokenKind) -> Result<&'a Token, PhyResStream> {
+        let tk = self.next()?;
+
+        match tk.kind == kind {
+            true => Ok(tk),
+            false => Err(PhyResult::new(
+                TokenStreamErr::ExpectedToken(format!("{:?}", kind), tk.describe()),
+                Some(tk.loc.clone()),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tools::results::Loc;
+
+    use super::{TokenStream, TokenStreamErr};
+    use crate::lexer::{Lexer, TokenKind};
+
+    #[test]
+    fn peek_ahead() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("var a = 1").unwrap();
+        let mut stream = TokenStream::new(tokens).unwrap();
+
+        assert_eq!(stream.peek().kind, TokenKind::Var);
+        assert_eq!(stream.peek_nth(1).value, "a");
+        assert_eq!(stream.peek_nth(3).kind, TokenKind::Int);
+        // Past the end, Eof is returned
+        assert_eq!(stream.peek_nth(4).kind, TokenKind::Eof);
+        assert_eq!(stream.peek_nth(100).kind, TokenKind::Eof);
+
+        // Peeking doesn't consume
+        assert_eq!(stream.position(), 0);
+        assert_eq!(stream.next().unwrap().kind, TokenKind::Var);
+        assert_eq!(stream.position(), 1);
+        assert_eq!(stream.prev().kind, TokenKind::Var);
+        assert!(stream.is_at(TokenKind::Identifier));
+        assert_eq!(stream.peek_nth(2).value, "1");
+
+        for _ in 0..3 {
+            stream.next().unwrap();
+        }
+
+        // Eof is never consumed
+        assert!(stream.eof());
+        let err = stream.next().unwrap_err();
+        assert_eq!(err.err, TokenStreamErr::UnexpectedEof);
+        assert_eq!(stream.peek().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn expect() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("fn f").unwrap();
+        let mut stream = TokenStream::new(tokens).unwrap();
+
+        assert_eq!(stream.expect(TokenKind::Fn).unwrap().value, "fn");
+
+        let err = stream.expect(TokenKind::OpenParen).unwrap_err();
+        assert_eq!(
+            err.err,
+            TokenStreamErr::ExpectedToken("OpenParen".into(), "Identifier 'f' at 3..4".into())
+        );
+        assert_eq!(err.loc, Some(Loc::new(3, 4)));
+        // The wrong token is consumed anyway
+        assert!(stream.eof());
+
+        let err = stream.expect(TokenKind::OpenParen).unwrap_err();
+        assert_eq!(err.err, TokenStreamErr::UnexpectedEof);
+
+        let err = TokenStream::new(&tokens[..1]).unwrap_err();
+        assert_eq!(err.err, TokenStreamErr::MissingEof);
+        assert_eq!(err.loc, Some(Loc::new(2, 2)));
+        assert!(TokenStream::new(&[]).is_err());
+    }
+}
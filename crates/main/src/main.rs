@@ -10,7 +10,9 @@ use colored::*;
 use frontend::{
     ast::ast_pretty_print::AstPrinter, lexer::Lexer, parser::Parser,
 };
+use resolver::resolver::Resolver;
 use runtime::{interpreter::Interpreter, values::RtVal};
+use tools::results::{LineDirective, PhyReport, PhyResult, DEFAULT_TAB_WIDTH};
 
 // --------
 //   Cli
@@ -24,6 +26,10 @@ struct Cli {
     /// Path to the file to parse
     file: Option<String>,
 
+    /// Evaluates a single expression and prints its value
+    #[arg(short, long)]
+    expr: Option<String>,
+
     /// Interactive mode after interpreting a file
     #[arg(short, long)]
     inter: bool,
@@ -35,6 +41,22 @@ struct Cli {
     // Prints the AST tree
     #[arg(short, long)]
     print_ast: bool,
+
+    /// Reports warnings as errors
+    #[arg(long)]
+    strict: bool,
+
+    /// Tail calls don't grow the call stack
+    #[arg(long)]
+    tco: bool,
+
+    /// Number of decimals of the printed reals
+    #[arg(long)]
+    real_precision: Option<usize>,
+
+    /// Columns between two tab stops in the reported code
+    #[arg(long, default_value_t = DEFAULT_TAB_WIDTH)]
+    tab_width: usize,
 }
 
 struct Repl {
@@ -47,22 +69,61 @@ fn main() {
     let mut repl = Repl {
         cli: Cli::parse(),
         ast_printer: AstPrinter {},
-        interpreter: Interpreter::new(),
+        interpreter: Interpreter::with_output(Box::new(io::stdout())),
     };
+    repl.interpreter.tco = repl.cli.tco;
+    repl.interpreter.real_precision = repl.cli.real_precision;
 
     repl.run();
 }
 
 impl Repl {
     pub fn run(&mut self) {
+        if let Some(e) = &self.cli.expr {
+            self.run_expr(e.clone());
+            return;
+        }
+
         let _ = match &self.cli.file {
             Some(f) => self.run_file(f.clone()),
             None => self.run_repl(),
         };
     }
 
+    fn run_expr(&mut self, code: String) {
+        let mut lexer = Lexer::new();
+        let mut parser = Parser::default();
+        parser.strict = self.cli.strict;
+
+        let tokens = match lexer.tokenize(&code) {
+            Ok(tk) => tk,
+            Err(e) => {
+                e.iter().for_each(|e| self.report(e, "<expr>", &code, &[]));
+                return;
+            }
+        };
+
+        let res = parser.parse_expression(tokens);
+        parser.warnings.iter().for_each(|w| self.report(w, "<expr>", &code, &[]));
+
+        let expr = match res {
+            Ok(expr) => expr,
+            Err(e) => {
+                e.iter().for_each(|e| self.report(e, "<expr>", &code, &[]));
+                return;
+            }
+        };
+
+        match expr.accept(&mut self.interpreter) {
+            Ok(res) => println!("{}", res),
+            Err(e) => self.report(&e, "<expr>", &code, &[]),
+        }
+    }
+
     fn run_file(&mut self, file_path: String) -> Result<(), Box<dyn Error>> {
-        let code = fs::read_to_string(file_path)?;
+        let code = fs::read_to_string(&file_path)?;
+        // Imports are resolved relative to the file being run
+        self.interpreter.files.push(fs::canonicalize(&file_path)?);
         self.sequence(code);
 
         Ok(())
@@ -99,12 +160,14 @@ impl Repl {
     fn sequence(&mut self, code: String) {
         let mut lexer = Lexer::new();
         let mut parser = Parser::default();
+        parser.strict = self.cli.strict;
 
         let tokens = match lexer.tokenize(&code) {
             Ok(tk) => tk,
             Err(e) => {
-                e.iter()
-                    .for_each(|e| e.report(&"placeholder.rz".into(), &code));
+                e.iter().for_each(|e| {
+                    self.report(e, "placeholder.rz", &code, &lexer.line_directives)
+                });
 
                 return;
             }
@@ -114,11 +177,18 @@ impl Repl {
             println!("Tokens: {:#?}", tokens);
         }
 
-        let nodes = match parser.parse(tokens) {
+        let res = parser.parse(tokens);
+
+        parser.warnings.iter().for_each(|w| {
+            self.report(w, "placeholder.rz", &code, &lexer.line_directives)
+        });
+
+        let nodes = match res {
             Ok(n) => n,
             Err(e) => {
-                e.iter()
-                    .for_each(|e| e.report(&"placeholder.rz".into(), &code));
+                e.iter().for_each(|e| {
+                    self.report(e, "placeholder.rz", &code, &lexer.line_directives)
+                });
                 return;
             }
         };
@@ -129,13 +199,31 @@ impl Repl {
             }
         }
 
+        if let Err(e) = Resolver::default().resolve(&nodes) {
+            self.report(&e, "placeholder.rz", &code, &lexer.line_directives);
+            return;
+        }
+
         match self.interpreter.interpret(&nodes) {
             Ok(res) => {
                 if res != RtVal::Null {
                     println!("{}", res);
                 }
             }
-            Err(e) => e.report(&"placeholder.rz".into(), &code),
+            Err(e) => {
+                self.report(&e, "placeholder.rz", &code, &lexer.line_directives);
+
+                if !self.interpreter.backtrace.is_empty() {
+                    println!(
+                        "{}",
+                        self.interpreter.render_backtrace("placeholder.rz", &code, &lexer.line_directives)
+                    );
+                }
+            }
         }
     }
+
+    fn report<T: PhyReport>(&self, res: &PhyResult<T>, file_name: &str, code: &str, directives: &[LineDirective]) {
+        print!("{}", res.render(file_name, code, directives, self.cli.tab_width));
+    }
 }
@@ -3,16 +3,16 @@ use std::collections::HashMap;
 use colored::Colorize;
 use ecow::EcoString;
 use thiserror::Error;
-use tools::results::{PhyReport, PhyResult};
+use tools::results::{Loc, PhyReport, PhyResult};
 
 use frontend::ast::{
     expr::{
-        AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr,
-        LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr,
+        AssignExpr, BinaryExpr, BoolLiteralExpr, CallExpr, Expr, GetExpr, GroupingExpr, IfExpr, IfExprBranch, IdentifierExpr, IndexExpr, IntLiteralExpr,
+        LogicalExpr, NullLiteralExpr, RealLiteralExpr, SliceExpr, StrLiteralExpr, TypeCheckExpr, UnaryExpr, VisitExpr,
     },
     stmt::{
-        BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt,
-        VisitStmt, WhileStmt,
+        BlockStmt, EnumDeclStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ImportStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt,
+        VisitStmt, WhileLetStmt, WhileStmt,
     },
 };
 
@@ -20,15 +20,32 @@ use frontend::ast::{
 pub enum ResolverErr {
     #[error("local variable initializer is shadoweding global variable")]
     LocalVarInOwnInit,
+
+    #[error("'return' outside of a function")]
+    ReturnOutsideFn,
 }
 
 impl PhyReport for ResolverErr {
     fn get_err_msg(&self) -> String {
-        format!("{}: ", "Resolver error".red())
+        format!("{} {}", "Resolver error:".red(), self)
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ResolverWarning {
+    // Location of the shadowed declaration
+    #[error("variable '{0}' shadows one of an enclosing scope, prefix it with '_' if it's intended")]
+    ShadowedVar(String, Loc),
+}
+
+impl PhyReport for ResolverWarning {
+    fn get_err_msg(&self) -> String {
+        format!("{} {}", "Resolver warning:".yellow(), self)
     }
 }
 
 pub type ResolverRes = Result<(), PhyResult<ResolverErr>>;
+pub type PhyResResolverWarning = PhyResult<ResolverWarning>;
 
 // Bool is for tracking if the variable is initialized, avoiding weird cases
 // where we initialize the variable with its shadowing global one
@@ -40,6 +57,15 @@ pub type ResolverRes = Result<(), PhyResult<ResolverErr>>;
 pub struct Resolver {
     scopes: Vec<HashMap<EcoString, bool>>,
     locals: HashMap<EcoString, usize>,
+    // Number of function bodies being resolved
+    fn_depth: usize,
+    pub warnings: Vec<PhyResResolverWarning>,
+    // Warns about variables declared with the name of one of an
+    // enclosing scope
+    pub warn_shadowing: bool,
+    // Declaration of the variables of each scope, globals apart
+    var_locs: Vec<HashMap<EcoString, Loc>>,
+    global_locs: HashMap<EcoString, Loc>,
 }
 
 // If we can’t find it in the stack of local scopes, we assume it must be global
@@ -58,6 +84,16 @@ impl Resolver {
         expr.accept(self)
     }
 
+    // Each branch of an 'if' expression is a scope, as a block
+    fn resolve_branch(&mut self, branch: &IfExprBranch) -> ResolverRes {
+        self.begin_scope();
+        self.resolve(&branch.stmts)?;
+        self.resolve_expr(&branch.value)?;
+        self.end_scope();
+
+        Ok(())
+    }
+
     fn resolve_local(&mut self, name: &EcoString) {
         for (idx, scope) in self.scopes.iter().rev().enumerate() {
             match scope.get(name) {
@@ -95,24 +131,59 @@ impl Resolver {
     fn resolve_fn(&mut self, stmt: &FnDeclStmt) -> ResolverRes {
         self.begin_scope();
 
+        // Parameters have no location of their own
         stmt.params.iter().for_each(|p| {
+            self.check_shadowing(p, &stmt.loc);
+            self.record_var_loc(p.clone(), stmt.loc.clone());
             self.declare(p.clone());
             self.define(p.clone());
         });
 
-        self.resolve(&stmt.body.clone())?;
+        self.fn_depth += 1;
+        let res = self.resolve(&stmt.body.clone());
+        self.fn_depth -= 1;
+        res?;
 
         self.end_scope();
 
         Ok(())
     }
 
+    // Names starting with '_' can shadow on purpose
+    fn check_shadowing(&mut self, name: &EcoString, loc: &Loc) {
+        if !self.warn_shadowing || self.scopes.is_empty() || name.starts_with('_') {
+            return;
+        }
+
+        let outer = self
+            .var_locs
+            .iter()
+            .rev()
+            .skip(1)
+            .find_map(|s| s.get(name))
+            .or_else(|| self.global_locs.get(name));
+
+        if let Some(outer) = outer {
+            let warning = ResolverWarning::ShadowedVar(name.to_string(), outer.clone());
+            self.warnings.push(PhyResult::new(warning, Some(loc.clone())));
+        }
+    }
+
+    fn record_var_loc(&mut self, name: EcoString, loc: Loc) {
+        match self.var_locs.last_mut() {
+            Some(scope) => scope.insert(name, loc),
+            None => self.global_locs.insert(name, loc),
+        };
+    }
+
     fn begin_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.var_locs.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
+        self.var_locs.pop();
     }
 }
 
@@ -128,6 +199,8 @@ impl VisitStmt<(), ResolverErr> for Resolver {
     }
 
     fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> ResolverRes {
+        self.check_shadowing(&stmt.name, &stmt.loc);
+        self.record_var_loc(stmt.name.clone(), stmt.loc.clone());
         self.declare(stmt.name.clone());
 
         if let Some(v) = &stmt.value {
@@ -165,6 +238,20 @@ impl VisitStmt<(), ResolverErr> for Resolver {
         self.resolve_stmt(&stmt.body)
     }
 
+    fn visit_while_let_stmt(&mut self, stmt: &WhileLetStmt) -> ResolverRes {
+        self.resolve_expr(&stmt.value)?;
+
+        self.begin_scope();
+        self.check_shadowing(&stmt.name, &stmt.loc);
+        self.record_var_loc(stmt.name.clone(), stmt.loc.clone());
+        self.declare(stmt.name.clone());
+        self.define(stmt.name.clone());
+        self.resolve_stmt(&stmt.body)?;
+        self.end_scope();
+
+        Ok(())
+    }
+
     fn visit_for_stmt(&mut self, stmt: &ForStmt) -> ResolverRes {
         self.resolve_stmt(&(&stmt.placeholder).into())?;
         self.resolve_stmt(&stmt.body)
@@ -178,12 +265,28 @@ impl VisitStmt<(), ResolverErr> for Resolver {
     }
 
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> ResolverRes {
+        if self.fn_depth == 0 {
+            return Err(PhyResult::new(ResolverErr::ReturnOutsideFn, Some(stmt.loc.clone())));
+        }
+
         if let Some(v) = &stmt.value {
             self.resolve_expr(v)?;
         }
 
         Ok(())
     }
+
+    // Imported declarations are only known once the file is read, at runtime
+    fn visit_import_stmt(&mut self, _: &ImportStmt) -> ResolverRes {
+        Ok(())
+    }
+
+    fn visit_enum_decl_stmt(&mut self, stmt: &EnumDeclStmt) -> ResolverRes {
+        self.declare(stmt.name.clone());
+        self.define(stmt.name.clone());
+
+        Ok(())
+    }
 }
 
 impl VisitExpr<(), ResolverErr> for Resolver {
@@ -210,6 +313,14 @@ impl VisitExpr<(), ResolverErr> for Resolver {
         Ok(())
     }
 
+    fn visit_bool_literal_expr(&mut self, _: &BoolLiteralExpr) -> ResolverRes {
+        Ok(())
+    }
+
+    fn visit_null_literal_expr(&mut self, _: &NullLiteralExpr) -> ResolverRes {
+        Ok(())
+    }
+
     fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> ResolverRes {
         if !self.scopes.is_empty()
             && self.scopes.last().unwrap().get(&expr.name) == Some(&false)
@@ -230,7 +341,7 @@ impl VisitExpr<(), ResolverErr> for Resolver {
     }
 
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> ResolverRes {
-        self.resolve_expr(&expr.into())?;
+        self.resolve_expr(&expr.value)?;
         self.resolve_local(&expr.name);
 
         Ok(())
@@ -250,11 +361,41 @@ impl VisitExpr<(), ResolverErr> for Resolver {
 
         Ok(())
     }
+
+    fn visit_type_check_expr(&mut self, expr: &TypeCheckExpr) -> ResolverRes {
+        self.resolve_expr(&expr.value)
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> ResolverRes {
+        self.resolve_expr(&expr.object)
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> ResolverRes {
+        self.resolve_expr(&expr.object)?;
+        self.resolve_expr(&expr.index)
+    }
+
+    fn visit_slice_expr(&mut self, expr: &SliceExpr) -> ResolverRes {
+        self.resolve_expr(&expr.object)?;
+        self.resolve_expr(&expr.start)?;
+        self.resolve_expr(&expr.end)
+    }
+
+    fn visit_if_expr(&mut self, expr: &IfExpr) -> ResolverRes {
+        self.resolve_expr(&expr.condition)?;
+        self.resolve_branch(&expr.then_branch)?;
+        self.resolve_branch(&expr.else_branch)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{resolver::ResolverErr, utils::lex_parse_resolve};
+    use tools::results::Loc;
+
+    use crate::{
+        resolver::{Resolver, ResolverErr, ResolverWarning},
+        utils::lex_parse_resolve,
+    };
 
     #[test]
     fn depth() {
@@ -272,7 +413,7 @@ var a
     }
 }
 ";
-        let resolver = lex_parse_resolve(code);
+        assert!(lex_parse_resolve(code).is_ok());
     }
 
     #[test]
@@ -286,5 +427,89 @@ var a
         let resolver = lex_parse_resolve(code);
         let err = resolver.err().unwrap().err;
         assert_eq!(err, ResolverErr::LocalVarInOwnInit);
+
+        // Branches of 'if' expressions are scopes
+        let code = "var a\nvar b = if true { var a = a; a } else { 0 }";
+        let err = lex_parse_resolve(code).err().unwrap().err;
+        assert_eq!(err, ResolverErr::LocalVarInOwnInit);
+    }
+
+    #[test]
+    fn return_outside_fn() {
+        let err = lex_parse_resolve("var a = 1\nreturn a").err().unwrap();
+        assert_eq!(err.err, ResolverErr::ReturnOutsideFn);
+        assert_eq!(err.loc, Some(Loc::new(10, 18)));
+
+        let err = lex_parse_resolve("while true {\n    if true { return }\n}").err().unwrap();
+        assert_eq!(err.err, ResolverErr::ReturnOutsideFn);
+
+        // Once a function is over, its body doesn't count anymore
+        let err = lex_parse_resolve("fn f() { return 1 }\nreturn").err().unwrap();
+        assert_eq!(err.err, ResolverErr::ReturnOutsideFn);
+
+        let code = "
+fn f(a) {
+    fn g() {
+        while true { return 1 }
+    }
+    if a { return g() }
+    return
+}
+";
+        assert!(lex_parse_resolve(code).is_ok());
+    }
+
+    #[test]
+    fn shadowing() {
+        use frontend::parser::utils::lex_and_parse;
+
+        let code = "
+var a = 1
+var b
+{
+    var a = 2
+    var c
+    {
+        var c
+        var _b
+        var d
+    }
+}
+fn f() {
+    var a
+    var d
+}
+fn g(a, _b) {
+    while var b = a {
+        for a in 3 {}
+    }
+}
+";
+        let nodes = lex_and_parse(code).unwrap();
+
+        // Opt-in
+        let mut resolver = Resolver::default();
+        resolver.resolve(&nodes).unwrap();
+        assert!(resolver.warnings.is_empty());
+
+        let mut resolver = Resolver {
+            warn_shadowing: true,
+            ..Default::default()
+        };
+        resolver.resolve(&nodes).unwrap();
+
+        let warnings: Vec<(ResolverWarning, Loc)> =
+            resolver.warnings.into_iter().map(|w| (w.err, w.loc.unwrap())).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (ResolverWarning::ShadowedVar("a".into(), Loc::new(1, 10)), Loc::new(23, 32)),
+                (ResolverWarning::ShadowedVar("c".into(), Loc::new(37, 42)), Loc::new(57, 62)),
+                (ResolverWarning::ShadowedVar("a".into(), Loc::new(1, 10)), Loc::new(113, 118)),
+                (ResolverWarning::ShadowedVar("a".into(), Loc::new(1, 10)), Loc::new(131, 196)),
+                (ResolverWarning::ShadowedVar("b".into(), Loc::new(11, 16)), Loc::new(149, 165)),
+                (ResolverWarning::ShadowedVar("a".into(), Loc::new(131, 196)), Loc::new(175, 181)),
+            ]
+        );
     }
 }
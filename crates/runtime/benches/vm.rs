@@ -0,0 +1,50 @@
+// Compares the bytecode VM against the tree-walking interpreter on an
+// arithmetic heavy loop. Run with: cargo bench -p runtime --bench vm
+use std::time::{Duration, Instant};
+
+use frontend::{lexer::Lexer, parser::Parser};
+use runtime::{compiler::compile, interpreter::Interpreter, values::RtVal, vm::run};
+
+const RUNS: u32 = 5;
+
+const CODE: &str = "
+var sum = 0
+var i = 0
+while i < 100000 {
+    sum = sum + i * 3 % 7 - i / 5
+    i = i + 1
+}
+sum
+";
+
+fn time(f: impl Fn() -> RtVal) -> (Duration, RtVal) {
+    let mut res = f();
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        res = f();
+    }
+
+    (start.elapsed() / RUNS, res)
+}
+
+fn main() {
+    let mut lexer = Lexer::new();
+    let tokens = lexer.tokenize(CODE).unwrap();
+    let mut parser = Parser::default();
+    let nodes = parser.parse(tokens).unwrap();
+
+    let (compile_t, chunk) = {
+        let start = Instant::now();
+        let chunk = compile(&nodes).unwrap();
+        (start.elapsed(), chunk)
+    };
+
+    let (walker_t, walker_res) = time(|| Interpreter::new().interpret(&nodes).unwrap());
+    let (vm_t, vm_res) = time(|| run(&chunk).unwrap());
+    assert_eq!(walker_res, vm_res);
+
+    println!("ops: {}", chunk.code.len());
+    println!("compile: {:?}", compile_t);
+    println!("tree-walker: {:?}", walker_t);
+    println!("vm: {:?}", vm_t);
+}
@@ -0,0 +1,567 @@
+use colored::*;
+use ecow::EcoString;
+use thiserror::Error;
+use tools::results::{Loc, PhyReport, PhyResult};
+
+use crate::{environment::DISCARD, values::RtVal};
+use frontend::ast::expr::{
+    AssignExpr, BinaryExpr, BoolLiteralExpr, CallExpr, GetExpr, GroupingExpr, IdentifierExpr, IfExpr, IfExprBranch, IndexExpr,
+    IntLiteralExpr, LogicalExpr, NullLiteralExpr, RealLiteralExpr, SliceExpr, StrLiteralExpr, TypeCheckExpr, UnaryExpr,
+    VisitExpr,
+};
+use frontend::ast::stmt::{
+    BlockStmt, EnumDeclStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ImportStmt, PrintStmt,
+    ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileLetStmt, WhileStmt,
+};
+
+// ----------------
+// Error managment
+// ----------------
+#[derive(Debug, Error, PartialEq)]
+pub enum CompilerErr {
+    #[error("{0} can't be compiled to bytecode yet")]
+    Unsupported(String),
+
+    #[error("variable '{0}' is already declared in this scope")]
+    AlreadyDeclaredVar(String),
+
+    #[error("'_' only discards values, it can't be read")]
+    DiscardRead,
+}
+
+impl PhyReport for CompilerErr {
+    fn get_err_msg(&self) -> String {
+        format!("{} {}", "Compiler error:".red(), self)
+    }
+}
+
+pub(crate) type PhyResCompiler = PhyResult<CompilerErr>;
+type CompilerRes = Result<(), PhyResCompiler>;
+
+// ----------
+//  Bytecode
+// ----------
+// Indexes in 'Constant' refer to the chunk constant pool, the ones in the
+// global ops to its names pool and the ones in the local ops to a stack
+// slot. Jump targets are absolute positions in the code
+#[derive(Debug, PartialEq, Clone)]
+pub enum Op {
+    Constant(usize),
+    True,
+    False,
+    Null,
+    Pop,
+    // Pops the value of an expression statement, it is the result of the
+    // chunk if it's the last one executed
+    PopResult,
+    Negate,
+    Not,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    TypeCheck(usize),
+    DefineGlobal(usize),
+    GetGlobal(usize),
+    SetGlobal(usize),
+    GetLocal(usize),
+    SetLocal(usize),
+    // Conditional jumps don't pop the condition
+    Jump(usize),
+    JumpIfFalse(usize),
+    JumpIfTrue(usize),
+    JumpIfNotNull(usize),
+    Print,
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<RtVal>,
+    pub names: Vec<EcoString>,
+    // Location of the node each op comes from, used for runtime errors
+    pub locs: Vec<Loc>,
+}
+
+impl Chunk {
+    fn write(&mut self, op: Op, loc: &Loc) -> usize {
+        self.code.push(op);
+        self.locs.push(loc.clone());
+        self.code.len() - 1
+    }
+
+    fn add_constant(&mut self, value: RtVal) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    fn add_name(&mut self, name: &EcoString) -> usize {
+        match self.names.iter().position(|n| n == name) {
+            Some(idx) => idx,
+            None => {
+                self.names.push(name.clone());
+                self.names.len() - 1
+            }
+        }
+    }
+}
+
+// Lowers the statements to bytecode. Top level variables are globals
+// looked up by name, the ones declared in blocks live on the VM stack
+pub fn compile(stmts: &[Stmt]) -> Result<Chunk, PhyResCompiler> {
+    let mut compiler = Compiler::default();
+
+    for stmt in stmts {
+        stmt.accept(&mut compiler)?;
+    }
+
+    Ok(compiler.chunk)
+}
+
+// -----------
+//  Compiling
+// -----------
+struct Local {
+    name: EcoString,
+    depth: usize,
+}
+
+#[derive(Default)]
+struct Compiler {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    depth: usize,
+}
+
+impl Compiler {
+    fn emit_jump(&mut self, op: fn(usize) -> Op, loc: &Loc) -> usize {
+        // Target is patched once known
+        self.chunk.write(op(usize::MAX), loc)
+    }
+
+    fn patch_jump(&mut self, idx: usize) {
+        let target = self.chunk.code.len();
+
+        self.chunk.code[idx] = match self.chunk.code[idx] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfTrue(_) => Op::JumpIfTrue(target),
+            Op::JumpIfNotNull(_) => Op::JumpIfNotNull(target),
+            _ => unreachable!("only jumps are patched"),
+        };
+    }
+
+    fn resolve_local(&self, name: &EcoString) -> Option<usize> {
+        self.locals.iter().rposition(|l| &l.name == name)
+    }
+
+    fn end_scope(&mut self, loc: &Loc) {
+        self.depth -= 1;
+
+        while self.locals.last().is_some_and(|l| l.depth > self.depth) {
+            self.locals.pop();
+            self.chunk.write(Op::Pop, loc);
+        }
+    }
+
+    // The value would be on the stack above the locals of the branch
+    fn compile_branch(&mut self, branch: &IfExprBranch, loc: &Loc) -> CompilerRes {
+        if !branch.stmts.is_empty() {
+            return self.unsupported("statement in an 'if' expression", loc);
+        }
+
+        branch.value.accept(self)
+    }
+
+    fn unsupported(&self, what: &str, loc: &Loc) -> CompilerRes {
+        Err(PhyResult::new(CompilerErr::Unsupported(what.into()), Some(loc.clone())))
+    }
+}
+
+impl VisitStmt<(), CompilerErr> for Compiler {
+    fn visit_expr_stmt(&mut self, stmt: &ExprStmt) -> CompilerRes {
+        stmt.expr.accept(self)?;
+        self.chunk.write(Op::PopResult, &stmt.loc);
+
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> CompilerRes {
+        stmt.expr.accept(self)?;
+        self.chunk.write(Op::Print, &stmt.loc);
+
+        Ok(())
+    }
+
+    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> CompilerRes {
+        match &stmt.value {
+            Some(v) => v.accept(self)?,
+            None => {
+                self.chunk.write(Op::Null, &stmt.loc);
+            }
+        }
+
+        if stmt.name == DISCARD {
+            self.chunk.write(Op::Pop, &stmt.loc);
+            return Ok(());
+        }
+
+        if self.depth == 0 {
+            let idx = self.chunk.add_name(&stmt.name);
+            self.chunk.write(Op::DefineGlobal(idx), &stmt.loc);
+
+            return Ok(());
+        }
+
+        // The value stays on the stack, it is the slot of the local
+        if self
+            .locals
+            .iter()
+            .rev()
+            .take_while(|l| l.depth == self.depth)
+            .any(|l| l.name == stmt.name)
+        {
+            return Err(PhyResult::new(
+                CompilerErr::AlreadyDeclaredVar(stmt.name.to_string()),
+                Some(stmt.loc.clone()),
+            ));
+        }
+
+        self.locals.push(Local { name: stmt.name.clone(), depth: self.depth });
+
+        Ok(())
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> CompilerRes {
+        self.depth += 1;
+
+        for s in &stmt.stmts {
+            s.accept(self)?;
+        }
+
+        self.end_scope(&stmt.loc);
+
+        Ok(())
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> CompilerRes {
+        stmt.condition.accept(self)?;
+
+        let then_jump = self.emit_jump(Op::JumpIfFalse, &stmt.loc);
+        self.chunk.write(Op::Pop, &stmt.loc);
+
+        if let Some(t) = &stmt.then_branch {
+            t.accept(self)?;
+        }
+
+        let else_jump = self.emit_jump(Op::Jump, &stmt.loc);
+        self.patch_jump(then_jump);
+        self.chunk.write(Op::Pop, &stmt.loc);
+
+        if let Some(e) = &stmt.else_branch {
+            e.accept(self)?;
+        }
+
+        self.patch_jump(else_jump);
+
+        Ok(())
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> CompilerRes {
+        let loop_start = self.chunk.code.len();
+        stmt.condition.accept(self)?;
+
+        let exit_jump = self.emit_jump(Op::JumpIfFalse, &stmt.loc);
+        self.chunk.write(Op::Pop, &stmt.loc);
+
+        stmt.body.accept(self)?;
+        self.chunk.write(Op::Jump(loop_start), &stmt.loc);
+
+        self.patch_jump(exit_jump);
+        self.chunk.write(Op::Pop, &stmt.loc);
+
+        Ok(())
+    }
+
+    fn visit_while_let_stmt(&mut self, stmt: &WhileLetStmt) -> CompilerRes {
+        self.unsupported("'while var' loop", &stmt.loc)
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> CompilerRes {
+        self.unsupported("'for' loop", &stmt.loc)
+    }
+
+    fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> CompilerRes {
+        self.unsupported("function declaration", &stmt.loc)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> CompilerRes {
+        self.unsupported("'return' statement", &stmt.loc)
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> CompilerRes {
+        self.unsupported("'import' statement", &stmt.loc)
+    }
+
+    fn visit_enum_decl_stmt(&mut self, stmt: &EnumDeclStmt) -> CompilerRes {
+        self.unsupported("enum declaration", &stmt.loc)
+    }
+}
+
+impl VisitExpr<(), CompilerErr> for Compiler {
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> CompilerRes {
+        expr.left.accept(self)?;
+        expr.right.accept(self)?;
+
+        let op = match expr.operator.as_str() {
+            "+" => Op::Add,
+            "-" => Op::Sub,
+            "*" => Op::Mul,
+            "/" => Op::Div,
+            "%" => Op::Mod,
+            "==" => Op::Equal,
+            "!=" => Op::NotEqual,
+            "<" => Op::Less,
+            "<=" => Op::LessEqual,
+            ">" => Op::Greater,
+            ">=" => Op::GreaterEqual,
+            op => return self.unsupported(&format!("operator '{}'", op), &expr.loc),
+        };
+        self.chunk.write(op, &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> CompilerRes {
+        expr.expr.accept(self)
+    }
+
+    fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> CompilerRes {
+        let idx = self.chunk.add_constant(expr.value.into());
+        self.chunk.write(Op::Constant(idx), &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> CompilerRes {
+        let idx = self.chunk.add_constant(expr.value.into());
+        self.chunk.write(Op::Constant(idx), &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> CompilerRes {
+        let idx = self.chunk.add_constant(expr.value.clone().into());
+        self.chunk.write(Op::Constant(idx), &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_bool_literal_expr(&mut self, expr: &BoolLiteralExpr) -> CompilerRes {
+        let op = if expr.value { Op::True } else { Op::False };
+        self.chunk.write(op, &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_null_literal_expr(&mut self, expr: &NullLiteralExpr) -> CompilerRes {
+        self.chunk.write(Op::Null, &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> CompilerRes {
+        if expr.name == DISCARD {
+            return Err(PhyResult::new(CompilerErr::DiscardRead, Some(expr.loc.clone())));
+        }
+
+        let op = match self.resolve_local(&expr.name) {
+            Some(slot) => Op::GetLocal(slot),
+            None => Op::GetGlobal(self.chunk.add_name(&expr.name)),
+        };
+        self.chunk.write(op, &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> CompilerRes {
+        expr.right.accept(self)?;
+
+        let op = match expr.operator.as_str() {
+            "!" => Op::Not,
+            _ => Op::Negate,
+        };
+        self.chunk.write(op, &expr.loc);
+
+        Ok(())
+    }
+
+    // Like in the tree-walker, an assignment evaluates to the assigned
+    // value. Setting pops it, so it is read back from the variable
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> CompilerRes {
+        expr.value.accept(self)?;
+
+        if expr.name == DISCARD {
+            return Ok(());
+        }
+
+        let (set, get) = match self.resolve_local(&expr.name) {
+            Some(slot) => (Op::SetLocal(slot), Op::GetLocal(slot)),
+            None => {
+                let idx = self.chunk.add_name(&expr.name);
+                (Op::SetGlobal(idx), Op::GetGlobal(idx))
+            }
+        };
+        self.chunk.write(set, &expr.loc);
+        self.chunk.write(get, &expr.loc);
+
+        Ok(())
+    }
+
+    // The left value is kept as result if the jump is taken
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> CompilerRes {
+        expr.left.accept(self)?;
+
+        let jump: fn(usize) -> Op = match expr.operator.as_str() {
+            "and" => Op::JumpIfFalse,
+            "or" => Op::JumpIfTrue,
+            _ => Op::JumpIfNotNull,
+        };
+        let end_jump = self.emit_jump(jump, &expr.loc);
+        self.chunk.write(Op::Pop, &expr.loc);
+
+        expr.right.accept(self)?;
+        self.patch_jump(end_jump);
+
+        Ok(())
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> CompilerRes {
+        self.unsupported("function call", &expr.loc)
+    }
+
+    fn visit_type_check_expr(&mut self, expr: &TypeCheckExpr) -> CompilerRes {
+        expr.value.accept(self)?;
+
+        let idx = self.chunk.add_name(&expr.type_name);
+        self.chunk.write(Op::TypeCheck(idx), &expr.loc);
+
+        Ok(())
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> CompilerRes {
+        self.unsupported("property access", &expr.loc)
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> CompilerRes {
+        self.unsupported("index expression", &expr.loc)
+    }
+
+    fn visit_slice_expr(&mut self, expr: &SliceExpr) -> CompilerRes {
+        self.unsupported("slice expression", &expr.loc)
+    }
+
+    fn visit_if_expr(&mut self, expr: &IfExpr) -> CompilerRes {
+        expr.condition.accept(self)?;
+
+        let then_jump = self.emit_jump(Op::JumpIfFalse, &expr.loc);
+        self.chunk.write(Op::Pop, &expr.loc);
+        self.compile_branch(&expr.then_branch, &expr.loc)?;
+
+        let else_jump = self.emit_jump(Op::Jump, &expr.loc);
+        self.patch_jump(then_jump);
+        self.chunk.write(Op::Pop, &expr.loc);
+        self.compile_branch(&expr.else_branch, &expr.loc)?;
+
+        self.patch_jump(else_jump);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use frontend::parser::utils::lex_and_parse;
+
+    use super::{compile, CompilerErr, Op};
+
+    #[test]
+    fn compile_expr() {
+        let nodes = lex_and_parse("1 + 2 * 3").unwrap();
+        let chunk = compile(&nodes).unwrap();
+
+        assert_eq!(
+            chunk.code,
+            vec![
+                Op::Constant(0),
+                Op::Constant(1),
+                Op::Constant(2),
+                Op::Mul,
+                Op::Add,
+                Op::PopResult
+            ]
+        );
+        assert_eq!(chunk.constants, vec![1.into(), 2.into(), 3.into()]);
+    }
+
+    #[test]
+    fn compile_vars() {
+        let code = "
+var a = 1
+{
+    var b = a
+    b = 2
+}
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let chunk = compile(&nodes).unwrap();
+
+        assert_eq!(
+            chunk.code,
+            vec![
+                Op::Constant(0),
+                Op::DefineGlobal(0),
+                Op::GetGlobal(0),
+                Op::Constant(1),
+                Op::SetLocal(0),
+                Op::GetLocal(0),
+                Op::PopResult,
+                Op::Pop,
+            ]
+        );
+        assert_eq!(chunk.names, vec!["a"]);
+    }
+
+    #[test]
+    fn compile_errors() {
+        let nodes = lex_and_parse("fn add(a, b) { return a + b }").unwrap();
+        assert_eq!(
+            compile(&nodes).err().unwrap().err,
+            CompilerErr::Unsupported("function declaration".into())
+        );
+
+        let nodes = lex_and_parse("var a = if true { var b = 1; b } else { 2 }").unwrap();
+        assert_eq!(
+            compile(&nodes).err().unwrap().err,
+            CompilerErr::Unsupported("statement in an 'if' expression".into())
+        );
+
+        let nodes = lex_and_parse("{\nvar a = 1\nvar a = 2\n}").unwrap();
+        assert_eq!(
+            compile(&nodes).err().unwrap().err,
+            CompilerErr::AlreadyDeclaredVar("a".into())
+        );
+
+        let nodes = lex_and_parse("{\nvar _ = 1\nvar _ = 2\n_ = 3\n}").unwrap();
+        assert!(compile(&nodes).is_ok());
+        let nodes = lex_and_parse("var _ = 1\n_ + 1").unwrap();
+        assert_eq!(compile(&nodes).err().unwrap().err, CompilerErr::DiscardRead);
+    }
+}
@@ -21,9 +21,15 @@ pub enum EnvErr {
 
     #[error("undeclared variable '{0}'")]
     UndeclaredVar(String),
+
+    #[error("'_' only discards values, it can't be read")]
+    DiscardRead,
 }
 
-type EnvWrapper = Rc<RefCell<Env>>;
+// Name binding nothing: values declared or assigned to it are dropped
+pub const DISCARD: &str = "_";
+
+pub type EnvWrapper = Rc<RefCell<Env>>;
 
 // -------------
 //  Environment
@@ -43,6 +49,10 @@ impl Env {
     }
 
     pub fn declare_var(&mut self, var_name: EcoString, value: RtVal) -> Result<(), EnvErr> {
+        if var_name == DISCARD {
+            return Ok(());
+        }
+
         if let Vacant(v) = self.vars.entry(var_name.clone()) {
             v.insert(value);
         } else {
@@ -53,6 +63,10 @@ impl Env {
     }
 
     pub fn get_var(&self, var_name: EcoString) -> Result<RtVal, EnvErr> {
+        if var_name == DISCARD {
+            return Err(EnvErr::DiscardRead);
+        }
+
         match self.vars.get(&var_name) {
             Some(v) => Ok(v.clone()),
             None => {
@@ -66,6 +80,10 @@ impl Env {
     }
 
     pub fn assign(&mut self, var_name: EcoString, value: RtVal) -> Result<(), EnvErr> {
+        if var_name == DISCARD {
+            return Ok(());
+        }
+
         if let Occupied(mut v) = self.vars.entry(var_name.clone()) {
             v.insert(value);
             Ok(())
@@ -107,4 +125,15 @@ mod tests {
             EnvErr::UndeclaredVar { .. }
         ));
     }
+
+    #[test]
+    fn discard() {
+        let mut env = Env::default();
+        assert!(env.declare_var(EcoString::from("_"), 1.into()).is_ok());
+        assert!(env.declare_var(EcoString::from("_"), 2.into()).is_ok());
+        assert!(env.assign(EcoString::from("_"), 3.into()).is_ok());
+
+        assert!(env.vars.is_empty());
+        assert!(matches!(env.get_var(EcoString::from("_")), Err(EnvErr::DiscardRead)));
+    }
 }
@@ -0,0 +1,146 @@
+use thiserror::Error;
+use tools::results::{PhyReport, PhyResult};
+
+use frontend::parser::{parse, ParserErr};
+
+use crate::environment::EnvWrapper;
+use crate::interpreter::{InterpErr, Interpreter};
+use crate::values::RtVal;
+
+// ----------------
+// Error managment
+// ----------------
+#[derive(Debug, Error, PartialEq)]
+pub enum EvalErr {
+    #[error("{0}")]
+    Parsing(ParserErr),
+
+    #[error("{0}")]
+    Runtime(InterpErr),
+}
+
+impl PhyReport for EvalErr {
+    fn get_err_msg(&self) -> String {
+        match self {
+            EvalErr::Parsing(e) => e.get_err_msg(),
+            EvalErr::Runtime(e) => e.get_err_msg(),
+        }
+    }
+}
+
+pub type PhyResEval = PhyResult<EvalErr>;
+pub type EvalRes = Result<RtVal, Vec<PhyResEval>>;
+
+/// Runs the code in a new interpreter and returns the value of the last
+/// statement, null if it isn't an expression
+///
+/// ```
+/// use runtime::eval::eval;
+///
+/// assert_eq!(eval("var a = 2\na * 3").unwrap(), 6.into());
+/// assert!(eval("1 +").is_err());
+/// ```
+pub fn eval(source: &str) -> EvalRes {
+    Interpreter::new().eval(source)
+}
+
+// Keeps the interpreter and its global environment across evaluations,
+// so that a snippet can use what the previous ones declared
+#[derive(Default)]
+pub struct Session {
+    interpreter: Interpreter,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn eval(&mut self, source: &str) -> EvalRes {
+        self.interpreter.eval(source)
+    }
+
+    // Global environment, for the host to read or declare variables
+    pub fn env(&self) -> EnvWrapper {
+        self.interpreter.globals.clone()
+    }
+}
+
+impl Interpreter {
+    // Same as 'eval' but the code runs in this interpreter, with the
+    // variables declared by the previous runs
+    pub fn eval(&mut self, source: &str) -> EvalRes {
+        let nodes = parse(source).map_err(|errs| {
+            errs.into_iter()
+                .map(|e| PhyResult::new(EvalErr::Parsing(e.err), e.loc))
+                .collect::<Vec<_>>()
+        })?;
+
+        self.interpret(&nodes)
+            .map_err(|e| vec![PhyResult::new(EvalErr::Runtime(e.err), e.loc)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecow::EcoString;
+    use frontend::parser::ParserErr;
+
+    use super::{eval, EvalErr, Session};
+    use crate::{interpreter::InterpErr, values::RtVal};
+
+    #[test]
+    fn eval_last_value() {
+        assert_eq!(eval("1 + 2").unwrap(), 3.into());
+        assert_eq!(eval("").unwrap(), RtVal::Null);
+
+        let code = "
+fn greet(name) {
+    return \"hello \" + name
+}
+var msg = greet(\"raze\")
+msg
+";
+        assert_eq!(eval(code).unwrap(), EcoString::from("hello raze").into());
+
+        // Statements that aren't expressions evaluate to null
+        assert_eq!(eval("var a = 1\nprint a").unwrap(), RtVal::Null);
+    }
+
+    #[test]
+    fn eval_errors() {
+        let errs = eval("print (1\nprint 2 +").err().unwrap();
+        assert_eq!(errs.len(), 2);
+        assert!(errs.iter().all(|e| matches!(e.err, EvalErr::Parsing(_))));
+
+        let errs = eval("\"abc").err().unwrap();
+        assert!(matches!(errs[0].err, EvalErr::Parsing(ParserErr::Lexing(_))));
+
+        let errs = eval("var a = 1\na + true").err().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(errs[0].err, EvalErr::Runtime(InterpErr::OperationEvaluation(_))));
+    }
+
+    #[test]
+    fn session_keeps_env() {
+        let mut session = Session::new();
+
+        assert_eq!(session.eval("fn f() { return 42 }").unwrap(), RtVal::Null);
+        assert_eq!(session.eval("f()").unwrap(), 42.into());
+
+        // Errors don't reset the session
+        assert!(session.eval("var a = 1\na + true").is_err());
+        assert_eq!(session.eval("a + f()").unwrap(), 43.into());
+
+        session
+            .env()
+            .borrow_mut()
+            .declare_var(EcoString::from("host"), 8.into())
+            .unwrap();
+        assert_eq!(session.eval("host * 2").unwrap(), 16.into());
+        assert_eq!(session.env().borrow().get_var("a".into()).unwrap(), 1.into());
+
+        // Each session has its own environment
+        assert!(Session::new().eval("f()").is_err());
+    }
+}
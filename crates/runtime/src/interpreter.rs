@@ -1,23 +1,31 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use colored::Colorize;
 use ecow::EcoString;
 use thiserror::Error;
-use tools::results::{PhyReport, PhyResult};
+use tools::results::{line_of, map_line, LineDirective, Loc, PhyReport, PhyResult};
 
 use crate::callable::Callable;
-use crate::environment::Env;
-use crate::native_functions::{NativeClock, PhyNativeFn};
-use crate::values::RtVal;
+use crate::environment::{Env, EnvErr};
+use crate::native_functions::{
+    NativeClock, NativeFnErr, NativeFnPtr, NativeHost, NativeInput, NativeLen, PhyNativeFn,
+};
+use crate::values::{Function, OverflowMode, RtVal};
 use frontend::ast::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr, LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr
+    AssignExpr, BinaryExpr, BoolLiteralExpr, CallExpr, Expr, GetExpr, GroupingExpr, IfExpr, IfExprBranch, IdentifierExpr, IndexExpr, IntLiteralExpr, LogicalExpr, NullLiteralExpr, RealLiteralExpr, SliceExpr, StrLiteralExpr, TypeCheckExpr, UnaryExpr, VisitExpr
 };
 use frontend::ast::stmt::{
-    BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt,
-    VisitStmt, WhileStmt,
+    BlockStmt, EnumDeclStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ImportStmt, PrintStmt, ReturnStmt, Stmt,
+    VarDeclStmt, VisitStmt, WhileLetStmt, WhileStmt,
 };
+use frontend::lexer::Lexer;
+use frontend::parser::Parser;
 
 // ----------------
 // Error managment
@@ -39,8 +47,8 @@ pub enum InterpErr {
     Negation(String),
 
     // Variables
-    #[error("{0}")]
-    VarDeclEnv(String),
+    #[error("'{0}' is already declared in this scope")]
+    AlreadyDeclared(String),
 
     #[error("{0}")]
     GetVarEnv(String),
@@ -73,9 +81,45 @@ pub enum InterpErr {
     #[error("{0}")]
     FnCall(String),
 
+    // Property
+    #[error("values of type '{0}' don't have properties")]
+    NoProperties(String),
+
+    #[error("enum '{0}' has no variant '{1}'")]
+    UnknownEnumVariant(String, String),
+
+    // Index
+    #[error("{0}")]
+    Index(String),
+
+    // Import
+    #[error("can't read imported file '{0}': {1}")]
+    ImportRead(String, String),
+
+    #[error("in imported file '{0}': {1}")]
+    ImportFile(String, String),
+
+    #[error("circular import of file '{0}'")]
+    CircularImport(String),
+
+    // Print
+    #[error("can't write output: {0}")]
+    Output(String),
+
+    #[error("maximum call depth of {0} exceeded")]
+    StackOverflow(usize),
+
+    #[error("budget of {0} steps exceeded")]
+    Timeout(usize),
+
     // Results
     #[error("return: {0}")]
     Return(RtVal),
+
+    // 'return f(..)' with tail calls optimization, the function being
+    // called runs the new call instead of nesting it
+    #[error("tail call of '{}'", .0.name)]
+    TailCall(Rc<Function>, Vec<RtVal>),
 }
 
 impl PhyReport for InterpErr {
@@ -96,39 +140,158 @@ pub struct Interpreter {
     pub globals: Rc<RefCell<Env>>,
     pub env: Rc<RefCell<Env>>,
     pub locals: HashMap<Rc<Expr>, usize>,
+    // Files currently being executed, the last one being the innermost
+    // import. Used to resolve relative paths and detect import cycles
+    pub files: Vec<PathBuf>,
+    // Where 'print' writes
+    pub out: Box<dyn Write>,
+    // Where 'input' reads lines, stdin if none
+    pub input: Option<Box<dyn BufRead>>,
+    pub overflow_mode: OverflowMode,
+    // Functions being called, the innermost last
+    pub call_stack: Vec<CallFrame>,
+    // Call stack at the moment the last error occurred
+    pub backtrace: Vec<CallFrame>,
+    // Calls deeper than this are an error instead of a native stack overflow
+    pub max_call_depth: usize,
+    // Calls in progress. Unlike the call stack, imports never reset it
+    depth: usize,
+    // Tail calls reuse the current call frame
+    pub tco: bool,
+    // Decimals of the printed reals, the shortest exact form if none
+    pub real_precision: Option<usize>,
+    // Steps allowed in a host call to 'interpret' or 'exec_stmt', imports
+    // included, unlimited if none. Blocks, loop iterations and calls are
+    // steps: anything running for ever goes through them
+    pub max_steps: Option<usize>,
+    steps: usize,
+    // Bools are used as ints, 1 and 0, in arithmetic and comparisons
+    pub bool_as_int: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+    pub fn_name: EcoString,
+    pub loc: Loc,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+
+    pub fn with_output(out: Box<dyn Write>) -> Self {
         let globals = Rc::new(RefCell::new(Env::new(None)));
 
-        let _ = globals.borrow_mut().declare_var(
-            EcoString::from("clock"),
-            RtVal::NativeFnVal(Rc::new(PhyNativeFn {
-                name: EcoString::from("clock"),
-                func: Rc::new(NativeClock),
-            })),
-        );
+        let natives: [(&str, Rc<dyn Callable<NativeFnErr>>); 3] = [
+            ("clock", Rc::new(NativeClock)),
+            ("len", Rc::new(NativeLen)),
+            ("input", Rc::new(NativeInput)),
+        ];
+
+        for (name, func) in natives {
+            let _ = globals.borrow_mut().declare_var(
+                EcoString::from(name),
+                RtVal::NativeFnVal(Rc::new(PhyNativeFn { name: EcoString::from(name), func })),
+            );
+        }
 
         let env = globals.clone();
 
-        Self { globals, env, locals: HashMap::new() }
+        Self {
+            globals,
+            env,
+            locals: HashMap::new(),
+            files: vec![],
+            out,
+            input: None,
+            overflow_mode: OverflowMode::default(),
+            call_stack: vec![],
+            backtrace: vec![],
+            max_call_depth: 1000,
+            depth: 0,
+            tco: false,
+            real_precision: None,
+            max_steps: None,
+            steps: 0,
+            bool_as_int: false,
+        }
+    }
+
+    // Exposes a host function to the scripts as a global. The arity is
+    // checked at each call, before the function runs
+    pub fn register_native(&mut self, name: &str, arity: usize, f: NativeFnPtr) -> Result<(), EnvErr> {
+        let func = Rc::new(NativeHost { arity, func: f });
+
+        self.globals.borrow_mut().declare_var(
+            EcoString::from(name),
+            RtVal::NativeFnVal(Rc::new(PhyNativeFn { name: EcoString::from(name), func })),
+        )
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Interpreter {
     pub fn interpret(&mut self, nodes: &Vec<Stmt>) -> InterpRes {
+        self.start_run();
+
+        self.run(nodes)
+    }
+
+    // Executes statements in the middle of a run, as the imports do: the
+    // call stack of the functions they are called from is kept
+    fn run(&mut self, nodes: &Vec<Stmt>) -> InterpRes {
         let mut res: RtVal = RtVal::new_null();
 
         for node in nodes {
-            match node.accept(self) {
-                Ok(r) => res = r,
-                Err(e) => return Err(e),
-            }
+            res = node.accept(self)?;
         }
 
         Ok(res)
     }
+
+    // Executes one statement in the current environment, for hosts
+    // feeding statements one at a time. Returns the value of an
+    // expression statement, null otherwise
+    pub fn exec_stmt(&mut self, stmt: &Stmt) -> InterpRes {
+        self.start_run();
+
+        stmt.accept(self)
+    }
+
+    // Forgets the previous run. Only for the host entry points, the
+    // statements of the imported files are run in the middle of one
+    fn start_run(&mut self) {
+        self.steps = 0;
+        self.call_stack.clear();
+        self.backtrace.clear();
+    }
+
+    fn step(&mut self, loc: &Loc) -> Result<(), PhyResInterp> {
+        self.steps += 1;
+
+        match self.max_steps {
+            Some(max) if self.steps > max => {
+                Err(PhyResult::new(InterpErr::Timeout(max), Some(loc.clone())))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Declares in the current scope only, an enclosing one can be shadowed
+    fn declare(&self, name: &EcoString, value: RtVal, loc: &Loc) -> Result<(), PhyResInterp> {
+        self.env
+            .borrow_mut()
+            .declare_var(name.clone(), value)
+            .map_err(|_| {
+                PhyResult::new(InterpErr::AlreadyDeclared(name.to_string()), Some(loc.clone()))
+            })
+    }
 }
 
 impl VisitStmt<RtVal, InterpErr> for Interpreter {
@@ -138,7 +301,10 @@ impl VisitStmt<RtVal, InterpErr> for Interpreter {
 
     fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> InterpRes {
         let value = stmt.expr.accept(self)?;
-        println!("{}", value);
+
+        writeln!(self.out, "{}", print_text(&value, self.real_precision)).map_err(|e| {
+            PhyResult::new(InterpErr::Output(e.to_string()), Some(stmt.loc.clone()))
+        })?;
 
         Ok(RtVal::new_null())
     }
@@ -148,17 +314,14 @@ impl VisitStmt<RtVal, InterpErr> for Interpreter {
             None => RtVal::new_null(),
         };
 
-        self.env
-            .borrow_mut()
-            .declare_var(stmt.name.clone(), value)
-            .map_err(|e| {
-                PhyResult::new(InterpErr::VarDeclEnv(e.to_string()), Some(stmt.loc.clone()))
-            })?;
+        self.declare(&stmt.name, value, &stmt.loc)?;
 
         Ok(RtVal::new_null())
     }
 
     fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> InterpRes {
+        self.step(&stmt.loc)?;
+
         let new_env = Env::new(Some(self.env.clone()));
         self.execute_block_stmt(&stmt.stmts, new_env)?;
 
@@ -194,6 +357,7 @@ impl VisitStmt<RtVal, InterpErr> for Interpreter {
 
     fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> InterpRes {
         loop {
+            self.step(&stmt.loc)?;
             let cond = stmt.condition.accept(self)?;
 
             match cond {
@@ -215,6 +379,30 @@ impl VisitStmt<RtVal, InterpErr> for Interpreter {
         Ok(RtVal::new_null())
     }
 
+    // Each iteration binds the value in a new scope, closures made in
+    // the body keep their own value
+    fn visit_while_let_stmt(&mut self, stmt: &WhileLetStmt) -> InterpRes {
+        loop {
+            self.step(&stmt.loc)?;
+            let value = stmt.value.accept(self)?;
+
+            if value == RtVal::Null {
+                break;
+            }
+
+            let mut new_env = Env::new(Some(self.env.clone()));
+            let _ = new_env.declare_var(stmt.name.clone(), value);
+
+            let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(new_env)));
+            let res = stmt.body.accept(self);
+            self.env = prev_env;
+
+            res?;
+        }
+
+        Ok(RtVal::new_null())
+    }
+
     fn visit_for_stmt(&mut self, stmt: &ForStmt) -> InterpRes {
         let new_env = Env::new(Some(self.env.clone()));
         let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(new_env)));
@@ -227,6 +415,7 @@ impl VisitStmt<RtVal, InterpErr> for Interpreter {
         }
 
         for i in range {
+            self.step(&stmt.loc)?;
             self.env
                 .borrow_mut()
                 .assign(stmt.placeholder.name.clone(), i.into())
@@ -245,15 +434,7 @@ impl VisitStmt<RtVal, InterpErr> for Interpreter {
     fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> Result<RtVal, PhyResult<InterpErr>> {
         let func = RtVal::new_fn(stmt, self.env.clone());
 
-        self.env
-            .borrow_mut()
-            .declare_var(stmt.name.clone(), func)
-            .map_err(|_| {
-                PhyResult::new(
-                    InterpErr::VarDeclEnv(stmt.name.to_string()),
-                    Some(stmt.loc.clone()),
-                )
-            })?;
+        self.declare(&stmt.name, func, &stmt.loc)?;
 
         Ok(RtVal::new_null())
     }
@@ -262,11 +443,68 @@ impl VisitStmt<RtVal, InterpErr> for Interpreter {
         let mut value = RtVal::new_null();
 
         if let Some(v) = &stmt.value {
+            if let Expr::Call(call) = v {
+                if self.tco && self.depth > 0 {
+                    return self.tail_call(call);
+                }
+            }
+
             value = v.accept(self)?;
         }
 
         Err(PhyResult::new(InterpErr::Return(value), None))
     }
+
+    fn visit_enum_decl_stmt(&mut self, stmt: &EnumDeclStmt) -> InterpRes {
+        self.declare(&stmt.name, RtVal::new_enum(stmt), &stmt.loc)?;
+
+        Ok(RtVal::new_null())
+    }
+
+    // The imported file is executed in the current environment, so its
+    // top level declarations become visible to the importer
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> InterpRes {
+        let import_err = |e: InterpErr| PhyResult::new(e, Some(stmt.loc.clone()));
+
+        let path = match self.files.last().and_then(|f| f.parent()) {
+            Some(dir) => dir.join(stmt.path.as_str()),
+            None => PathBuf::from(stmt.path.as_str()),
+        };
+        let path = path
+            .canonicalize()
+            .map_err(|e| import_err(InterpErr::ImportRead(stmt.path.to_string(), e.to_string())))?;
+
+        if self.files.contains(&path) {
+            return Err(import_err(InterpErr::CircularImport(stmt.path.to_string())));
+        }
+
+        let code = fs::read_to_string(&path)
+            .map_err(|e| import_err(InterpErr::ImportRead(stmt.path.to_string(), e.to_string())))?;
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).map_err(|errs| {
+            let msg = errs.iter().map(|e| e.err.to_string()).collect::<Vec<_>>().join(", ");
+            import_err(InterpErr::ImportFile(stmt.path.to_string(), msg))
+        })?;
+
+        let mut parser = Parser::default();
+        let nodes = parser.parse(tokens).map_err(|errs| {
+            let msg = errs.iter().map(|e| e.err.to_string()).collect::<Vec<_>>().join(", ");
+            import_err(InterpErr::ImportFile(stmt.path.to_string(), msg))
+        })?;
+
+        self.files.push(path);
+        let res = self.run(&nodes);
+        self.files.pop();
+
+        match res {
+            Ok(_) => Ok(RtVal::new_null()),
+            // Circular imports are reported as is so the error isn't
+            // nested once per file in the cycle
+            Err(e) if matches!(e.err, InterpErr::CircularImport(..)) => Err(e),
+            Err(e) => Err(import_err(InterpErr::ImportFile(stmt.path.to_string(), e.err.to_string()))),
+        }
+    }
 }
 
 impl Interpreter {
@@ -286,6 +524,221 @@ impl Interpreter {
 
         res
     }
+
+    // Branch of an 'if' expression, in its own scope as a block
+    fn eval_branch(&mut self, branch: &IfExprBranch) -> InterpRes {
+        let env = Env::new(Some(self.env.clone()));
+        let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(env)));
+
+        let mut res = Ok(RtVal::new_null());
+        for s in branch.stmts.iter() {
+            res = s.accept(self);
+
+            if res.is_err() {
+                break;
+            }
+        }
+
+        if res.is_ok() {
+            res = branch.value.accept(self);
+        }
+
+        let _ = std::mem::replace(&mut self.env, prev_env);
+
+        res
+    }
+
+    // User functions and native ones only differ by their error type
+    fn call_fn<T: PhyReport + Display>(
+        &mut self,
+        name: &EcoString,
+        func: &dyn Callable<T>,
+        args: Vec<RtVal>,
+        loc: &Loc,
+    ) -> InterpRes {
+        if func.arity() != args.len() {
+            return Err(PhyResult::new(
+                InterpErr::WrongArgsNb(func.arity(), args.len()),
+                Some(loc.clone()),
+            ));
+        }
+
+        self.step(loc)?;
+
+        if self.depth >= self.max_call_depth {
+            return Err(PhyResult::new(
+                InterpErr::StackOverflow(self.max_call_depth),
+                Some(loc.clone()),
+            ));
+        }
+
+        self.depth += 1;
+        self.call_stack.push(CallFrame { fn_name: name.clone(), loc: loc.clone() });
+        let res = func.call(self, args);
+
+        // The error goes through every frame on its way up, the stack is
+        // saved by the innermost one only
+        if res.is_err() && self.backtrace.is_empty() {
+            self.backtrace = self.call_stack.clone();
+        }
+        self.call_stack.pop();
+        self.depth -= 1;
+
+        res.map_err(|e| PhyResult::new(InterpErr::FnCall(e.err.to_string()), Some(loc.clone())))
+    }
+
+    // A '?.' on null skips the rest of the chain: 'a?.b.c(1)[0]' is null
+    // when 'a' is, which None tells. Parentheses end the chain
+    fn eval_chain(&mut self, expr: &Expr) -> Result<Option<RtVal>, PhyResInterp> {
+        match expr {
+            Expr::Get(e) => self.eval_get(e),
+            Expr::Call(e) => self.eval_call(e),
+            Expr::Index(e) => self.eval_index(e),
+            Expr::Slice(e) => self.eval_slice(e),
+            e => e.accept(self).map(Some),
+        }
+    }
+
+    fn eval_get(&mut self, expr: &GetExpr) -> Result<Option<RtVal>, PhyResInterp> {
+        let Some(object) = self.eval_chain(&expr.object)? else {
+            return Ok(None);
+        };
+
+        match &object {
+            RtVal::Null if expr.optional => Ok(None),
+            RtVal::EnumVal(e) => e.get_variant(&expr.name).map(Some).ok_or_else(|| {
+                PhyResult::new(
+                    InterpErr::UnknownEnumVariant(e.name.to_string(), expr.name.to_string()),
+                    Some(expr.loc.clone()),
+                )
+            }),
+            _ => Err(PhyResult::new(
+                InterpErr::NoProperties(object.get_type_name().to_string()),
+                Some(expr.loc.clone()),
+            )),
+        }
+    }
+
+    fn eval_call(&mut self, expr: &CallExpr) -> Result<Option<RtVal>, PhyResInterp> {
+        let Some(callee) = self.eval_chain(&expr.callee)? else {
+            return Ok(None);
+        };
+
+        let mut args: Vec<RtVal> = vec![];
+        for a in &expr.args {
+            args.push(a.accept(self)?);
+        }
+
+        match callee {
+            RtVal::FuncVal(f) => self.call_fn(&f.name, f.as_ref(), args, &expr.loc).map(Some),
+            RtVal::NativeFnVal(f) => self.call_fn(&f.name, f.func.as_ref(), args, &expr.loc).map(Some),
+            _ => Err(PhyResult::new(InterpErr::NonFnCall, Some(expr.loc.clone()))),
+        }
+    }
+
+    fn eval_index(&mut self, expr: &IndexExpr) -> Result<Option<RtVal>, PhyResInterp> {
+        let Some(object) = self.eval_chain(&expr.object)? else {
+            return Ok(None);
+        };
+        let index = expr.index.accept(self)?;
+
+        object.index(&index).map(Some).map_err(|e| {
+            PhyResult::new(InterpErr::Index(e.to_string()), Some(expr.loc.clone()))
+        })
+    }
+
+    fn eval_slice(&mut self, expr: &SliceExpr) -> Result<Option<RtVal>, PhyResInterp> {
+        let Some(object) = self.eval_chain(&expr.object)? else {
+            return Ok(None);
+        };
+        let start = expr.start.accept(self)?;
+        let end = expr.end.accept(self)?;
+
+        object.slice(&start, &end, expr.inclusive).map(Some).map_err(|e| {
+            PhyResult::new(InterpErr::Index(e.to_string()), Some(expr.loc.clone()))
+        })
+    }
+
+    // Only calls to user functions are optimized, as they are the
+    // ones able to recurse
+    fn tail_call(&mut self, expr: &CallExpr) -> InterpRes {
+        self.step(&expr.loc)?;
+        let Some(callee) = self.eval_chain(&expr.callee)? else {
+            return Err(PhyResult::new(InterpErr::Return(RtVal::Null), None));
+        };
+
+        let mut args: Vec<RtVal> = vec![];
+        for a in &expr.args {
+            args.push(a.accept(self)?);
+        }
+
+        let func = match callee {
+            RtVal::FuncVal(f) => f,
+            RtVal::NativeFnVal(f) => {
+                let value = self.call_fn(&f.name, f.func.as_ref(), args, &expr.loc)?;
+                return Err(PhyResult::new(InterpErr::Return(value), None));
+            }
+            _ => return Err(PhyResult::new(InterpErr::NonFnCall, Some(expr.loc.clone()))),
+        };
+
+        if func.params.len() != args.len() {
+            return Err(PhyResult::new(
+                InterpErr::WrongArgsNb(func.params.len(), args.len()),
+                Some(expr.loc.clone()),
+            ));
+        }
+
+        // The current frame is reused by the called function
+        if let Some(frame) = self.call_stack.last_mut() {
+            *frame = CallFrame { fn_name: func.name.clone(), loc: expr.loc.clone() };
+        }
+
+        Err(PhyResult::new(InterpErr::TailCall(func, args), None))
+    }
+
+    // One line per frame of the last error, the innermost first
+    pub fn render_backtrace(
+        &self,
+        file_name: &str,
+        code: &str,
+        directives: &[LineDirective],
+    ) -> String {
+        self.backtrace
+            .iter()
+            .rev()
+            .map(|frame| {
+                let (file, line) = map_line(directives, file_name, line_of(code, frame.loc.start));
+
+                format!("  in '{}', called from {} [line {}]", frame.fn_name, file, line)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+// Text written by 'print', with the given decimals for reals
+pub(crate) fn print_text(value: &RtVal, real_precision: Option<usize>) -> String {
+    match (value, real_precision) {
+        (RtVal::RealVal(r), Some(p)) => format!("{:.*}", p, r.borrow().value),
+        _ => value.to_string(),
+    }
+}
+
+// Bools next to a number or another bool become ints. With anything
+// else, like a string, the operation stays an error
+pub(crate) fn coerce_bools(lhs: RtVal, rhs: RtVal) -> (RtVal, RtVal) {
+    let numeric = |v: &RtVal| matches!(v, RtVal::IntVal(_) | RtVal::RealVal(_) | RtVal::BoolVal(_));
+
+    if !numeric(&lhs) || !numeric(&rhs) {
+        return (lhs, rhs);
+    }
+
+    let to_int = |v: RtVal| match &v {
+        RtVal::BoolVal(b) => (b.borrow().value as i64).into(),
+        _ => v,
+    };
+
+    (to_int(lhs), to_int(rhs))
 }
 
 impl VisitExpr<RtVal, InterpErr> for Interpreter {
@@ -306,7 +759,12 @@ impl VisitExpr<RtVal, InterpErr> for Interpreter {
             ));
         }
 
-        match lhs.operate(&rhs, &expr.operator) {
+        let (lhs, rhs) = match self.bool_as_int {
+            true => coerce_bools(lhs, rhs),
+            false => (lhs, rhs),
+        };
+
+        match lhs.operate_with(&rhs, &expr.operator, self.overflow_mode) {
             Ok(res) => Ok(res),
             Err(e) => Err(PhyResult::new(
                 InterpErr::OperationEvaluation(e.to_string()),
@@ -320,12 +778,13 @@ impl VisitExpr<RtVal, InterpErr> for Interpreter {
 
         self.env
             .borrow_mut()
-            .assign(expr.name.clone(), value)
+            .assign(expr.name.clone(), value.clone())
             .map_err(|e| {
                 PhyResult::new(InterpErr::AssignEnv(e.to_string()), Some(expr.loc.clone()))
             })?;
 
-        Ok(RtVal::new_null())
+        // The value is given back for chained assignments: 'a = b = 1'
+        Ok(value)
     }
 
     fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> InterpRes {
@@ -344,19 +803,19 @@ impl VisitExpr<RtVal, InterpErr> for Interpreter {
         Ok(expr.value.clone().into())
     }
 
+    fn visit_bool_literal_expr(&mut self, expr: &BoolLiteralExpr) -> InterpRes {
+        Ok(expr.value.into())
+    }
+
+    fn visit_null_literal_expr(&mut self, _: &NullLiteralExpr) -> InterpRes {
+        Ok(RtVal::new_null())
+    }
+
     fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> InterpRes {
-        match expr.name.as_str() {
-            "true" => Ok(true.into()),
-            "false" => Ok(false.into()),
-            "null" => Ok(RtVal::new_null()),
-            _ => self
-                .env
-                .borrow()
-                .get_var(expr.name.clone())
-                .map_err(|e| {
-                    PhyResult::new(InterpErr::GetVarEnv(e.to_string()), Some(expr.loc.clone()))
-                }),
-        }
+        self.env
+            .borrow()
+            .get_var(expr.name.clone())
+            .map_err(|e| PhyResult::new(InterpErr::GetVarEnv(e.to_string()), Some(expr.loc.clone())))
     }
 
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> InterpRes {
@@ -378,7 +837,7 @@ impl VisitExpr<RtVal, InterpErr> for Interpreter {
             _ => {}
         }
 
-        value.negate().map_err(|e| {
+        value.negate_with(self.overflow_mode).map_err(|e| {
             PhyResult::new(InterpErr::Negation(e.to_string()), Some(expr.loc.clone()))
         })?;
 
@@ -417,41 +876,67 @@ impl VisitExpr<RtVal, InterpErr> for Interpreter {
                     ))
                 }
             }
+        } else if op == "??" && left != RtVal::Null {
+            return Ok(left);
         }
 
         expr.right.accept(self)
     }
 
     fn visit_call_expr(&mut self, expr: &CallExpr) -> InterpRes {
-        let callee = expr.callee.accept(self)?;
+        Ok(self.eval_call(expr)?.unwrap_or(RtVal::Null))
+    }
 
-        let mut args: Vec<RtVal> = vec![];
-        for a in &expr.args {
-            args.push(a.accept(self)?);
-        }
+    // Unknown type names aren't an error, no value can be of that type
+    fn visit_type_check_expr(&mut self, expr: &TypeCheckExpr) -> InterpRes {
+        let value = expr.value.accept(self)?;
 
-        if let RtVal::FuncVal(f) = callee {
-            if f.arity() != args.len() {
-                return Err(PhyResult::new(
-                    InterpErr::WrongArgsNb(f.arity(), args.len()),
-                    Some(expr.loc.clone()),
-                ));
-            }
+        Ok((value.get_type_name() == expr.type_name).into())
+    }
 
-            f.call(self, args).map_err(|e| {
-                PhyResult::new(InterpErr::FnCall(e.err.to_string()), Some(expr.loc.clone()))
-            })
-        } else {
-            Err(PhyResult::new(InterpErr::NonFnCall, Some(expr.loc.clone())))
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> InterpRes {
+        Ok(self.eval_get(expr)?.unwrap_or(RtVal::Null))
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> InterpRes {
+        Ok(self.eval_index(expr)?.unwrap_or(RtVal::Null))
+    }
+
+    fn visit_slice_expr(&mut self, expr: &SliceExpr) -> InterpRes {
+        Ok(self.eval_slice(expr)?.unwrap_or(RtVal::Null))
+    }
+
+    fn visit_if_expr(&mut self, expr: &IfExpr) -> InterpRes {
+        let cond = expr.condition.accept(self)?;
+
+        match cond {
+            RtVal::BoolVal(b) => match b.borrow().value {
+                true => self.eval_branch(&expr.then_branch),
+                false => self.eval_branch(&expr.else_branch),
+            },
+            _ => Err(PhyResult::new(
+                InterpErr::NonBoolIfCond,
+                Some(expr.loc.clone()),
+            )),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{cell::RefCell, io, rc::Rc};
+
     use ecow::EcoString;
+    use frontend::parser::{utils::lex_and_parse, MAX_DEPTH};
+    use tools::results::PhyResult;
 
-    use crate::{interpreter::InterpErr, utils::lex_parse_interp};
+    use crate::{
+        environment::EnvErr,
+        interpreter::{InterpErr, Interpreter},
+        native_functions::{NativeFnErr, NativeFnRes},
+        utils::{lex_parse_interp, Sink},
+        values::{OverflowMode, RtVal},
+    };
 
     #[test]
     fn interp_literals() {
@@ -480,6 +965,18 @@ mod tests {
         assert_eq!(lex_parse_interp(code).unwrap(), 29.into());
     }
 
+    #[test]
+    fn interp_division() {
+        // Between ints, division truncates toward zero
+        assert_eq!(lex_parse_interp("7 / 2").unwrap(), 3.into());
+        assert_eq!(lex_parse_interp("-7 / 2").unwrap(), (-3).into());
+        assert_eq!(lex_parse_interp("7 / 2 == 3").unwrap(), true.into());
+
+        // A real operand gives a real division
+        assert_eq!(lex_parse_interp("7.0 / 2").unwrap(), 3.5.into());
+        assert_eq!(lex_parse_interp("7 / 2.").unwrap(), 3.5.into());
+    }
+
     #[test]
     fn interp_str_op() {
         let code = "\"foo\" * 4";
@@ -699,6 +1196,39 @@ res
         assert_eq!(lex_parse_interp(code).unwrap(), 11.into());
     }
 
+    #[test]
+    fn implicit_return() {
+        let code = "
+fn add(a, b) {
+    var c = a + b
+    c * 2
+}
+add(1, 2)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 6.into());
+
+        let code = "
+fn max(a, b) {
+    if a > b { return a }
+    b
+}
+max(1, 2) + max(4, 3)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 6.into());
+
+        // Only a final expression is returned
+        let code = "
+fn show(a) {
+    a + 1
+    print a
+}
+show(1)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), RtVal::Null);
+        assert_eq!(lex_parse_interp("fn f() { if true { 1 } }\nf()").unwrap(), RtVal::Null);
+        assert_eq!(lex_parse_interp("fn f() {}\nf()").unwrap(), RtVal::Null);
+    }
+
     #[test]
     fn first_class_fn() {
         let code = "
@@ -744,4 +1274,908 @@ a
 ";
         assert_eq!(lex_parse_interp(code).unwrap(), 2.into());
     }
+
+    #[test]
+    fn import() {
+        let dir = std::env::temp_dir().join(format!("raze_import_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let lib = dir.join("lib.rz");
+        std::fs::write(&lib, "fn add(a, b) { return a + b }\nvar ten = 10\n").unwrap();
+
+        let code = format!("
+import \"{}\"
+add(ten, 5)
+", lib.display());
+        assert_eq!(lex_parse_interp(&code).unwrap(), 15.into());
+
+        // Relative paths are resolved from the importing file
+        std::fs::write(dir.join("main.rz"), "import \"lib.rz\"\n").unwrap();
+        let code = format!("
+import \"{}\"
+add(1, 2)
+", dir.join("main.rz").display());
+        assert_eq!(lex_parse_interp(&code).unwrap(), 3.into());
+
+        // Errors
+        std::fs::write(dir.join("a.rz"), "import \"b.rz\"\n").unwrap();
+        std::fs::write(dir.join("b.rz"), "import \"a.rz\"\n").unwrap();
+        let code = format!("import \"{}\"", dir.join("a.rz").display());
+        assert!(matches!(
+            lex_parse_interp(&code).err().unwrap().err,
+            InterpErr::CircularImport(..)
+        ));
+
+        let code = format!("import \"{}\"", dir.join("nope.rz").display());
+        assert!(matches!(
+            lex_parse_interp(&code).err().unwrap().err,
+            InterpErr::ImportRead(..)
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn type_check() {
+        let code = "
+var a = 5
+a is int
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+
+        let code = "
+fn add(a, b) { return a + b }
+var res = add is fn and 1.5 is real
+res = res and \"foo\" is str
+res = res and true is bool
+res = res and add(1, 2) is int
+res and null is null
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+
+        let code = "
+var a = 5
+var res = a is str or a is real
+res or a is Point
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), false.into());
+    }
+
+    #[test]
+    fn coalesce() {
+        let code = "null ?? 5";
+        assert_eq!(lex_parse_interp(code).unwrap(), 5.into());
+
+        // Right side is never evaluated
+        let code = "3 ?? panic()";
+        assert_eq!(lex_parse_interp(code).unwrap(), 3.into());
+
+        let code = "
+var a
+var b
+a ?? b ?? \"default\"
+";
+        assert_eq!(
+            lex_parse_interp(code).unwrap(),
+            EcoString::from("default").into()
+        );
+    }
+
+    #[test]
+    fn optional_chaining() {
+        let code = "
+var a
+a?.b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), RtVal::Null);
+
+        let code = "
+var a
+a?.b?.c
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), RtVal::Null);
+
+        // The rest of the chain is skipped
+        assert_eq!(lex_parse_interp("null?.b.c").unwrap(), RtVal::Null);
+        assert_eq!(lex_parse_interp("var a\na?.b.c?.d.e").unwrap(), RtVal::Null);
+
+        // Calls, indexes and slices included, without evaluating their
+        // arguments
+        assert_eq!(lex_parse_interp("null?.b(1)").unwrap(), RtVal::Null);
+        assert_eq!(lex_parse_interp("null?.b[0]").unwrap(), RtVal::Null);
+        assert_eq!(lex_parse_interp("null?.b(undeclared)[0..1].c").unwrap(), RtVal::Null);
+
+        // Errors
+        assert_eq!(
+            lex_parse_interp("(null?.b).c").err().unwrap().err,
+            InterpErr::NoProperties("null".into())
+        );
+        assert_eq!(lex_parse_interp("(null?.b)(1)").err().unwrap().err, InterpErr::NonFnCall);
+
+        let code = "
+var a
+a.b
+";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::NoProperties("null".into())
+        );
+
+        let code = "
+var a = 5
+a?.b
+";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::NoProperties("int".into())
+        );
+    }
+
+    #[test]
+    fn increment() {
+        let code = "
+var i = 0
+i++
+i++
+i--
+i++
+i
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 2.into());
+
+        let code = "
+var i = 0
+while i < 5 { i++ }
+i
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 5.into());
+
+        // Only at the end of a statement, elsewhere they are two operators
+        assert_eq!(lex_parse_interp("5--3 == 8").unwrap(), true.into());
+        assert_eq!(lex_parse_interp("var a = 1\nvar b = 2\na--b").unwrap(), 3.into());
+        assert_eq!(lex_parse_interp("var i = 1\ni - -1").unwrap(), 2.into());
+    }
+
+    #[test]
+    fn enums() {
+        let code = "
+enum Color { Red, Green, Blue }
+var c = Color.Green
+c == Color.Green
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+
+        let code = "
+enum Color { Red, Green, Blue }
+Color.Red == Color.Blue
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), false.into());
+
+        // Same variant name in another enum is a different value
+        let code = "
+enum Color { Red, Green }
+enum Fruit { Apple, Red }
+Color.Red != Fruit.Red
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+
+        let code = "
+enum Color { Red, Green }
+var c = Color.Red
+c is Color
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+
+        // Errors
+        let code = "
+enum Color { Red, Green }
+Color.Purple
+";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::UnknownEnumVariant("Color".into(), "Purple".into())
+        );
+    }
+
+    #[test]
+    fn if_expr() {
+        let code = "
+var a = 5
+var b = if a > 2 { a * 2 } else { 0 }
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 10.into());
+
+        let code = "
+var b = if false { 1 } else {
+    \"no\"
+}
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), EcoString::from("no").into());
+
+        let code = "var b = if 1 { 1 } else { 2 }";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::NonBoolIfCond
+        );
+
+        // Branches are blocks ending with their value
+        let code = "
+var c = true
+var x = if c { var y = 2
+    y * 3 } else { 0 }
+x
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 6.into());
+
+        let code = "
+var y = 1
+var x = if y > 0 {
+    var y = 10
+    y = y + 1
+    y
+} else {
+    y
+}
+x + y
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 12.into());
+
+        // In their own scope
+        let code = "
+var x = if true { var y = 2; y } else { 0 }
+y
+";
+        assert!(matches!(lex_parse_interp(code).err().unwrap().err, InterpErr::GetVarEnv(_)));
+    }
+
+    #[test]
+    fn print_output() {
+        let code = "
+var a = 2
+print a
+print a * 1.5
+print \"hello\"
+print a > 1
+";
+        let buf = Rc::new(RefCell::new(Vec::new()));
+        let mut interp = Interpreter::with_output(Box::new(Sink(buf.clone())));
+        interp.interpret(&lex_and_parse(code).unwrap()).unwrap();
+
+        // Strings are printed without quotes
+        assert_eq!(buf.borrow().as_slice(), b"2\n3.0\nhello\ntrue\n");
+
+        // Reals use their shortest exact form by default
+        let code = "print 3.14159\nprint 0.1 + 0.2\nprint 2.\nprint 7";
+        let buf = Rc::new(RefCell::new(Vec::new()));
+        let mut interp = Interpreter::with_output(Box::new(Sink(buf.clone())));
+        interp.interpret(&lex_and_parse(code).unwrap()).unwrap();
+        assert_eq!(buf.borrow().as_slice(), b"3.14159\n0.30000000000000004\n2.0\n7\n");
+
+        // A precision only changes reals
+        let buf = Rc::new(RefCell::new(Vec::new()));
+        let mut interp = Interpreter::with_output(Box::new(Sink(buf.clone())));
+        interp.real_precision = Some(2);
+        interp.interpret(&lex_and_parse(code).unwrap()).unwrap();
+        assert_eq!(buf.borrow().as_slice(), b"3.14\n0.30\n2.00\n7\n");
+    }
+
+    #[test]
+    fn str_len_index() {
+        assert_eq!(lex_parse_interp("len(\"é\")").unwrap(), 1.into());
+        assert_eq!(lex_parse_interp("len(\"\")").unwrap(), 0.into());
+        assert_eq!(lex_parse_interp("len(\"héllo wörld\")").unwrap(), 11.into());
+
+        let code = "
+var s = \"éa日\"
+s[0] + s[2] + s[1]
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), EcoString::from("é日a").into());
+        assert_eq!(lex_parse_interp("\"日本\"[1] is str").unwrap(), true.into());
+
+        // Negative indexes count from the end
+        assert_eq!(lex_parse_interp("\"éa日\"[-1]").unwrap(), EcoString::from("日").into());
+        assert_eq!(lex_parse_interp("\"éa日\"[-3]").unwrap(), EcoString::from("é").into());
+
+        // Errors
+        assert_eq!(
+            lex_parse_interp("\"éa\"[2]").err().unwrap().err,
+            InterpErr::Index("index 2 is out of bounds for length 2".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"éa\"[-3]").err().unwrap().err,
+            InterpErr::Index("index -3 is out of bounds for length 2".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"éa\"[true]").err().unwrap().err,
+            InterpErr::Index("index must be an int, found: bool".into())
+        );
+        assert_eq!(
+            lex_parse_interp("12[0]").err().unwrap().err,
+            InterpErr::Index("values of type 'int' can't be indexed".into())
+        );
+        assert_eq!(
+            lex_parse_interp("len(12)").err().unwrap().err,
+            InterpErr::FnCall("len() expects an argument of type 'str', found: int".into())
+        );
+        assert_eq!(
+            lex_parse_interp("len(\"a\", \"b\")").err().unwrap().err,
+            InterpErr::WrongArgsNb(1, 2)
+        );
+    }
+
+    #[test]
+    fn overflow_mode() {
+        let interp_with = |code: &str, mode: OverflowMode| {
+            let nodes = lex_and_parse(code).unwrap();
+            let mut interp = Interpreter::new();
+            interp.overflow_mode = mode;
+            interp.interpret(&nodes)
+        };
+        let max = "9223372036854775807";
+
+        // Checked is the default
+        assert_eq!(
+            lex_parse_interp(&format!("{} + 1", max)).err().unwrap().err,
+            InterpErr::OperationEvaluation("integer overflow in '+' operation".into())
+        );
+        assert_eq!(
+            interp_with(&format!("{} * 2", max), OverflowMode::Checked).err().unwrap().err,
+            InterpErr::OperationEvaluation("integer overflow in '*' operation".into())
+        );
+        assert_eq!(
+            interp_with(&format!("{} - 1", max), OverflowMode::Checked).unwrap(),
+            9223372036854775806i64.into()
+        );
+
+        assert_eq!(
+            interp_with(&format!("{} + 1", max), OverflowMode::Wrapping).unwrap(),
+            i64::MIN.into()
+        );
+        assert_eq!(
+            interp_with(&format!("{} * 2", max), OverflowMode::Wrapping).unwrap(),
+            (-2).into()
+        );
+
+        // Negation too
+        let min = "-(-9223372036854775807 - 1)";
+        assert_eq!(
+            interp_with(min, OverflowMode::Checked).err().unwrap().err,
+            InterpErr::Negation("integer overflow in '-' operation".into())
+        );
+        assert_eq!(interp_with(min, OverflowMode::Wrapping).unwrap(), i64::MIN.into());
+        assert_eq!(
+            interp_with(&format!("-{}", max), OverflowMode::Checked).unwrap(),
+            (-i64::MAX).into()
+        );
+
+        // Division by zero is an error whatever the mode
+        for mode in [OverflowMode::Checked, OverflowMode::Wrapping] {
+            assert_eq!(
+                interp_with("1 % 0", mode).err().unwrap().err,
+                InterpErr::OperationEvaluation("division by zero".into())
+            );
+        }
+    }
+
+    #[test]
+    fn backtrace() {
+        let code = "
+fn inner(a) {
+    return a + true
+}
+fn outer() {
+    var b = 1
+    return inner(b)
+}
+outer()
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let mut interp = Interpreter::new();
+        assert!(interp.interpret(&nodes).is_err());
+
+        let names: Vec<&str> = interp.backtrace.iter().map(|f| f.fn_name.as_str()).collect();
+        assert_eq!(names, ["outer", "inner"]);
+        assert!(interp.call_stack.is_empty());
+
+        assert_eq!(
+            interp.render_backtrace("main.rz", code, &[]),
+            "  in 'inner', called from main.rz [line 7]\n  in 'outer', called from main.rz [line 9]"
+        );
+
+        // A successful run clears the previous backtrace
+        let nodes = lex_and_parse("fn f() { return 1 }\nf()").unwrap();
+        assert_eq!(interp.interpret(&nodes).unwrap(), 1.into());
+        assert!(interp.backtrace.is_empty());
+
+        // An import doesn't lose the frames it runs in
+        let dir = std::env::temp_dir().join(format!("raze_backtrace_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.rz");
+        std::fs::write(&lib, "var x = 1\n").unwrap();
+
+        let code = format!("
+fn g() {{ return x + true }}
+fn f() {{
+    import \"{}\"
+    return g()
+}}
+f()
+", lib.display());
+        let nodes = lex_and_parse(&code).unwrap();
+        let mut interp = Interpreter::new();
+        assert!(interp.interpret(&nodes).is_err());
+
+        let names: Vec<&str> = interp.backtrace.iter().map(|f| f.fn_name.as_str()).collect();
+        assert_eq!(names, ["f", "g"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn redeclaration() {
+        let code = "var a = 1\nvar a = 2";
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.err, InterpErr::AlreadyDeclared("a".into()));
+        assert_eq!(err.loc.unwrap().start, 10);
+
+        let code = "{\n    var a\n    fn a() {}\n}";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::AlreadyDeclared("a".into())
+        );
+
+        let code = "
+var a = 1
+var b = 0
+{
+    var a = 2
+    {
+        var a = 3
+        b = a
+    }
+    b = b + a
+}
+b + a";
+        assert_eq!(lex_parse_interp(code).unwrap(), 6.into());
+    }
+
+    #[test]
+    fn int_real_equality() {
+        assert_eq!(lex_parse_interp("1 == 1.0").unwrap(), true.into());
+        assert_eq!(lex_parse_interp("1.0 == 1").unwrap(), true.into());
+        assert_eq!(lex_parse_interp("1 != 1.0").unwrap(), false.into());
+        assert_eq!(lex_parse_interp("2 == 2.0000001").unwrap(), false.into());
+        assert_eq!(lex_parse_interp("2.0000001 != 2").unwrap(), true.into());
+        assert_eq!(
+            lex_parse_interp("9007199254740993 == 9007199254740992.0").unwrap(),
+            false.into()
+        );
+        assert_eq!(lex_parse_interp("-3 == -3.").unwrap(), true.into());
+    }
+
+    #[test]
+    fn else_if_chain() {
+        let code = "
+fn sign(n) {
+    var res
+    if n < 0 { res = \"negative\" } else if n == 0 { res = \"zero\" } else if n < 10 {
+        res = \"small\"
+    } else { res = \"big\" }
+    return res
+}
+sign(-4) + sign(0) + sign(3) + sign(12)";
+        assert_eq!(
+            lex_parse_interp(code).unwrap(),
+            EcoString::from("negativezerosmallbig").into()
+        );
+    }
+
+    #[test]
+    fn tail_calls() {
+        let code = "
+fn count(n, acc) {
+    if n == 0 { return acc }
+    return count(n - 1, acc + 1)
+}
+count(5000, 0)";
+        let nodes = lex_and_parse(code).unwrap();
+
+        // Without TCO, each call is a new frame
+        let mut interp = Interpreter::new();
+        interp.max_call_depth = 100;
+        let err = interp.interpret(&nodes).err().unwrap();
+        assert!(err.err.to_string().contains("maximum call depth of 100 exceeded"));
+        assert_eq!(interp.backtrace.len(), 100);
+
+        let mut interp = Interpreter::new();
+        interp.max_call_depth = 100;
+        interp.tco = true;
+        assert_eq!(interp.interpret(&nodes).unwrap(), 5000.into());
+
+        // Mutual recursion, natives and non tail calls
+        let code = "
+fn is_even(n) {
+    if n == 0 { return true }
+    return is_odd(n - 1)
+}
+fn is_odd(n) {
+    if n == 0 { return false }
+    return is_even(n - 1)
+}
+fn size(s) { return len(s) }
+fn fact(n) {
+    if n == 0 { return 1 }
+    return n * fact(n - 1)
+}
+fn f() { return g(1) }
+fn g() { return 0 }
+fn h(a) { return a?.b(1) }
+";
+        let mut interp = Interpreter::new();
+        interp.max_call_depth = 50;
+        interp.tco = true;
+        interp.interpret(&lex_and_parse(code).unwrap()).unwrap();
+
+        let mut run = |code: &str| interp.interpret(&lex_and_parse(code).unwrap());
+        assert_eq!(run("is_even(1001)").unwrap(), false.into());
+        assert_eq!(run("size(\"abc\")").unwrap(), 3.into());
+        assert_eq!(run("fact(10)").unwrap(), 3628800.into());
+        assert!(run("fact(60)").err().unwrap().err.to_string().contains("maximum call depth"));
+        assert!(run("f()").err().unwrap().err.to_string().contains("expected 0 but got 1"));
+        assert_eq!(run("h(null)").unwrap(), RtVal::Null);
+
+        // Imports inside the recursion don't reset the depth
+        let dir = std::env::temp_dir().join(format!("raze_depth_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let one = dir.join("one.rz");
+        std::fs::write(&one, "var one = 1\n").unwrap();
+
+        let code = format!("
+fn r(n) {{
+    import \"{}\"
+    if n == 300 {{ return n }}
+    return r(n + one)
+}}
+r(0)
+", one.display());
+        let nodes = lex_and_parse(&code).unwrap();
+
+        let mut interp = Interpreter::new();
+        interp.max_call_depth = 100;
+        let err = interp.interpret(&nodes).err().unwrap();
+        assert!(err.err.to_string().contains("maximum call depth of 100 exceeded"));
+
+        let mut interp = Interpreter::new();
+        interp.max_call_depth = 100;
+        interp.tco = true;
+        assert_eq!(interp.interpret(&nodes).unwrap(), 300.into());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn exec_stmt() {
+        let mut interp = Interpreter::new();
+        let nodes = lex_and_parse("var a = 4\na * 2\nb").unwrap();
+
+        assert_eq!(interp.exec_stmt(&nodes[0]).unwrap(), RtVal::Null);
+        assert_eq!(interp.exec_stmt(&nodes[1]).unwrap(), 8.into());
+        assert!(matches!(
+            interp.exec_stmt(&nodes[2]).err().unwrap().err,
+            InterpErr::GetVarEnv(_)
+        ));
+
+        // The environment is kept between statements, even after an error
+        assert_eq!(interp.exec_stmt(&nodes[1]).unwrap(), 8.into());
+
+        // The statements of an import don't reset the call stack
+        let dir = std::env::temp_dir().join(format!("raze_exec_stmt_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.rz");
+        std::fs::write(&lib, "var x = 1\nvar y = 2\n").unwrap();
+
+        let code = format!("
+fn g() {{ return x + true }}
+fn f() {{
+    import \"{}\"
+    return g()
+}}
+f()
+", lib.display());
+        let nodes = lex_and_parse(&code).unwrap();
+        interp.exec_stmt(&nodes[0]).unwrap();
+        interp.exec_stmt(&nodes[1]).unwrap();
+        assert!(interp.exec_stmt(&nodes[2]).is_err());
+
+        let names: Vec<&str> = interp.backtrace.iter().map(|f| f.fn_name.as_str()).collect();
+        assert_eq!(names, ["f", "g"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn deep_expr() {
+        let chain = vec!["1"; 1000].join(" + ");
+        assert_eq!(lex_parse_interp(&chain).unwrap(), 1000.into());
+
+        // Test threads have a smaller stack than the main thread
+        let parens = std::thread::Builder::new().stack_size(8 << 20).spawn(|| {
+            let parens = format!("{}1{}", "(".repeat(MAX_DEPTH - 1), ")".repeat(MAX_DEPTH - 1));
+            assert_eq!(lex_parse_interp(&parens).unwrap(), 1.into());
+        });
+        parens.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn while_let() {
+        let code = "
+var lines = \"\"
+while var line = input() {
+    lines = lines + line + \",\"
+}
+lines";
+        let mut interp = Interpreter::new();
+        interp.input = Some(Box::new(io::Cursor::new("a\nb c\r\n\nd")));
+        let res = interp.interpret(&lex_and_parse(code).unwrap()).unwrap();
+
+        // Empty lines aren't the end of the input
+        assert_eq!(res, EcoString::from("a,b c,,d,").into());
+
+        // The variable is only bound in the body
+        let mut interp = Interpreter::new();
+        interp.input = Some(Box::new(io::Cursor::new("a")));
+        let err = interp
+            .interpret(&lex_and_parse("while var l = input() {}\nl").unwrap())
+            .err()
+            .unwrap();
+        assert!(matches!(err.err, InterpErr::GetVarEnv(_)));
+
+        let code = "
+fn next(n) {
+    if n < 3 { return n + 1 }
+    return null
+}
+var i = 0
+var sum = 0
+while var n = next(i) {
+    sum = sum + n
+    i = n
+}
+sum";
+        assert_eq!(lex_parse_interp(code).unwrap(), 6.into());
+    }
+
+    #[test]
+    fn register_native() {
+        fn double(_: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+            match &args[0] {
+                RtVal::IntVal(i) => Ok((i.borrow().value * 2).into()),
+                v => Err(PhyResult::new(NativeFnErr::Host(format!("can't double a {}", v.get_type_name())), None)),
+            }
+        }
+
+        let mut interp = Interpreter::new();
+        interp.register_native("double", 1, double).unwrap();
+
+        let code = "fn f(a) { return double(a) + 1 }\nf(double(10))";
+        assert_eq!(interp.interpret(&lex_and_parse(code).unwrap()).unwrap(), 41.into());
+
+        let err = interp.interpret(&lex_and_parse("double(1, 2)").unwrap()).unwrap_err();
+        assert_eq!(err.err, InterpErr::WrongArgsNb(1, 2));
+
+        let err = interp.interpret(&lex_and_parse("double(true)").unwrap()).unwrap_err();
+        assert_eq!(err.err, InterpErr::FnCall("can't double a bool".into()));
+
+        // Names are shared with the built-ins and the scripts' globals
+        let err = interp.register_native("len", 1, double).unwrap_err();
+        assert!(matches!(err, EnvErr::AlreadyDeclaredVar(name) if name == "len"));
+    }
+
+    #[test]
+    fn chained_assign() {
+        let code = "
+var a = 0
+var b = 0
+a = b = 5
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 5.into());
+        assert_eq!(lex_parse_interp(&format!("{}a", code)).unwrap(), 5.into());
+        assert_eq!(lex_parse_interp(&format!("{}b", code)).unwrap(), 5.into());
+
+        // Evaluated from right to left, each one gets the value
+        let code = "
+var a = 1
+var b = 2
+var c = 3
+a = b = c = a + 10
+a + b + c
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 33.into());
+
+        // The innermost assignment is the one failing
+        let err = lex_parse_interp("var a = 1\na = undeclared = 2").unwrap_err();
+        assert_eq!(err.loc, Some(tools::results::Loc::new(14, 28)));
+        assert!(matches!(err.err, InterpErr::AssignEnv(_)));
+    }
+
+    #[test]
+    fn discard() {
+        assert_eq!(lex_parse_interp("var _ = 1").unwrap(), RtVal::Null);
+        // Never declared, so never declared twice
+        assert_eq!(lex_parse_interp("var _ = 1\nvar _ = 2\n_ = 3").unwrap(), 3.into());
+
+        let code = "
+var count = 0
+for _ in 0..3 { count = count + 1 }
+fn second(_, b) { b }
+count + second(1, 2)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 5.into());
+
+        // The value is still computed
+        let code = "
+var calls = 0
+fn f() { calls = calls + 1 }
+var _ = f()
+calls
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        for code in ["var _ = 1\n_", "fn f(_) { _ }\nf(1)", "for _ in 3 { print _ }"] {
+            let err = lex_parse_interp(code).unwrap_err();
+            assert!(err.err.to_string().contains("'_' only discards values"), "{}", code);
+        }
+    }
+
+    #[test]
+    fn modulo() {
+        // Two ints give an int
+        assert_eq!(lex_parse_interp("5 % 2").unwrap(), 1.into());
+        assert_eq!(lex_parse_interp("6 % 3").unwrap(), 0.into());
+
+        // Any real gives a real
+        assert_eq!(lex_parse_interp("5.5 % 2").unwrap(), 1.5.into());
+        assert_eq!(lex_parse_interp("5 % 2.").unwrap(), 1.0.into());
+        assert_eq!(lex_parse_interp("7.5 % 2.5").unwrap(), 0.0.into());
+
+        // The sign is the one of the left operand, like the truncated division
+        assert_eq!(lex_parse_interp("-5 % 3").unwrap(), (-2).into());
+        assert_eq!(lex_parse_interp("5 % -3").unwrap(), 2.into());
+        assert_eq!(lex_parse_interp("-5 % -3").unwrap(), (-2).into());
+        assert_eq!(lex_parse_interp("-5.5 % 2").unwrap(), (-1.5).into());
+        assert_eq!(lex_parse_interp("(-5 / 3) * 3 + -5 % 3").unwrap(), (-5).into());
+
+        assert_eq!(lex_parse_interp("5.5 % 0").unwrap().to_string(), "NaN");
+        assert_eq!(
+            lex_parse_interp("5 % 0").unwrap_err().err,
+            InterpErr::OperationEvaluation("division by zero".into())
+        );
+    }
+
+    #[test]
+    fn step_budget() {
+        let mut interp = Interpreter::new();
+        interp.max_steps = Some(100);
+
+        let err = interp.interpret(&lex_and_parse("while true { }").unwrap()).unwrap_err();
+        assert_eq!(err.err, InterpErr::Timeout(100));
+        assert_eq!(err.loc.unwrap().start, 0);
+
+        // Inside a function, the error goes up like any other
+        interp.tco = true;
+        let code = "fn f(n) { return f(n + 1) }\nf(0)";
+        let err = interp.interpret(&lex_and_parse(code).unwrap()).unwrap_err();
+        assert!(err.err.to_string().contains("budget of 100 steps exceeded"));
+
+        // The budget is for each 'interpret' call
+        interp.interpret(&lex_and_parse("var sum = 0").unwrap()).unwrap();
+        let code = "sum = 0\nfor i in 10 { sum = sum + i }\nsum";
+        for _ in 0..20 {
+            assert_eq!(interp.interpret(&lex_and_parse(code).unwrap()).unwrap(), 45.into());
+        }
+
+        // And for each 'exec_stmt' one
+        let nodes = lex_and_parse("for i in 40 { }").unwrap();
+        interp.exec_stmt(&nodes[0]).unwrap();
+        interp.exec_stmt(&nodes[0]).unwrap();
+
+        // Imports don't get a budget of their own
+        let dir = std::env::temp_dir().join(format!("raze_budget_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let x = dir.join("x.rz");
+        std::fs::write(&x, "1\n").unwrap();
+
+        let code = format!("while true {{ import \"{}\" }}", x.display());
+        let err = interp.interpret(&lex_and_parse(&code).unwrap()).unwrap_err();
+        assert_eq!(err.err, InterpErr::Timeout(100));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Unlimited by default
+        let mut interp = Interpreter::new();
+        let code = "var i = 0\nwhile i < 10000 { i = i + 1 }\ni";
+        assert_eq!(interp.interpret(&lex_and_parse(code).unwrap()).unwrap(), 10000.into());
+    }
+
+    #[test]
+    fn bool_as_int() {
+        // Off by default, it is a type error
+        let err = lex_parse_interp("true + 1").unwrap_err();
+        assert_eq!(
+            err.err,
+            InterpErr::OperationEvaluation("operation not supported".into())
+        );
+        assert!(lex_parse_interp("true < 2").is_err());
+
+        let mut interp = Interpreter::new();
+        interp.bool_as_int = true;
+        let mut run = |code: &str| interp.interpret(&lex_and_parse(code).unwrap());
+
+        assert_eq!(run("true + 1").unwrap(), 2.into());
+        assert_eq!(run("true + 1 == 2").unwrap(), true.into());
+        assert_eq!(run("false * 2.5").unwrap(), 0.0.into());
+        assert_eq!(run("true + true").unwrap(), 2.into());
+        assert_eq!(run("true < 2").unwrap(), true.into());
+        assert_eq!(run("false == 0").unwrap(), true.into());
+        assert_eq!(run("true != false").unwrap(), true.into());
+
+        // Only next to numbers
+        assert!(run("\"a\" + true").is_err());
+        assert!(run("true + null").is_err());
+    }
+
+    #[test]
+    fn grouping_is_transparent() {
+        assert_eq!(lex_parse_interp("(((2 + 3)))").unwrap(), 5.into());
+        assert_eq!(lex_parse_interp("(2 + 3) * (1 - 3)").unwrap(), (-10).into());
+
+        // Located on the binary expression, not on the parentheses
+        let err = lex_parse_interp("var a = 1\nprint 2 * (a + \"x\")").unwrap_err();
+        assert!(matches!(err.err, InterpErr::OperationEvaluation(_)));
+        assert_eq!(err.loc, Some(tools::results::Loc::new(21, 28)));
+    }
+
+    #[test]
+    fn str_slice() {
+        let code = "
+var s = \"héllo\"
+s[1..3] + s[0..=0] + s[-2..5]
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), EcoString::from("élhlo").into());
+        assert_eq!(lex_parse_interp("\"abc\"[1..1]").unwrap(), EcoString::from("").into());
+        assert_eq!(lex_parse_interp("\"abc\"[0..=2]").unwrap(), EcoString::from("abc").into());
+        assert_eq!(lex_parse_interp("\"abc\"[0..-1]").unwrap(), EcoString::from("ab").into());
+
+        // Errors
+        assert_eq!(
+            lex_parse_interp("\"abc\"[1..4]").err().unwrap().err,
+            InterpErr::Index("index 4 is out of bounds for length 3".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"abc\"[0..=3]").err().unwrap().err,
+            InterpErr::Index("index 3 is out of bounds for length 3".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"abc\"[-4..2]").err().unwrap().err,
+            InterpErr::Index("index -4 is out of bounds for length 3".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"abc\"[2..1]").err().unwrap().err,
+            InterpErr::Index("range 2..1 starts after its end".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"abc\"[-1..=0]").err().unwrap().err,
+            InterpErr::Index("range -1..=0 starts after its end".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"abc\"[0..true]").err().unwrap().err,
+            InterpErr::Index("index must be an int, found: bool".into())
+        );
+        assert_eq!(
+            lex_parse_interp("12[0..1]").err().unwrap().err,
+            InterpErr::Index("values of type 'int' can't be indexed".into())
+        );
+    }
 }
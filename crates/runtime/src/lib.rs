@@ -3,7 +3,11 @@ pub mod values;
 pub mod environment;
 pub mod callable;
 pub mod native_functions;
+pub mod compiler;
+pub mod vm;
+pub mod eval;
 
+#[cfg(test)]
 mod utils;
 
 extern crate frontend;
@@ -1,6 +1,6 @@
 use colored::*;
 use ecow::EcoString;
-use std::{fmt, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+use std::{fmt, io, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
 use thiserror::Error;
 
 use crate::{
@@ -13,6 +13,9 @@ use tools::results::{PhyReport, PhyResult};
 
 pub type NativeFnRes = Result<RtVal, PhyResult<NativeFnErr>>;
 
+// Function exposed by an application embedding the interpreter
+pub type NativeFnPtr = fn(&mut Interpreter, Vec<RtVal>) -> NativeFnRes;
+
 pub struct PhyNativeFn {
     pub name: EcoString,
     pub func: Rc<dyn Callable<NativeFnErr>>,
@@ -41,6 +44,15 @@ impl fmt::Display for PhyNativeFn {
 pub enum NativeFnErr {
     #[error("time access failed")]
     GetTime,
+
+    #[error("{0}() expects an argument of type '{1}', found: {2}")]
+    WrongArgType(String, String, String),
+
+    #[error("failed to read input: {0}")]
+    ReadInput(String),
+
+    #[error("{0}")]
+    Host(String),
 }
 
 impl PhyReport for NativeFnErr {
@@ -65,3 +77,71 @@ impl Callable<NativeFnErr> for NativeClock {
         }
     }
 }
+
+
+// Len
+pub struct NativeLen;
+
+impl Callable<NativeFnErr> for NativeLen {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match &args[0] {
+            RtVal::StrVal(s) => Ok((s.borrow().len() as i64).into()),
+            v => Err(PhyResult::new(
+                NativeFnErr::WrongArgType("len".into(), "str".into(), v.get_type_name().to_string()),
+                None,
+            )),
+        }
+    }
+}
+
+
+// Input
+pub struct NativeInput;
+
+impl Callable<NativeFnErr> for NativeInput {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    // The next line without its line break, null once the input is over
+    fn call(&self, interpreter: &mut Interpreter, _: Vec<RtVal>) -> NativeFnRes {
+        let mut line = String::new();
+
+        let read = match &mut interpreter.input {
+            Some(input) => input.read_line(&mut line),
+            None => io::stdin().read_line(&mut line),
+        };
+
+        match read {
+            Ok(0) => Ok(RtVal::new_null()),
+            Ok(_) => {
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+
+                Ok(line.into())
+            }
+            Err(e) => Err(PhyResult::new(NativeFnErr::ReadInput(e.to_string()), None)),
+        }
+    }
+}
+
+
+// Host
+pub struct NativeHost {
+    pub arity: usize,
+    pub func: NativeFnPtr,
+}
+
+impl Callable<NativeFnErr> for NativeHost {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        (self.func)(interpreter, args)
+    }
+}
@@ -1,3 +1,5 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
 use frontend::parser::utils::lex_and_parse;
 
 use crate::values::RtVal;
@@ -9,3 +11,17 @@ pub fn lex_parse_interp(code: &str) -> Result<RtVal, PhyResInterp> {
     let mut interp = Interpreter::new();
     interp.interpret(&nodes)
 }
+
+// Output shared so that the buffer can be read once the interpreter, or
+// the VM, owns it
+pub struct Sink(pub Rc<RefCell<Vec<u8>>>);
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use colored::*;
 use ecow::EcoString;
-use frontend::ast::stmt::{FnDeclStmt, Stmt};
-use std::{cell::RefCell, fmt::Display, rc::Rc};
+use frontend::ast::{expr::format_real, stmt::{EnumDeclStmt, FnDeclStmt, Stmt}};
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
 use thiserror::Error;
 use tools::results::{PhyReport, PhyResult};
 
@@ -38,12 +38,39 @@ pub enum RtValErr {
     #[error("{0}")]
     FnExecution(String),
 
+    // Index
+    #[error("values of type '{0}' can't be indexed")]
+    NotIndexable(String),
+
+    #[error("index must be an int, found: {0}")]
+    NonIntIndex(String),
+
+    #[error("index {0} is out of bounds for length {1}")]
+    IndexOutOfBounds(i64, usize),
+
+    #[error("range {0}{1}{2} starts after its end")]
+    ReversedRange(i64, String, i64),
+
+    // Int arithmetic
+    #[error("integer overflow in '{0}' operation")]
+    IntOverflow(String),
+
+    #[error("division by zero")]
+    DivisionByZero,
+
+    // Map keys
+    #[error("values of type '{0}' can't be used as keys")]
+    UnhashableKey(String),
+
     // Others
     #[error("can't use a null value in a binary operation")]
     OperationOnNull,
 
     #[error("operation not supported")]
     UnknownOperation,
+
+    #[error("expected a value of type '{0}', found: {1}")]
+    WrongType(String, String),
 }
 
 impl PhyReport for RtValErr {
@@ -63,6 +90,8 @@ pub enum RtVal {
     BoolVal(Rc<RefCell<Bool>>),
     FuncVal(Rc<Function>),
     NativeFnVal(Rc<PhyNativeFn>),
+    EnumVal(Rc<Enum>),
+    EnumVariantVal(Rc<EnumVariant>),
     Null,
 }
 
@@ -79,9 +108,28 @@ impl RtVal {
         RtVal::Null
     }
 
+    // Enum variants have the type of their enum
+    pub fn get_type_name(&self) -> EcoString {
+        match self {
+            RtVal::IntVal(_) => "int".into(),
+            RtVal::RealVal(_) => "real".into(),
+            RtVal::StrVal(_) => "str".into(),
+            RtVal::BoolVal(_) => "bool".into(),
+            RtVal::FuncVal(_) | RtVal::NativeFnVal(_) => "fn".into(),
+            RtVal::EnumVal(_) => "enum".into(),
+            RtVal::EnumVariantVal(v) => v.enum_name.clone(),
+            RtVal::Null => "null".into(),
+        }
+    }
+
     pub fn negate(&self) -> Result<(), RtValErr> {
+        self.negate_with(OverflowMode::default())
+    }
+
+    // Same as 'negate' but an int follows the given mode
+    pub fn negate_with(&self, overflow: OverflowMode) -> Result<(), RtValErr> {
         match &self {
-            RtVal::IntVal(i) => i.borrow_mut().negate(),
+            RtVal::IntVal(i) => i.borrow_mut().negate(overflow)?,
             RtVal::RealVal(r) => r.borrow_mut().negate(),
             RtVal::BoolVal(b) => b.borrow_mut().negate(),
             _ => return Err(RtValErr::UnNegatable),
@@ -90,6 +138,21 @@ impl RtVal {
         Ok(())
     }
 
+    // Same as 'operate' but integer arithmetic follows the given mode
+    pub fn operate_with(
+        &self,
+        rhs: &RtVal,
+        operator: &str,
+        overflow: OverflowMode,
+    ) -> Result<RtVal, RtValErr> {
+        match (&self, &rhs) {
+            (RtVal::IntVal(i1), RtVal::IntVal(i2)) if Int::is_arith(operator) => {
+                i1.borrow().arith(&i2.borrow(), operator, overflow)
+            }
+            _ => self.operate(rhs, operator),
+        }
+    }
+
     // TODO: Error handling for other operation
     pub fn operate(&self, rhs: &RtVal, operator: &str) -> Result<RtVal, RtValErr> {
         match (&self, &rhs) {
@@ -105,10 +168,31 @@ impl RtVal {
             (RtVal::BoolVal(b1), RtVal::BoolVal(b2)) => {
                 b1.borrow().operate(&*b2.borrow(), operator)
             }
+            (RtVal::EnumVariantVal(v1), RtVal::EnumVariantVal(v2)) => v1.operate(v2, operator),
             (RtVal::Null, _) | (_, RtVal::Null) => Err(RtValErr::OperationOnNull),
             _ => Err(RtValErr::UnknownOperation),
         }
     }
+
+    pub fn index(&self, idx: &RtVal) -> Result<RtVal, RtValErr> {
+        match (self, idx) {
+            (RtVal::StrVal(s), RtVal::IntVal(i)) => s.borrow().char_at(i.borrow().value),
+            (RtVal::StrVal(_), _) => Err(RtValErr::NonIntIndex(idx.get_type_name().to_string())),
+            _ => Err(RtValErr::NotIndexable(self.get_type_name().to_string())),
+        }
+    }
+
+    pub fn slice(&self, start: &RtVal, end: &RtVal, inclusive: bool) -> Result<RtVal, RtValErr> {
+        match (self, start, end) {
+            (RtVal::StrVal(s), RtVal::IntVal(a), RtVal::IntVal(b)) => {
+                s.borrow().substr(a.borrow().value, b.borrow().value, inclusive)
+            }
+            (RtVal::StrVal(_), RtVal::IntVal(_), other) | (RtVal::StrVal(_), other, _) => {
+                Err(RtValErr::NonIntIndex(other.get_type_name().to_string()))
+            }
+            _ => Err(RtValErr::NotIndexable(self.get_type_name().to_string())),
+        }
+    }
 }
 
 // -------
@@ -119,20 +203,65 @@ pub struct Int {
     pub value: i64,
 }
 
-impl Negate for Int {
-    fn negate(&mut self) {
-        self.value *= -1;
+// What integer arithmetic does when the result doesn't fit in an i64
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverflowMode {
+    #[default]
+    Checked,
+    Wrapping,
+}
+
+impl Int {
+    fn is_arith(operator: &str) -> bool {
+        matches!(operator, "+" | "-" | "*" | "/" | "%")
+    }
+
+    // The opposite of i64::MIN doesn't fit either
+    fn negate(&mut self, overflow: OverflowMode) -> Result<(), RtValErr> {
+        let res = match overflow {
+            OverflowMode::Checked => self.value.checked_neg(),
+            OverflowMode::Wrapping => Some(self.value.wrapping_neg()),
+        };
+
+        self.value = res.ok_or_else(|| RtValErr::IntOverflow("-".into()))?;
+
+        Ok(())
+    }
+
+    // Integer division is truncated toward zero. As soon as one of the
+    // operands is a real, the division is a real one. The same goes for
+    // '%': the remainder has the sign of the left operand, -5 % 3 is -2,
+    // and with a real it is the one of f64, 5.5 % 2 is 1.5
+    fn arith(&self, rhs: &Int, operator: &str, overflow: OverflowMode) -> Result<RtVal, RtValErr> {
+        let (a, b) = (self.value, rhs.value);
+
+        if b == 0 && matches!(operator, "/" | "%") {
+            return Err(RtValErr::DivisionByZero);
+        }
+
+        let res = match (overflow, operator) {
+            (OverflowMode::Checked, "+") => a.checked_add(b),
+            (OverflowMode::Checked, "-") => a.checked_sub(b),
+            (OverflowMode::Checked, "*") => a.checked_mul(b),
+            (OverflowMode::Checked, "/") => a.checked_div(b),
+            (OverflowMode::Checked, "%") => a.checked_rem(b),
+            (OverflowMode::Wrapping, "+") => Some(a.wrapping_add(b)),
+            (OverflowMode::Wrapping, "-") => Some(a.wrapping_sub(b)),
+            (OverflowMode::Wrapping, "*") => Some(a.wrapping_mul(b)),
+            (OverflowMode::Wrapping, "/") => Some(a.wrapping_div(b)),
+            (OverflowMode::Wrapping, "%") => Some(a.wrapping_rem(b)),
+            (_, op) => return Err(RtValErr::UnsupportedOpOnType(op.to_string(), "int".into())),
+        };
+
+        res.map(|v| v.into())
+            .ok_or_else(|| RtValErr::IntOverflow(operator.to_string()))
     }
 }
 
 impl Operate<Int> for Int {
     fn operate(&self, rhs: &Int, operator: &str) -> Result<RtVal, RtValErr> {
         match operator {
-            "+" => Ok((self.value + rhs.value).into()),
-            "-" => Ok((self.value - rhs.value).into()),
-            "*" => Ok((self.value * rhs.value).into()),
-            "/" => Ok((self.value / rhs.value).into()),
-            "%" => Ok((self.value % rhs.value).into()),
+            op if Int::is_arith(op) => self.arith(rhs, op, OverflowMode::default()),
             "<" => Ok((self.value < rhs.value).into()),
             ">" => Ok((self.value > rhs.value).into()),
             "<=" => Ok((self.value <= rhs.value).into()),
@@ -156,8 +285,8 @@ impl Operate<Real> for Int {
             ">" => Ok((self.value as f64 > rhs.value).into()),
             "<=" => Ok((self.value as f64 <= rhs.value).into()),
             ">=" => Ok((self.value as f64 >= rhs.value).into()),
-            "==" => Ok((self.value as f64 == rhs.value).into()),
-            "!=" => Ok((self.value as f64 != rhs.value).into()),
+            "==" => Ok(int_eq_real(self.value, rhs.value).into()),
+            "!=" => Ok((!int_eq_real(self.value, rhs.value)).into()),
             op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "int".into())),
         }
     }
@@ -186,6 +315,16 @@ impl Negate for Real {
     }
 }
 
+// Compares the values exactly. Converting the int to a real would round
+// big ints and make distinct values equal
+fn int_eq_real(int: i64, real: f64) -> bool {
+    // i64::MAX as f64 is 2^63, out of the int range
+    real.fract() == 0.0
+        && real >= i64::MIN as f64
+        && real < i64::MAX as f64
+        && real as i64 == int
+}
+
 impl Operate<Int> for Real {
     fn operate(&self, rhs: &Int, operator: &str) -> Result<RtVal, RtValErr> {
         match operator {
@@ -198,8 +337,8 @@ impl Operate<Int> for Real {
             ">" => Ok((self.value > rhs.value as f64).into()),
             "<=" => Ok((self.value <= rhs.value as f64).into()),
             ">=" => Ok((self.value >= rhs.value as f64).into()),
-            "==" => Ok((self.value == rhs.value as f64).into()),
-            "!=" => Ok((self.value != rhs.value as f64).into()),
+            "==" => Ok(int_eq_real(rhs.value, self.value).into()),
+            "!=" => Ok((!int_eq_real(rhs.value, self.value)).into()),
             op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "real".into())),
         }
     }
@@ -232,6 +371,60 @@ pub struct Str {
     pub value: EcoString,
 }
 
+// Length and indexes count characters, not bytes
+impl Str {
+    pub fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    // Negative indexes count from the end, -1 being the last char
+    pub fn char_at(&self, idx: i64) -> Result<RtVal, RtValErr> {
+        let len = self.len();
+
+        self.char_pos(idx)
+            .and_then(|i| self.value.chars().nth(i))
+            .map(|c| EcoString::from(c).into())
+            .ok_or(RtValErr::IndexOutOfBounds(idx, len))
+    }
+
+    // Chars from 'start' to 'end', excluded unless 'inclusive'. The
+    // bounds can be negative like the indexes, but not be reversed
+    pub fn substr(&self, start: i64, end: i64, inclusive: bool) -> Result<RtVal, RtValErr> {
+        let len = self.len();
+        let from = self.char_pos(start).filter(|i| *i <= len);
+        let to = self
+            .char_pos(end)
+            .and_then(|i| if inclusive { i.checked_add(1) } else { Some(i) })
+            .filter(|i| *i <= len);
+
+        match (from, to) {
+            (Some(from), Some(to)) if from <= to => {
+                let sub: String = self.value.chars().skip(from).take(to - from).collect();
+                Ok(EcoString::from(sub).into())
+            }
+            (Some(_), Some(_)) => {
+                let operator = if inclusive { "..=" } else { ".." };
+                Err(RtValErr::ReversedRange(start, operator.into(), end))
+            }
+            (Some(_), None) => Err(RtValErr::IndexOutOfBounds(end, len)),
+            (None, _) => Err(RtValErr::IndexOutOfBounds(start, len)),
+        }
+    }
+
+    fn char_pos(&self, idx: i64) -> Option<usize> {
+        let from_start = match idx < 0 {
+            true => (self.len() as i64).checked_add(idx),
+            false => Some(idx),
+        };
+
+        from_start.and_then(|i| usize::try_from(i).ok())
+    }
+}
+
 impl Operate<Str> for Str {
     fn operate(&self, rhs: &Str, operator: &str) -> Result<RtVal, RtValErr> {
         match operator {
@@ -278,6 +471,53 @@ impl Operate<Bool> for Bool {
     }
 }
 
+// --------
+//   Enum
+// --------
+#[derive(Debug, PartialEq)]
+pub struct Enum {
+    pub name: EcoString,
+    pub variants: Vec<EcoString>,
+}
+
+// A variant is only equal to itself, variants with the same name in
+// different enums are different values
+#[derive(Debug, PartialEq)]
+pub struct EnumVariant {
+    pub enum_name: EcoString,
+    pub name: EcoString,
+}
+
+impl RtVal {
+    pub fn new_enum(value: &EnumDeclStmt) -> Self {
+        RtVal::EnumVal(Rc::new(Enum {
+            name: value.name.clone(),
+            variants: value.variants.clone(),
+        }))
+    }
+}
+
+impl Enum {
+    pub fn get_variant(&self, name: &EcoString) -> Option<RtVal> {
+        self.variants.contains(name).then(|| {
+            RtVal::EnumVariantVal(Rc::new(EnumVariant {
+                enum_name: self.name.clone(),
+                name: name.clone(),
+            }))
+        })
+    }
+}
+
+impl Operate<EnumVariant> for EnumVariant {
+    fn operate(&self, rhs: &EnumVariant, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "==" => Ok((self == rhs).into()),
+            "!=" => Ok((self != rhs).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "enum".into())),
+        }
+    }
+}
+
 // ------------
 //   Function
 // ------------
@@ -312,23 +552,40 @@ impl Callable<RtValErr> for Function {
         interpreter: &mut Interpreter,
         args: Vec<RtVal>,
     ) -> Result<RtVal, PhyResult<RtValErr>> {
-        let mut new_env = Env::new(Some(self.closure.clone()));
-
-        for (p, v) in self.params.iter().zip(args) {
-            new_env
-                .declare_var(p.clone(), v)
-                .map_err(|_| PhyResult::new(RtValErr::WrongFnParamDecl, None))?;
-        }
+        // Tail calls replace the function and its arguments and loop,
+        // without growing the stack
+        let mut tail_fn: Option<Rc<Function>> = None;
+        let mut args = args;
+
+        loop {
+            let func = tail_fn.as_deref().unwrap_or(self);
+            let mut new_env = Env::new(Some(func.closure.clone()));
+
+            for (p, v) in func.params.iter().zip(args) {
+                new_env
+                    .declare_var(p.clone(), v)
+                    .map_err(|_| PhyResult::new(RtValErr::WrongFnParamDecl, None))?;
+            }
 
-        match interpreter.execute_block_stmt(&self.body, new_env) {
-            Ok(_) => Ok(RtVal::new_null()),
-            Err(e) => match e.err {
-                InterpErr::Return(v) => Ok(v),
-                _ => Err(PhyResult::new(
-                    RtValErr::FnExecution(e.err.to_string()),
-                    None,
-                )),
-            },
+            return match interpreter.execute_block_stmt(&func.body, new_env) {
+                // A body ending with an expression returns its value
+                Ok(v) => match func.body.last() {
+                    Some(Stmt::Expr(_)) => Ok(v),
+                    _ => Ok(RtVal::new_null()),
+                },
+                Err(e) => match e.err {
+                    InterpErr::Return(v) => Ok(v),
+                    InterpErr::TailCall(f, a) => {
+                        tail_fn = Some(f);
+                        args = a;
+                        continue;
+                    }
+                    _ => Err(PhyResult::new(
+                        RtValErr::FnExecution(e.err.to_string()),
+                        None,
+                    )),
+                },
+            };
         }
     }
 
@@ -337,6 +594,86 @@ impl Callable<RtValErr> for Function {
     }
 }
 
+// ------------
+//   Map keys
+// ------------
+// Values that can be hashed. Reals aren't, NaN is never equal to itself
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Int(i64),
+    Str(EcoString),
+    Bool(bool),
+}
+
+impl TryFrom<&RtVal> for HashKey {
+    type Error = RtValErr;
+
+    fn try_from(value: &RtVal) -> Result<Self, Self::Error> {
+        match value {
+            RtVal::IntVal(i) => Ok(HashKey::Int(i.borrow().value)),
+            RtVal::StrVal(s) => Ok(HashKey::Str(s.borrow().value.clone())),
+            RtVal::BoolVal(b) => Ok(HashKey::Bool(b.borrow().value)),
+            _ => Err(RtValErr::UnhashableKey(value.get_type_name().to_string())),
+        }
+    }
+}
+
+impl From<&HashKey> for RtVal {
+    fn from(key: &HashKey) -> Self {
+        match key {
+            HashKey::Int(i) => (*i).into(),
+            HashKey::Str(s) => s.clone().into(),
+            HashKey::Bool(b) => (*b).into(),
+        }
+    }
+}
+
+// Keys are checked when given, an unhashable one is an error. Entries
+// are kept in insertion order, so that iterating and printing a map
+// don't depend on the hashing
+#[derive(Debug, Default, Clone)]
+pub struct RtMap {
+    entries: Vec<(HashKey, RtVal)>,
+    // Position of each key in the entries
+    indexes: HashMap<HashKey, usize>,
+}
+
+impl RtMap {
+    // Returns the previous value of the key, if any. Updating a key
+    // keeps its place
+    pub fn insert(&mut self, key: &RtVal, value: RtVal) -> Result<Option<RtVal>, RtValErr> {
+        let key: HashKey = key.try_into()?;
+
+        match self.indexes.get(&key) {
+            Some(idx) => Ok(Some(std::mem::replace(&mut self.entries[*idx].1, value))),
+            None => {
+                self.indexes.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn get(&self, key: &RtVal) -> Result<Option<RtVal>, RtValErr> {
+        Ok(self
+            .indexes
+            .get(&key.try_into()?)
+            .map(|idx| self.entries[*idx].1.clone()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&HashKey, &RtVal)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 // --------
 //   Into
 // --------
@@ -368,25 +705,260 @@ impl From<String> for RtVal {
     }
 }
 
+impl From<&str> for RtVal {
+    fn from(value: &str) -> Self {
+        RtVal::StrVal(Rc::new(RefCell::new(Str {
+            value: value.into(),
+        })))
+    }
+}
+
 impl From<bool> for RtVal {
     fn from(value: bool) -> Self {
         RtVal::BoolVal(Rc::new(RefCell::new(Bool { value })))
     }
 }
 
+// --------
+//   From
+// --------
+// Types must match exactly, an int isn't converted to a real
+impl RtVal {
+    fn wrong_type(&self, expected: &str) -> RtValErr {
+        RtValErr::WrongType(expected.into(), self.get_type_name().to_string())
+    }
+}
+
+impl TryFrom<RtVal> for i64 {
+    type Error = RtValErr;
+
+    fn try_from(value: RtVal) -> Result<Self, Self::Error> {
+        match &value {
+            RtVal::IntVal(i) => Ok(i.borrow().value),
+            v => Err(v.wrong_type("int")),
+        }
+    }
+}
+
+impl TryFrom<RtVal> for f64 {
+    type Error = RtValErr;
+
+    fn try_from(value: RtVal) -> Result<Self, Self::Error> {
+        match &value {
+            RtVal::RealVal(r) => Ok(r.borrow().value),
+            v => Err(v.wrong_type("real")),
+        }
+    }
+}
+
+impl TryFrom<RtVal> for bool {
+    type Error = RtValErr;
+
+    fn try_from(value: RtVal) -> Result<Self, Self::Error> {
+        match &value {
+            RtVal::BoolVal(b) => Ok(b.borrow().value),
+            v => Err(v.wrong_type("bool")),
+        }
+    }
+}
+
+impl TryFrom<RtVal> for String {
+    type Error = RtValErr;
+
+    fn try_from(value: RtVal) -> Result<Self, Self::Error> {
+        match &value {
+            RtVal::StrVal(s) => Ok(s.borrow().value.to_string()),
+            v => Err(v.wrong_type("str")),
+        }
+    }
+}
+
 // -----------
 //   Display
 // -----------
+impl RtVal {
+    // How the value is shown inside a collection. Strings are quoted
+    // there, to tell "1" from 1
+    pub fn repr(&self) -> String {
+        match self {
+            RtVal::StrVal(s) => format!("\"{}\"", s.borrow().value.escape_debug()),
+            _ => self.to_string(),
+        }
+    }
+}
+
 impl Display for RtVal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
             RtVal::IntVal(i) => write!(f, "{}", i.borrow().value),
-            RtVal::RealVal(r) => write!(f, "{}", r.borrow().value),
+            RtVal::RealVal(r) => write!(f, "{}", format_real(r.borrow().value)),
             RtVal::BoolVal(b) => write!(f, "{}", b.borrow().value),
-            RtVal::StrVal(s) => write!(f, "\"{}\"", s.borrow().value),
+            RtVal::StrVal(s) => write!(f, "{}", s.borrow().value),
             RtVal::FuncVal(func) => write!(f, "<fn {}>", func.name),
             RtVal::NativeFnVal(func) => write!(f, "{}", func),
+            RtVal::EnumVal(e) => write!(f, "<enum {}>", e.name),
+            RtVal::EnumVariantVal(v) => write!(f, "{}.{}", v.enum_name, v.name),
             RtVal::Null => write!(f, "null"),
         }
     }
 }
+
+// Two maps with the same entries are equal whatever their order
+impl PartialEq for RtMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(k, v)| {
+                other.indexes.get(k).is_some_and(|idx| other.entries[*idx].1 == *v)
+            })
+    }
+}
+
+impl Display for RtMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries: Vec<String> = self
+            .iter()
+            .map(|(k, v)| format!("{}: {}", RtVal::from(k).repr(), v.repr()))
+            .collect();
+
+        write!(f, "{{{}}}", entries.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecow::EcoString;
+
+    use super::{HashKey, RtMap, RtVal, RtValErr};
+    use crate::utils::lex_parse_interp;
+
+    #[test]
+    fn map_keys() {
+        let mut map = RtMap::default();
+        let str_key: RtVal = EcoString::from("a").into();
+
+        assert_eq!(map.insert(&1.into(), 10.into()).unwrap(), None);
+        assert_eq!(map.insert(&str_key, 20.into()).unwrap(), None);
+        assert_eq!(map.insert(&true.into(), 30.into()).unwrap(), None);
+        assert_eq!(map.len(), 3);
+
+        // Keys are compared by value, not by reference
+        assert_eq!(map.get(&1.into()).unwrap(), Some(10.into()));
+        assert_eq!(map.get(&EcoString::from("a").into()).unwrap(), Some(20.into()));
+        assert_eq!(map.get(&2.into()).unwrap(), None);
+        assert_eq!(map.insert(&1.into(), 11.into()).unwrap(), Some(10.into()));
+        assert_eq!(map.len(), 3);
+
+        let key = HashKey::try_from(&str_key).unwrap();
+        assert_eq!(RtVal::from(&key), str_key);
+    }
+
+    #[test]
+    fn map_insertion_order() {
+        let mut map = RtMap::default();
+        let keys: Vec<RtVal> = vec![
+            EcoString::from("z").into(),
+            3.into(),
+            EcoString::from("a").into(),
+            false.into(),
+            (-1).into(),
+        ];
+
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key, (i as i64).into()).unwrap();
+        }
+        // An update keeps the place of the key
+        map.insert(&3.into(), 10.into()).unwrap();
+
+        assert_eq!(map.to_string(), "{\"z\": 0, 3: 10, \"a\": 2, false: 3, -1: 4}");
+
+        let iterated: Vec<RtVal> = map.iter().map(|(k, _)| RtVal::from(k)).collect();
+        assert_eq!(iterated, keys);
+
+        // Equality doesn't depend on the order
+        let mut reversed = RtMap::default();
+        for (k, v) in map.iter().collect::<Vec<_>>().into_iter().rev() {
+            reversed.insert(&RtVal::from(k), v.clone()).unwrap();
+        }
+        assert_eq!(reversed, map);
+        assert_ne!(reversed.to_string(), map.to_string());
+    }
+
+    #[test]
+    fn unhashable_keys() {
+        let mut map = RtMap::default();
+        let func = lex_parse_interp("fn f() {}\nf").unwrap();
+
+        for key in [1.5.into(), func, RtVal::Null] {
+            let name = key.get_type_name().to_string();
+
+            assert!(matches!(
+                map.insert(&key, 1.into()).err().unwrap(),
+                RtValErr::UnhashableKey(n) if n == name
+            ));
+            assert!(map.get(&key).is_err());
+        }
+
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn display_and_repr() {
+        let s: RtVal = EcoString::from("a \"b\"").into();
+
+        // Bare at the top level, quoted inside a collection
+        assert_eq!(s.to_string(), "a \"b\"");
+        assert_eq!(s.repr(), "\"a \\\"b\\\"\"");
+        assert_eq!(RtVal::from(1).repr(), "1");
+        assert_eq!(RtVal::Null.repr(), "null");
+
+        let mut map = RtMap::default();
+        assert_eq!(map.to_string(), "{}");
+
+        map.insert(&EcoString::from("1").into(), s).unwrap();
+        map.insert(&1.into(), 2.5.into()).unwrap();
+        map.insert(&true.into(), EcoString::from("x").into()).unwrap();
+        assert_eq!(map.to_string(), "{\"1\": \"a \\\"b\\\"\", 1: 2.5, true: \"x\"}");
+    }
+
+    #[test]
+    fn real_round_trip() {
+        let reals = [
+            0.1, 0.1 + 0.2, 1.0 / 3.0, 2.0, -7.5, 1e300, 1.5e-7, 123456789.125, f64::MAX,
+            f64::MIN_POSITIVE, f64::EPSILON,
+        ];
+
+        for r in reals {
+            let text = RtVal::from(r).to_string();
+
+            assert_eq!(text.parse::<f64>().unwrap(), r, "{}", text);
+            // Read back by the language, it is still the same real
+            assert_eq!(lex_parse_interp(&text).unwrap(), r.into(), "{}", text);
+        }
+
+        assert_eq!(RtVal::from(0.1 + 0.2).to_string(), "0.30000000000000004");
+        assert_eq!(RtVal::from(2.0).to_string(), "2.0");
+        assert_eq!(RtVal::from(1e300).to_string(), "1e300");
+        assert_eq!(RtVal::from(1.5e-7).to_string(), "1.5e-7");
+    }
+
+    #[test]
+    fn rust_conversions() {
+        assert_eq!(i64::try_from(RtVal::from(-42)).unwrap(), -42);
+        assert_eq!(f64::try_from(RtVal::from(0.5)).unwrap(), 0.5);
+        assert!(bool::try_from(RtVal::from(true)).unwrap());
+        assert_eq!(String::try_from(RtVal::from("héllo")).unwrap(), "héllo");
+        assert_eq!(RtVal::from("a"), EcoString::from("a").into());
+
+        // Types aren't converted into each other
+        assert!(matches!(
+            f64::try_from(RtVal::from(1)),
+            Err(RtValErr::WrongType(expected, found)) if expected == "real" && found == "int"
+        ));
+        assert_eq!(
+            i64::try_from(RtVal::Null).unwrap_err().to_string(),
+            "expected a value of type 'int', found: null"
+        );
+        assert!(bool::try_from(RtVal::from("true")).is_err());
+        assert!(String::try_from(RtVal::from(1.5)).is_err());
+    }
+}
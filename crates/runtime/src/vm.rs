@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use colored::*;
+use ecow::EcoString;
+use thiserror::Error;
+use tools::results::{PhyReport, PhyResult};
+
+use crate::compiler::{Chunk, Op};
+use crate::interpreter::{coerce_bools, print_text};
+use crate::values::{OverflowMode, RtVal};
+
+// ----------------
+// Error managment
+// ----------------
+#[derive(Debug, Error, PartialEq)]
+pub enum VmErr {
+    #[error("{0}")]
+    OperationEvaluation(String),
+
+    #[error("can't use '!' token on anything other than a bool value")]
+    BangOpOnNonBool,
+
+    #[error("can't use '-' token on anything other than an int or a real value")]
+    NegateNonNumeric,
+
+    #[error("uninitialized variable")]
+    UninitializedValue,
+
+    #[error("condition is not a boolean")]
+    NonBoolCond,
+
+    #[error("variable '{0}' is already declared")]
+    AlreadyDeclaredVar(String),
+
+    #[error("undeclared variable '{0}'")]
+    UndeclaredVar(String),
+
+    #[error("can't write output: {0}")]
+    Output(String),
+}
+
+impl PhyReport for VmErr {
+    fn get_err_msg(&self) -> String {
+        format!("{} {}", "VM error:".red(), self)
+    }
+}
+
+pub(crate) type PhyResVm = PhyResult<VmErr>;
+
+// Executes a compiled chunk and returns the value of the last expression
+// statement, like the tree-walking interpreter does
+pub fn run(chunk: &Chunk) -> Result<RtVal, PhyResVm> {
+    let mut vm = Vm::default();
+    vm.run(chunk)
+}
+
+// -----------
+//  Execution
+// -----------
+// The settings are the interpreter's ones, so that both run a program
+// the same way
+pub struct Vm {
+    stack: Vec<RtVal>,
+    globals: HashMap<EcoString, RtVal>,
+    // Where 'print' writes
+    pub out: Box<dyn Write>,
+    pub overflow_mode: OverflowMode,
+    // Decimals of the printed reals, the shortest exact form if none
+    pub real_precision: Option<usize>,
+    // Bools are used as ints, 1 and 0, in arithmetic and comparisons
+    pub bool_as_int: bool,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+}
+
+impl Vm {
+    pub fn with_output(out: Box<dyn Write>) -> Self {
+        Self {
+            stack: vec![],
+            globals: HashMap::new(),
+            out,
+            overflow_mode: OverflowMode::default(),
+            real_precision: None,
+            bool_as_int: false,
+        }
+    }
+
+
+    pub fn run(&mut self, chunk: &Chunk) -> Result<RtVal, PhyResVm> {
+        let mut result = RtVal::new_null();
+        let mut ip = 0;
+
+        while ip < chunk.code.len() {
+            let loc = &chunk.locs[ip];
+            let err = |e: VmErr| PhyResult::new(e, Some(loc.clone()));
+            let op = &chunk.code[ip];
+            ip += 1;
+
+            match op {
+                Op::Constant(idx) => self.stack.push(chunk.constants[*idx].clone()),
+                Op::True => self.stack.push(true.into()),
+                Op::False => self.stack.push(false.into()),
+                Op::Null => self.stack.push(RtVal::new_null()),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::PopResult => result = self.pop(),
+                // Values are shared with the constant pool and variables,
+                // so a new one is created instead of negating in place
+                Op::Negate => {
+                    let value: RtVal = match self.pop() {
+                        RtVal::IntVal(i) => i.borrow().value.into(),
+                        RtVal::RealVal(r) => r.borrow().value.into(),
+                        _ => return Err(err(VmErr::NegateNonNumeric)),
+                    };
+                    value
+                        .negate_with(self.overflow_mode)
+                        .map_err(|e| err(VmErr::OperationEvaluation(e.to_string())))?;
+                    self.stack.push(value);
+                }
+                Op::Not => {
+                    let value = match self.pop() {
+                        RtVal::BoolVal(b) => (!b.borrow().value).into(),
+                        _ => return Err(err(VmErr::BangOpOnNonBool)),
+                    };
+                    self.stack.push(value);
+                }
+                Op::Add => self.binary("+").map_err(err)?,
+                Op::Sub => self.binary("-").map_err(err)?,
+                Op::Mul => self.binary("*").map_err(err)?,
+                Op::Div => self.binary("/").map_err(err)?,
+                Op::Mod => self.binary("%").map_err(err)?,
+                Op::Equal => self.binary("==").map_err(err)?,
+                Op::NotEqual => self.binary("!=").map_err(err)?,
+                Op::Less => self.binary("<").map_err(err)?,
+                Op::LessEqual => self.binary("<=").map_err(err)?,
+                Op::Greater => self.binary(">").map_err(err)?,
+                Op::GreaterEqual => self.binary(">=").map_err(err)?,
+                Op::TypeCheck(idx) => {
+                    let value = self.pop();
+                    self.stack.push((value.get_type_name() == chunk.names[*idx]).into());
+                }
+                Op::DefineGlobal(idx) => {
+                    let name = &chunk.names[*idx];
+                    let value = self.pop();
+
+                    if self.globals.insert(name.clone(), value).is_some() {
+                        return Err(err(VmErr::AlreadyDeclaredVar(name.to_string())));
+                    }
+                }
+                Op::GetGlobal(idx) => {
+                    let name = &chunk.names[*idx];
+
+                    match self.globals.get(name) {
+                        Some(v) => self.stack.push(v.clone()),
+                        None => return Err(err(VmErr::UndeclaredVar(name.to_string()))),
+                    }
+                }
+                Op::SetGlobal(idx) => {
+                    let name = &chunk.names[*idx];
+                    let value = self.pop();
+
+                    match self.globals.get_mut(name) {
+                        Some(v) => *v = value,
+                        None => return Err(err(VmErr::UndeclaredVar(name.to_string()))),
+                    }
+                }
+                Op::GetLocal(slot) => self.stack.push(self.stack[*slot].clone()),
+                Op::SetLocal(slot) => {
+                    let value = self.pop();
+                    self.stack[*slot] = value;
+                }
+                Op::Jump(target) => ip = *target,
+                Op::JumpIfFalse(target) => {
+                    if !self.peek_bool().map_err(err)? {
+                        ip = *target;
+                    }
+                }
+                Op::JumpIfTrue(target) => {
+                    if self.peek_bool().map_err(err)? {
+                        ip = *target;
+                    }
+                }
+                Op::JumpIfNotNull(target) => {
+                    if self.stack.last() != Some(&RtVal::Null) {
+                        ip = *target;
+                    }
+                }
+                Op::Print => {
+                    let text = print_text(&self.pop(), self.real_precision);
+                    writeln!(self.out, "{}", text).map_err(|e| err(VmErr::Output(e.to_string())))?;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    // The compiler always emits balanced code, an empty stack is a bug
+    fn pop(&mut self) -> RtVal {
+        self.stack.pop().expect("VM stack underflow")
+    }
+
+    fn peek_bool(&self) -> Result<bool, VmErr> {
+        match self.stack.last() {
+            Some(RtVal::BoolVal(b)) => Ok(b.borrow().value),
+            _ => Err(VmErr::NonBoolCond),
+        }
+    }
+
+    fn binary(&mut self, operator: &str) -> Result<(), VmErr> {
+        let rhs = self.pop();
+        let lhs = self.pop();
+
+        if lhs == RtVal::Null || rhs == RtVal::Null {
+            return Err(VmErr::UninitializedValue);
+        }
+
+        let (lhs, rhs) = match self.bool_as_int {
+            true => coerce_bools(lhs, rhs),
+            false => (lhs, rhs),
+        };
+
+        let res = lhs
+            .operate_with(&rhs, operator, self.overflow_mode)
+            .map_err(|e| VmErr::OperationEvaluation(e.to_string()))?;
+        self.stack.push(res);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecow::EcoString;
+    use frontend::parser::utils::lex_and_parse;
+
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{run, Vm, VmErr};
+    use crate::interpreter::Interpreter;
+    use crate::utils::{lex_parse_interp, Sink};
+    use crate::{compiler::compile, values::{OverflowMode, RtVal}};
+
+    fn compile_and_run(code: &str) -> Result<RtVal, VmErr> {
+        let nodes = lex_and_parse(code).unwrap();
+        let chunk = compile(&nodes).unwrap();
+        run(&chunk).map_err(|e| e.err)
+    }
+
+    #[test]
+    fn run_expr() {
+        assert_eq!(compile_and_run("1 + 2 * 3").unwrap(), 7.into());
+        assert_eq!(compile_and_run("-(6.5 - 4)").unwrap(), (-2.5).into());
+        assert_eq!(compile_and_run("!(1 < 2) or 3 >= 3").unwrap(), true.into());
+        assert_eq!(compile_and_run("null ?? \"a\"").unwrap(), EcoString::from("a").into());
+        assert_eq!(compile_and_run("2 is int").unwrap(), true.into());
+        assert_eq!(compile_and_run("if 1 > 2 { 1 } else { 2 }").unwrap(), 2.into());
+    }
+
+    #[test]
+    fn run_stmts() {
+        let code = "
+var sum = 0
+var i = 0
+while i < 10 {
+    var double = i * 2
+    if double % 3 == 0 {
+        sum = sum + double
+    }
+    i = i + 1
+}
+sum
+";
+        assert_eq!(compile_and_run(code).unwrap(), 36.into());
+        assert_eq!(compile_and_run(code).unwrap(), lex_parse_interp(code).unwrap());
+
+        let code = "
+var a = 1
+{
+    var a = 2
+    a = a + 1
+}
+a
+";
+        assert_eq!(compile_and_run(code).unwrap(), 1.into());
+
+        for code in ["5 % 2", "5.5 % 2", "-5 % 3", "5 % -3", "-5.5 % 2"] {
+            assert_eq!(compile_and_run(code).unwrap(), lex_parse_interp(code).unwrap(), "{}", code);
+        }
+
+        // Chained assignments go from right to left
+        let code = "
+var a = 0
+var b = 0
+{
+    var c = 0
+    a = c = b = 5
+    c
+}
+";
+        assert_eq!(compile_and_run(code).unwrap(), 5.into());
+        assert_eq!(compile_and_run("var a = 0\nvar b = 0\na = b = 5\na + b").unwrap(), 10.into());
+
+        // Nothing is bound to '_', the stack stays balanced
+        let code = "var a = 1\n{\n    var _ = 2\n    var b = 3\n    a = _ = b\n}\na";
+        assert_eq!(compile_and_run(code).unwrap(), 3.into());
+    }
+
+    #[test]
+    fn run_errors() {
+        assert_eq!(compile_and_run("-true").err().unwrap(), VmErr::NegateNonNumeric);
+        assert_eq!(compile_and_run("if 1 {}").err().unwrap(), VmErr::NonBoolCond);
+        assert_eq!(
+            compile_and_run("a + 1").err().unwrap(),
+            VmErr::UndeclaredVar("a".into())
+        );
+        assert_eq!(
+            compile_and_run("var a\na + 1").err().unwrap(),
+            VmErr::UninitializedValue
+        );
+    }
+
+    #[test]
+    fn interpreter_settings() {
+        // Same output and values as the interpreter with the same settings
+        let run_both = |code: &str, set: &dyn Fn(&mut Vm, &mut Interpreter)| {
+            let nodes = lex_and_parse(code).unwrap();
+            let vm_buf = Rc::new(RefCell::new(vec![]));
+            let interp_buf = Rc::new(RefCell::new(vec![]));
+
+            let mut vm = Vm::with_output(Box::new(Sink(vm_buf.clone())));
+            let mut interp = Interpreter::with_output(Box::new(Sink(interp_buf.clone())));
+            set(&mut vm, &mut interp);
+
+            let vm_res = vm.run(&compile(&nodes).unwrap()).map_err(|e| e.err.to_string());
+            let interp_res = interp.interpret(&nodes).map_err(|e| e.err.to_string());
+            assert_eq!(vm_buf.borrow().as_slice(), interp_buf.borrow().as_slice(), "{}", code);
+
+            let output = String::from_utf8(vm_buf.borrow().clone()).unwrap();
+            (vm_res, interp_res.is_ok(), output)
+        };
+        let defaults = |_: &mut Vm, _: &mut Interpreter| {};
+
+        let (res, _, output) = run_both("print 1.5\nprint \"a\"\nprint 2 * 3", &defaults);
+        assert_eq!(res.unwrap(), RtVal::Null);
+        assert_eq!(output, "1.5\na\n6\n");
+
+        let precision = |vm: &mut Vm, interp: &mut Interpreter| {
+            vm.real_precision = Some(2);
+            interp.real_precision = Some(2);
+        };
+        assert_eq!(run_both("print 0.1 + 0.2\nprint 7", &precision).2, "0.30\n7\n");
+
+        let wrapping = |vm: &mut Vm, interp: &mut Interpreter| {
+            vm.overflow_mode = OverflowMode::Wrapping;
+            interp.overflow_mode = OverflowMode::Wrapping;
+        };
+        let max = "9223372036854775807";
+        for (code, op) in [(format!("{} + 1", max), '+'), (format!("-(-{} - 1)", max), '-')] {
+            let (res, interp_ok, _) = run_both(&code, &defaults);
+            assert_eq!(res.unwrap_err(), format!("integer overflow in '{}' operation", op));
+            assert!(!interp_ok);
+
+            let (res, _, _) = run_both(&code, &wrapping);
+            assert_eq!(res.unwrap(), i64::MIN.into());
+        }
+
+        let bools = |vm: &mut Vm, interp: &mut Interpreter| {
+            vm.bool_as_int = true;
+            interp.bool_as_int = true;
+        };
+        assert_eq!(run_both("true + 1", &bools).0.unwrap(), 2.into());
+        assert!(run_both("true + 1", &defaults).0.is_err());
+    }
+}
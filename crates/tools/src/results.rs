@@ -1,6 +1,13 @@
-use std::collections::VecDeque;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write,
+    path::PathBuf,
+};
 use colored::*;
 
+// Columns between two tab stops when showing code in reports
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct Loc {
@@ -15,6 +22,73 @@ impl Loc {
      pub fn get_len(&self) -> usize {
         self.end - self.start
     }
+
+    // Smallest location covering both
+    pub fn merge(&self, other: &Loc) -> Loc {
+        Loc::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+// Set by a '#line <n> "<file>"' directive in generated code: the source
+// line following the directive is reported as line 'line' of 'file'.
+// Without a file, the one of the previous directive is kept
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineDirective {
+    // Line of the directive itself in the source, starting at 1
+    pub src_line: usize,
+    pub line: usize,
+    pub file: Option<String>,
+}
+
+// Gives the file and line to report for a line of the source
+pub fn map_line(directives: &[LineDirective], file_name: &str, src_line: usize) -> (String, usize) {
+    let mut file = file_name;
+    let mut line = src_line;
+
+    for d in directives.iter().take_while(|d| d.src_line < src_line) {
+        if let Some(f) = &d.file {
+            file = f;
+        }
+
+        line = d.line + src_line - d.src_line - 1;
+    }
+
+    (file.to_string(), line)
+}
+
+// Lines of the code, ended like in the lexer: by a '\n' or a lone
+// '\r'. Each line break is one char, the '\r' of a '\r\n' stays at the
+// end of its line
+pub fn split_lines(code: &str) -> Vec<&str> {
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut chars = code.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let lone_cr = c == '\r' && chars.peek().map(|(_, next)| *next) != Some('\n');
+
+        if c == '\n' || lone_cr {
+            lines.push(&code[start..i]);
+            start = i + 1;
+        }
+    }
+
+    lines.push(&code[start..]);
+    lines
+}
+
+// Line, starting at 1, of the char at 'pos'. Past the end, the last one
+pub fn line_of(code: &str, pos: usize) -> usize {
+    let lines = split_lines(code);
+    let mut offset = 0;
+
+    lines
+        .iter()
+        .position(|line| {
+            offset += line.chars().count() + 1;
+            pos < offset
+        })
+        .map_or(lines.len(), |i| i + 1)
 }
 
 pub trait PhyReport {
@@ -38,39 +112,82 @@ impl<'a, T: PhyReport> PhyResult<T> {
         PhyResult { err, loc }
     }
 
-    pub fn report(&self, file_name: &String, code: &str) {
+    pub fn report(&self, file_name: &str, code: &str) {
+        self.report_with_directives(file_name, code, &[])
+    }
+
+    // Lines and file are the ones given by the '#line' directives of the
+    // code, if any
+    pub fn report_with_directives(&self, file_name: &str, code: &str, directives: &[LineDirective]) {
+        print!("{}", self.render(file_name, code, directives, DEFAULT_TAB_WIDTH));
+    }
+
+    // Text of the report. Tabs of the code are expanded so that the
+    // decorators stay under the characters they point to
+    pub fn render(
+        &self,
+        file_name: &str,
+        code: &str,
+        directives: &[LineDirective],
+        tab_width: usize,
+    ) -> String {
         // Error msg
-        println!("{}", self.err.get_err_msg());
+        let mut out = format!("{}\n", self.err.get_err_msg());
 
         // Additional infos on location
         if let Some(loc) = &self.loc {
             let cx = self.get_context(code, loc);
-            let deco = self.get_decorators(&cx, loc);
+            let deco = self.get_decorators(&cx, loc, tab_width);
+            let (file, line) = map_line(directives, file_name, cx.line);
 
-            println!("  {} {} [line {}]", "-->".cyan(), file_name, cx.line);
+            let _ = writeln!(out, "  {} {} [line {}]", "-->".cyan(), file, line);
 
             for (i, line) in cx.snippets {
+                let (_, i) = map_line(directives, file_name, i);
+
                 // If this line + 1 is % 10, the next one will be one digit
                 // longer, so we add a space before the smallest
                 let add_space = if (i + 1) % 10 == 0 { " " } else { "" };
 
-                println!(" {} {}", format!("{}{} |", add_space, i).cyan(), line);
+                let _ = writeln!(
+                    out,
+                    " {} {}",
+                    format!("{}{} |", add_space, i).cyan(),
+                    expand_tabs(line, tab_width)
+                );
             }
 
             // Here, 4 is for space at the beginning and between line nb and '|' and space again
-            let margin = cx.line.to_string().len() + 4;
-            println!("{}{}", " ".repeat(margin), deco.red());
+            let margin = line.to_string().len() + 4;
+            let _ = writeln!(out, "{}{}", " ".repeat(margin), deco.red());
         }
+
+        out
     }
 
-    fn get_context(&'a self, code: &'a str, loc: &Loc) -> ReportContext {
+    // File and line where the error is reported
+    pub fn get_report_location(
+        &self,
+        file_name: &str,
+        code: &str,
+        directives: &[LineDirective],
+    ) -> Option<(String, usize)> {
+        let loc = self.loc.as_ref()?;
+        let cx = self.get_context(code, loc);
+
+        Some(map_line(directives, file_name, cx.line))
+    }
+
+    fn get_context(&'a self, code: &'a str, loc: &Loc) -> ReportContext<'a> {
         let mut offset: usize = 0;
         let mut lines: VecDeque<(usize, &'a str)> = VecDeque::new();
 
-        for (i, line) in code.split('\n').enumerate() {
+        // Locations count chars, not bytes
+        for (i, line) in split_lines(code).into_iter().enumerate() {
             lines.push_back((i + 1, line));
+            let len = line.chars().count();
 
-            if loc.start >= offset && loc.start < offset + line.len() {
+            if loc.start >= offset && loc.start < offset + len {
                 return ReportContext {
                     line: i + 1,
                     snippets: lines,
@@ -81,8 +198,8 @@ impl<'a, T: PhyReport> PhyResult<T> {
                     lines.pop_front();
                 }
 
-                // + 1 because we don't have '\n' anymore
-                offset += line.len() + 1;
+                // + 1 because we don't have the line break anymore
+                offset += len + 1;
             }
         }
 
@@ -92,11 +209,160 @@ impl<'a, T: PhyReport> PhyResult<T> {
         )
     }
 
-    fn get_decorators(&self, cx: &ReportContext, loc: &Loc) -> String {
-        let mut decorators = " ".repeat(loc.start - cx.offset);
-        let indicators = "^".repeat(loc.get_len());
+    fn get_decorators(&self, cx: &ReportContext, loc: &Loc, tab_width: usize) -> String {
+        let line: Vec<char> = cx.snippets.back().map(|(_, l)| l.chars().collect()).unwrap_or_default();
+
+        // Column where the n-th char of the line is shown. A location
+        // going past the line goes on as if there were no tabs
+        let column = |n: usize| {
+            let before: String = line[..n.min(line.len())].iter().collect();
+            expand_tabs(&before, tab_width).chars().count() + n.saturating_sub(line.len())
+        };
+
+        let start = column(loc.start - cx.offset);
+        let end = column(loc.end - cx.offset);
 
-        decorators.push_str(indicators.as_str());
+        let mut decorators = " ".repeat(start);
+        decorators.push_str("^".repeat(end - start).as_str());
         decorators
     }
 }
+
+// Reports of several files, each file under its own header. Reports
+// of a file without source only have their message
+pub fn render_all<E: PhyReport>(
+    diagnostics: &[(PathBuf, Vec<PhyResult<E>>)],
+    sources: &HashMap<PathBuf, String>,
+) -> String {
+    let mut out = String::new();
+
+    for (path, results) in diagnostics.iter().filter(|(_, r)| !r.is_empty()) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+
+        let file = path.display().to_string();
+        let _ = writeln!(out, "{} {}", "==>".cyan(), file.bold());
+
+        for res in results {
+            match sources.get(path) {
+                Some(code) => out.push_str(&res.render(&file, code, &[], DEFAULT_TAB_WIDTH)),
+                None => {
+                    let _ = writeln!(out, "{}", res.err.get_err_msg());
+                }
+            }
+        }
+    }
+
+    out
+}
+
+// Replaces each tab by spaces up to the next tab stop
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut expanded = String::new();
+
+    for c in line.chars() {
+        match c {
+            '\t' => {
+                let spaces = tab_width - expanded.chars().count() % tab_width;
+                expanded.push_str(&" ".repeat(spaces));
+            }
+            c => expanded.push(c),
+        }
+    }
+
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::{line_of, render_all, PhyReport, PhyResult, Loc};
+
+    struct TestErr;
+
+    impl PhyReport for TestErr {
+        fn get_err_msg(&self) -> String {
+            "error".into()
+        }
+    }
+
+    #[test]
+    fn tabs_in_report() {
+        colored::control::set_override(false);
+
+        let code = "fn f() {\n\tvar a = b\n}";
+        let res = PhyResult::new(TestErr, Some(Loc::new(18, 19)));
+
+        // The 'b' is in column 12 once the tab is expanded
+        let report = res.render("test.rz", code, &[], 4);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[3], " 2 |     var a = b");
+        assert_eq!(lines[4].find('^'), Some(17));
+        assert_eq!(lines[3].find('b'), Some(17));
+
+        let report = res.render("test.rz", code, &[], 8);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[4].find('^'), lines[3].find('b'));
+        assert_eq!(lines[4].find('^'), Some(21));
+
+        // A tab inside the location widens the decorators
+        let code = "a\t=\tb";
+        let res = PhyResult::new(TestErr, Some(Loc::new(0, 5)));
+        let report = res.render("test.rz", code, &[], 4);
+        assert_eq!(report.lines().last().unwrap(), "     ^^^^^^^^^");
+    }
+
+    #[test]
+    fn cr_line_breaks() {
+        colored::control::set_override(false);
+
+        // Line breaks are the lexer's: '\n', '\r\n' or a lone '\r'
+        let code = "var a = 1\rvar b = 2\rprint c";
+        let res = PhyResult::new(TestErr, Some(Loc::new(26, 27)));
+        let report = res.render("test.rz", code, &[], 4);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[1], "  --> test.rz [line 3]");
+        assert_eq!(lines[2], " 2 | var b = 2");
+        assert_eq!(lines[3], " 3 | print c");
+        assert_eq!(lines[4].find('^'), lines[3].find('c'));
+
+        let code = "a\r\nb\rc\nd";
+        let lines: Vec<usize> = (0..code.chars().count()).map(|i| line_of(code, i)).collect();
+        assert_eq!(lines, [1, 1, 1, 2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn render_by_file() {
+        colored::control::set_override(false);
+
+        let (main, lib, gen) = (PathBuf::from("main.rz"), PathBuf::from("lib.rz"), PathBuf::from("gen.rz"));
+        let sources = HashMap::from([
+            (main.clone(), "var a = 1\nprint b".to_string()),
+            (lib.clone(), "fn f() {}".to_string()),
+        ]);
+        let diagnostics = vec![
+            (main.clone(), vec![PhyResult::new(TestErr, Some(Loc::new(16, 17))), PhyResult::new(TestErr, None)]),
+            (lib.clone(), vec![PhyResult::new(TestErr, Some(Loc::new(3, 4)))]),
+            (gen.clone(), vec![PhyResult::new(TestErr, Some(Loc::new(0, 1)))]),
+            (PathBuf::from("ok.rz"), vec![]),
+        ];
+
+        let report = render_all(&diagnostics, &sources);
+        let lines: Vec<&str> = report.lines().collect();
+
+        let main_header = lines.iter().position(|l| *l == "==> main.rz").unwrap();
+        let lib_header = lines.iter().position(|l| *l == "==> lib.rz").unwrap();
+        assert!(main_header < lib_header);
+        assert!(lines.contains(&"==> gen.rz"));
+        assert!(!report.contains("ok.rz"));
+
+        // Snippets come from the file's own source
+        assert!(lines[main_header..lib_header].contains(&" 2 | print b"));
+        assert!(lines[main_header..lib_header].contains(&"  --> main.rz [line 2]"));
+        assert!(lines[lib_header..].contains(&" 1 | fn f() {}"));
+        assert_eq!(report.matches("error").count(), 4);
+    }
+}